@@ -0,0 +1,524 @@
+use std::{cell::Cell, io::{Read, Write}, path::{Component, PathBuf}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use rialight_util::AnyStringType;
+use super::{File, IoError, IoErrorKind, DirectoryWalkOptions};
+
+/// Compression method of a zip archive entry.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ZipCompression {
+    Stored,
+    Deflated,
+    /// A method that cannot be read by this module.
+    Unsupported,
+}
+
+/// Options for entries added by `ZipArchiveWriter`.
+pub struct ZipEntryOptions {
+    m_compression: Cell<ZipCompression>,
+    m_compression_level: Cell<Option<i32>>,
+    m_modification_date: Cell<Option<SystemTime>>,
+    m_unix_mode: Cell<Option<u32>>,
+}
+
+impl Clone for ZipEntryOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_compression: self.m_compression.clone(),
+            m_compression_level: self.m_compression_level.clone(),
+            m_modification_date: self.m_modification_date.clone(),
+            m_unix_mode: self.m_unix_mode.clone(),
+        }
+    }
+}
+
+impl ZipEntryOptions {
+    pub fn new() -> Self {
+        ZipEntryOptions {
+            m_compression: Cell::new(ZipCompression::Deflated),
+            m_compression_level: Cell::new(None),
+            m_modification_date: Cell::new(None),
+            m_unix_mode: Cell::new(None),
+        }
+    }
+
+    /// Compression method, which defaults to `Deflated`.
+    pub fn compression(&self, value: ZipCompression) -> &Self {
+        self.m_compression.set(value);
+        self
+    }
+
+    /// Deflate level from 0 to 9. Defaults to 6.
+    pub fn compression_level(&self, value: i32) -> &Self {
+        self.m_compression_level.set(Some(value));
+        self
+    }
+
+    /// Modification date recorded for the entry. Defaults to the modification
+    /// date of the source file, or the current date for bytes.
+    pub fn modification_date(&self, value: SystemTime) -> &Self {
+        self.m_modification_date.set(Some(value));
+        self
+    }
+
+    /// Unix permissions recorded for the entry, such as `0o644`. Defaults to
+    /// the permissions of the source file on Unix, or `0o644` and `0o755` for directories.
+    pub fn unix_mode(&self, value: u32) -> &Self {
+        self.m_unix_mode.set(Some(value));
+        self
+    }
+
+    fn zip_options(&self, modification_date: Option<SystemTime>, unix_mode: Option<u32>, size: u64) -> Result<zip::write::FileOptions, IoError> {
+        let method = match self.m_compression.get() {
+            ZipCompression::Stored => zip::CompressionMethod::Stored,
+            ZipCompression::Deflated => zip::CompressionMethod::Deflated,
+            ZipCompression::Unsupported => return Err(IoError::new(IoErrorKind::InvalidInput, "unsupported zip compression")),
+        };
+        let date = self.m_modification_date.get().or(modification_date).unwrap_or_else(SystemTime::now);
+        Ok(zip::write::FileOptions::default()
+            .compression_method(method)
+            .compression_level(self.m_compression_level.get())
+            .last_modified_time(date_time_from_system_time(date))
+            .unix_permissions(self.m_unix_mode.get().or(unix_mode).unwrap_or(0o644) & 0o777)
+            // entries of 4 GiB and more need zip64 extensions
+            .large_file(size >= u32::MAX as u64))
+    }
+}
+
+impl Default for ZipEntryOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Description of an entry of a zip archive.
+#[derive(Clone, Debug)]
+pub struct ZipEntry {
+    m_name: String,
+    m_size: u64,
+    m_compressed_size: u64,
+    m_compression: ZipCompression,
+    m_directory: bool,
+    m_modification_date: Option<SystemTime>,
+    m_unix_mode: Option<u32>,
+}
+
+impl ZipEntry {
+    fn from_zip_file(file: &zip::read::ZipFile) -> Self {
+        ZipEntry {
+            m_name: file.name().to_owned(),
+            m_size: file.size(),
+            m_compressed_size: file.compressed_size(),
+            m_compression: match file.compression() {
+                zip::CompressionMethod::Stored => ZipCompression::Stored,
+                zip::CompressionMethod::Deflated => ZipCompression::Deflated,
+                _ => ZipCompression::Unsupported,
+            },
+            m_directory: file.is_dir(),
+            m_modification_date: system_time_from_date_time(file.last_modified()),
+            m_unix_mode: file.unix_mode(),
+        }
+    }
+
+    /// Name of the entry as stored, separated by `/`. Directory names end with `/`.
+    pub fn name(&self) -> String {
+        self.m_name.clone()
+    }
+
+    /// Uncompressed size in bytes.
+    pub fn size(&self) -> u64 {
+        self.m_size
+    }
+
+    /// Compressed size in bytes.
+    pub fn compressed_size(&self) -> u64 {
+        self.m_compressed_size
+    }
+
+    pub fn compression(&self) -> ZipCompression {
+        self.m_compression
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.m_directory
+    }
+
+    /// Modification date, read as UTC since zip archives do not record a time zone.
+    pub fn modification_date(&self) -> Option<SystemTime> {
+        self.m_modification_date
+    }
+
+    /// Unix permissions and file type, if the archive was created on Unix.
+    pub fn unix_mode(&self) -> Option<u32> {
+        self.m_unix_mode
+    }
+}
+
+/// A zip archive opened for reading. Zip64 archives are supported.
+pub struct ZipArchive {
+    m_archive: zip::ZipArchive<std::fs::File>,
+}
+
+impl ZipArchive {
+    /// Opens a zip archive, reading its central directory.
+    pub fn open(file: &File) -> Result<Self, IoError> {
+        let archive = zip::ZipArchive::new(std::fs::File::open(file.to_path_object())?).map_err(convert_zip_error)?;
+        Ok(ZipArchive { m_archive: archive })
+    }
+
+    /// The entries of the archive, in stored order.
+    pub fn entries(&mut self) -> Result<Vec<ZipEntry>, IoError> {
+        let mut r = vec![];
+        for i in 0..self.m_archive.len() {
+            r.push(ZipEntry::from_zip_file(&self.m_archive.by_index_raw(i).map_err(convert_zip_error)?));
+        }
+        Ok(r)
+    }
+
+    /// Opens an entry by name for reading it in parts.
+    pub fn open_entry(&mut self, name: impl AnyStringType) -> Result<ZipEntryReader<'_>, IoError> {
+        let file = self.m_archive.by_name(name.convert()).map_err(convert_zip_error)?;
+        Ok(ZipEntryReader { m_entry: ZipEntry::from_zip_file(&file), m_file: file })
+    }
+
+    /// Reads the contents of an entry by name.
+    pub fn read_entry(&mut self, name: impl AnyStringType) -> Result<Vec<u8>, IoError> {
+        read_zip_entry(self.m_archive.by_name(name.convert()).map_err(convert_zip_error)?)
+    }
+
+    /// Extracts every entry into a directory, creating it if necessary,
+    /// and restores modification dates and, on Unix, permissions.
+    ///
+    /// Fails with `InvalidData`, before writing anything, if an entry name
+    /// is absolute or would escape the directory through `..`.
+    pub fn extract(&mut self, directory: &File) -> Result<(), IoError> {
+        let mut names = vec![];
+        for i in 0..self.m_archive.len() {
+            let file = self.m_archive.by_index_raw(i).map_err(convert_zip_error)?;
+            names.push(safe_entry_path(file.name())?);
+        }
+        directory.create_directory()?;
+        for (i, name) in names.into_iter().enumerate() {
+            let mut file = self.m_archive.by_index(i).map_err(convert_zip_error)?;
+            if name.is_empty() {
+                continue;
+            }
+            let target = directory.resolve_path(name.as_str());
+            if file.is_dir() {
+                target.create_directory()?;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                parent.create_directory()?;
+            }
+            let mut output = std::fs::File::create(target.to_path_object())?;
+            std::io::copy(&mut file, &mut output)?;
+            if let Some(date) = system_time_from_date_time(file.last_modified()) {
+                output.set_modified(date)?;
+            }
+            #[cfg(unix)] {
+                use std::os::unix::fs::PermissionsExt;
+                if let Some(mode) = file.unix_mode() {
+                    output.set_permissions(std::fs::Permissions::from_mode(mode & 0o777))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An entry of a zip archive read in parts, as returned by `ZipArchive::open_entry`.
+/// It implements the `Read` trait.
+pub struct ZipEntryReader<'a> {
+    m_file: zip::read::ZipFile<'a>,
+    m_entry: ZipEntry,
+}
+
+impl ZipEntryReader<'_> {
+    /// Description of the entry.
+    pub fn entry(&self) -> &ZipEntry {
+        &self.m_entry
+    }
+}
+
+impl Read for ZipEntryReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.m_file.read(buffer)
+    }
+}
+
+/// A zip archive being created. Entries of 4 GiB and more
+/// use zip64 extensions. The archive is complete once `finish` is called.
+pub struct ZipArchiveWriter {
+    m_writer: zip::ZipWriter<std::fs::File>,
+    // canonical path of the archive, which `add_tree` does not add to itself
+    m_path: Option<PathBuf>,
+}
+
+impl ZipArchiveWriter {
+    /// Creates a zip archive, replacing the file if it exists.
+    pub fn create(file: &File) -> Result<Self, IoError> {
+        let writer = zip::ZipWriter::new(std::fs::File::create(file.to_path_object())?);
+        Ok(ZipArchiveWriter { m_writer: writer, m_path: std::fs::canonicalize(file.to_path_object()).ok() })
+    }
+
+    /// Adds an entry with the given contents.
+    pub fn add_bytes<B: AsRef<[u8]>>(&mut self, name: impl AnyStringType, b: B, options: &ZipEntryOptions) -> Result<(), IoError> {
+        let name = safe_entry_path(name.convert())?;
+        let b = b.as_ref();
+        self.m_writer.start_file(name, options.zip_options(None, None, b.len() as u64)?).map_err(convert_zip_error)?;
+        self.m_writer.write_all(b)
+    }
+
+    /// Adds an entry with the contents of a file, which is read in parts.
+    pub fn add_file(&mut self, name: impl AnyStringType, file: &File, options: &ZipEntryOptions) -> Result<(), IoError> {
+        let name = safe_entry_path(name.convert())?;
+        let mut input = std::fs::File::open(file.to_path_object())?;
+        let metadata = input.metadata()?;
+        self.m_writer.start_file(name, options.zip_options(metadata.modified().ok(), unix_mode(&metadata), metadata.len())?).map_err(convert_zip_error)?;
+        std::io::copy(&mut input, &mut self.m_writer)?;
+        Ok(())
+    }
+
+    /// Adds a directory entry.
+    pub fn add_directory(&mut self, name: impl AnyStringType, options: &ZipEntryOptions) -> Result<(), IoError> {
+        let name = safe_entry_path(name.convert())?;
+        let options = options.zip_options(None, Some(0o755), 0)?;
+        self.m_writer.add_directory(name, options).map_err(convert_zip_error)
+    }
+
+    /// Adds the files and directories of a directory tree on the disk under
+    /// a prefix, which may be empty, in sorted order. Mounts of the virtual
+    /// file system are ignored, as is the archive if it is inside the directory.
+    pub fn add_tree(&mut self, prefix: impl AnyStringType, directory: &File, options: &ZipEntryOptions) -> Result<(), IoError> {
+        let prefix = safe_entry_path(prefix.convert())?;
+        let walk_options = DirectoryWalkOptions::new();
+        walk_options.sorted(true).mounts(false);
+        for entry in directory.walk(&walk_options) {
+            let entry = entry?;
+            if entry.is_file() && self.m_path.is_some() && std::fs::canonicalize(entry.file().to_path_object()).ok() == self.m_path {
+                continue;
+            }
+            let name = if prefix.is_empty() { entry.relative_path() } else { prefix.clone() + "/" + &entry.relative_path() };
+            if entry.is_directory() {
+                let metadata = std::fs::metadata(entry.file().to_path_object())?;
+                let options = options.zip_options(metadata.modified().ok(), unix_mode(&metadata).or(Some(0o755)), 0)?;
+                self.m_writer.add_directory(name, options).map_err(convert_zip_error)?;
+            } else if entry.is_file() {
+                self.add_file(name, &entry.file(), options)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the central directory, completing the archive.
+    pub fn finish(mut self) -> Result<(), IoError> {
+        self.m_writer.finish().map_err(convert_zip_error)?.flush()
+    }
+}
+
+/// Normalizes an entry name, failing if it is absolute or escapes the archive root.
+fn safe_entry_path(name: &str) -> Result<String, IoError> {
+    let mut components = vec![];
+    for component in std::path::Path::new(&name.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(c) => components.push(c.to_str().unwrap_or_default().to_owned()),
+            Component::CurDir => {},
+            _ => return Err(IoError::new(IoErrorKind::InvalidData, format!("unsafe zip entry name: {}", name))),
+        }
+    }
+    Ok(components.join("/"))
+}
+
+fn unix_mode(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)] {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode())
+    }
+    #[cfg(not(unix))] {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Reads a zip entry, up to the size given by the archive. The size is not
+/// trusted for allocating, as the archive may be corrupt or malicious.
+pub(crate) fn read_zip_entry(entry: zip::read::ZipFile) -> Result<Vec<u8>, IoError> {
+    let size = entry.size();
+    let mut r = vec![];
+    entry.take(size).read_to_end(&mut r)?;
+    Ok(r)
+}
+
+pub(crate) fn convert_zip_error(error: zip::result::ZipError) -> IoError {
+    match error {
+        zip::result::ZipError::Io(error) => error,
+        zip::result::ZipError::FileNotFound => IoError::new(IoErrorKind::NotFound, error),
+        error => IoError::new(IoErrorKind::InvalidData, error),
+    }
+}
+
+fn date_time_from_system_time(date: SystemTime) -> zip::DateTime {
+    let seconds = date.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let time = seconds.rem_euclid(86400);
+    // zip dates range from 1980 to 2107, and earlier dates become 1980-01-01
+    u16::try_from(year).ok()
+        .and_then(|year| zip::DateTime::from_date_and_time(year, month, day, (time / 3600) as u8, (time / 60 % 60) as u8, (time % 60) as u8).ok())
+        .unwrap_or_default()
+}
+
+fn system_time_from_date_time(date: zip::DateTime) -> Option<SystemTime> {
+    let days = days_from_civil(date.year() as i64, date.month(), date.day());
+    let seconds = days * 86400 + date.hour() as i64 * 3600 + date.minute() as i64 * 60 + date.second() as i64;
+    UNIX_EPOCH.checked_add(Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+/// Converts days since 1970-01-01 into a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    (yoe + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+/// Converts a proleptic Gregorian date into days since 1970-01-01.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_entry() {
+        let temp = File::create_temp_directory().unwrap();
+        let archive = temp.file().resolve_path("a.zip");
+        let mut writer = ZipArchiveWriter::create(&archive).unwrap();
+        let options = ZipEntryOptions::new();
+        writer.add_bytes("a.txt", b"alpha".repeat(100), &options).unwrap();
+        writer.add_bytes("b/c.txt", "gamma", options.compression(ZipCompression::Stored)).unwrap();
+        writer.finish().unwrap();
+
+        let mut archive = ZipArchive::open(&archive).unwrap();
+        assert_eq!(archive.read_entry("a.txt").unwrap(), b"alpha".repeat(100));
+        assert_eq!(archive.read_entry("b/c.txt").unwrap(), b"gamma");
+        assert!(matches!(archive.read_entry("d.txt"), Err(error) if error.kind() == IoErrorKind::NotFound));
+    }
+
+    fn is_unsafe<T>(r: Result<T, IoError>) -> bool {
+        matches!(r, Err(error) if error.kind() == IoErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn unsafe_names() {
+        let temp = File::create_temp_directory().unwrap();
+        let source = temp.file().resolve_path("a.txt");
+        source.write("alpha").unwrap();
+        let mut writer = ZipArchiveWriter::create(&temp.file().resolve_path("a.zip")).unwrap();
+        let options = ZipEntryOptions::new();
+        for name in ["../x", "/x", "..\\x", "a/../../x"] {
+            assert!(is_unsafe(writer.add_bytes(name, "alpha", &options)), "{}", name);
+            assert!(is_unsafe(writer.add_file(name, &source, &options)), "{}", name);
+            assert!(is_unsafe(writer.add_directory(name, &options)), "{}", name);
+            assert!(is_unsafe(writer.add_tree(name, &temp.file(), &options)), "{}", name);
+        }
+        writer.add_bytes("./b.txt", "beta", &options).unwrap();
+        writer.finish().unwrap();
+        let mut archive = ZipArchive::open(&temp.file().resolve_path("a.zip")).unwrap();
+        let names: Vec<String> = archive.entries().unwrap().iter().map(|entry| entry.name()).collect();
+        assert_eq!(names, ["b.txt"]);
+
+        // archives from elsewhere may have any name
+        for name in ["../x", "/x", "..\\x"] {
+            let archive = temp.file().resolve_path("unsafe.zip");
+            let mut writer = zip::ZipWriter::new(std::fs::File::create(archive.to_path_object()).unwrap());
+            writer.start_file("a.txt", zip::write::FileOptions::default()).unwrap();
+            writer.write_all(b"alpha").unwrap();
+            writer.start_file(name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(b"unsafe").unwrap();
+            writer.finish().unwrap();
+            let output = temp.file().resolve_path("output");
+            assert!(is_unsafe(ZipArchive::open(&archive).unwrap().extract(&output)), "{}", name);
+            assert!(!output.exists());
+            assert!(!temp.file().resolve_path("x").exists());
+        }
+    }
+
+    #[test]
+    fn extract() {
+        let temp = File::create_temp_directory().unwrap();
+        let archive = temp.file().resolve_path("a.zip");
+        let date = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let mut writer = ZipArchiveWriter::create(&archive).unwrap();
+        let options = ZipEntryOptions::new();
+        options.modification_date(date).unix_mode(0o640);
+        writer.add_bytes("a/b.txt", "beta", &options).unwrap();
+        writer.add_directory("c", &options).unwrap();
+        writer.finish().unwrap();
+
+        let output = temp.file().resolve_path("output");
+        ZipArchive::open(&archive).unwrap().extract(&output).unwrap();
+        let b = output.resolve_path("a/b.txt");
+        assert_eq!(b.read_utf8().unwrap(), "beta");
+        assert_eq!(b.modification_date().unwrap(), date);
+        assert!(output.resolve_path("c").is_directory());
+        #[cfg(unix)] {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(b.to_path_object()).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        }
+    }
+
+    #[test]
+    fn add_tree() {
+        let temp = File::create_temp_directory().unwrap();
+        let directory = temp.file().resolve_path("tree");
+        directory.resolve_path("b/c").create_directory().unwrap();
+        directory.resolve_path("a.txt").write("alpha").unwrap();
+        directory.resolve_path("b/c/d.txt").write("delta".repeat(1000)).unwrap();
+        // written inside the tree, with stored entries so that copying
+        // the archive into itself would not end
+        let archive = directory.resolve_path("tree.zip");
+        let options = ZipEntryOptions::new();
+        options.compression(ZipCompression::Stored);
+        let mut writer = ZipArchiveWriter::create(&archive).unwrap();
+        writer.add_tree("prefix", &directory, &options).unwrap();
+        writer.finish().unwrap();
+
+        let mut archive = ZipArchive::open(&archive).unwrap();
+        let entries = archive.entries().unwrap();
+        let names: Vec<String> = entries.iter().map(|entry| entry.name()).collect();
+        assert_eq!(names, ["prefix/a.txt", "prefix/b/", "prefix/b/c/", "prefix/b/c/d.txt"]);
+        assert!(entries[1].is_directory());
+        assert_eq!(entries[3].compression(), ZipCompression::Stored);
+        assert_eq!(archive.read_entry("prefix/b/c/d.txt").unwrap(), b"delta".repeat(1000));
+    }
+
+    #[test]
+    fn large_file() {
+        // the sizes given to the options decide whether zip64 extensions are written
+        let temp = File::create_temp_directory().unwrap();
+        let write = |name: &str, size: u64| {
+            let archive = temp.file().resolve_path(name);
+            let mut writer = ZipArchiveWriter::create(&archive).unwrap();
+            writer.m_writer.start_file("a.txt", ZipEntryOptions::new().zip_options(None, None, size).unwrap()).unwrap();
+            writer.m_writer.write_all(b"alpha").unwrap();
+            writer.finish().unwrap();
+            assert_eq!(ZipArchive::open(&archive).unwrap().read_entry("a.txt").unwrap(), b"alpha");
+            std::fs::metadata(archive.to_path_object()).unwrap().len()
+        };
+        assert!(write("small.zip", 5) < write("large.zip", u32::MAX as u64));
+        assert_eq!(write("small.zip", u32::MAX as u64 - 1), write("small.zip", 5));
+    }
+}
@@ -0,0 +1,268 @@
+use std::{cell::Cell, io::Write, path::{Path, PathBuf}, time::Duration};
+use super::{File, IoError, IoErrorKind, blocking, temp};
+
+/// Options for `File::write_atomic` and `File::read_recovering`.
+pub struct AtomicWriteOptions {
+    m_backups: Cell<usize>,
+}
+
+impl Clone for AtomicWriteOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_backups: self.m_backups.clone(),
+        }
+    }
+}
+
+impl AtomicWriteOptions {
+    pub fn new() -> Self {
+        AtomicWriteOptions {
+            m_backups: Cell::new(0),
+        }
+    }
+
+    /// Number of previous contents kept as backups next to the file,
+    /// such as `save.dat.bak1` for the latest up to `save.dat.bak3`
+    /// for the oldest with 3 backups. Defaults to 0.
+    pub fn backups(&self, value: usize) -> &Self {
+        self.m_backups.set(value);
+        self
+    }
+}
+
+impl Default for AtomicWriteOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut r = path.as_os_str().to_owned();
+    r.push(format!(".bak{}", index));
+    PathBuf::from(r)
+}
+
+fn split_path(path: &Path) -> Result<(PathBuf, String), IoError> {
+    let name = path.file_name().and_then(|name| name.to_str())
+        .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, format!("not a file path: {}", path.display())))?;
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory.to_owned(),
+        _ => PathBuf::from("."),
+    };
+    Ok((directory, name.to_owned()))
+}
+
+fn temp_prefix(name: &str) -> String {
+    format!(".{}.tmp-", name)
+}
+
+fn ignore_not_found(result: Result<(), IoError>) -> Result<(), IoError> {
+    match result {
+        Err(error) if error.kind() == IoErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Shifts the backups of a file by one and makes the file the latest backup.
+fn rotate_backups(path: &Path, backups: usize) -> Result<(), IoError> {
+    if backups == 0 || !path.exists() {
+        return Ok(());
+    }
+    ignore_not_found(std::fs::remove_file(backup_path(path, backups)))?;
+    for i in (1..backups).rev() {
+        ignore_not_found(std::fs::rename(backup_path(path, i), backup_path(path, i + 1)))?;
+    }
+    // a hard link keeps the file in place until it is replaced
+    if std::fs::hard_link(path, backup_path(path, 1)).is_err() {
+        std::fs::copy(path, backup_path(path, 1))?;
+    }
+    Ok(())
+}
+
+fn write_path(path: &Path, bytes: &[u8], backups: usize) -> Result<(), IoError> {
+    let (directory, name) = split_path(path)?;
+    let temp_path = directory.join(temp::unique_name(&temp_prefix(&name)));
+    let r = write_replacing(path, &temp_path, bytes, backups);
+    if r.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    r?;
+    sync_directory(&directory)
+}
+
+fn write_replacing(path: &Path, temp_path: &Path, bytes: &[u8], backups: usize) -> Result<(), IoError> {
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(temp_path)?;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+    rotate_backups(path, backups)?;
+    std::fs::rename(temp_path, path)
+}
+
+/// Makes renames within a directory durable.
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> Result<(), IoError> {
+    std::fs::File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> Result<(), IoError> {
+    Ok(())
+}
+
+// age after which a temporary file is no longer taken as being written
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Removes temporary files left by writes interrupted by a crash,
+/// leaving those that may still be written by another process.
+fn remove_stale_temp_files(path: &Path) -> Result<(), IoError> {
+    let (directory, name) = split_path(path)?;
+    let prefix = temp_prefix(&name);
+    let entries = match std::fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == IoErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_name().to_str().map(|n| n.starts_with(&prefix)) != Some(true) {
+            continue;
+        }
+        let age = entry.metadata().and_then(|metadata| metadata.modified()).ok().and_then(|date| date.elapsed().ok());
+        if age.is_some_and(|age| age >= STALE_TEMP_AGE) {
+            ignore_not_found(std::fs::remove_file(entry.path()))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_path(path: &Path, backups: usize, is_valid: impl Fn(&[u8]) -> bool) -> Result<Vec<u8>, IoError> {
+    remove_stale_temp_files(path)?;
+    let error = match std::fs::read(path) {
+        Ok(bytes) if is_valid(&bytes) => return Ok(bytes),
+        Ok(_) => IoError::new(IoErrorKind::InvalidData, format!("{} is corrupt", path.display())),
+        Err(error) => error,
+    };
+    for i in 1..=backups {
+        match std::fs::read(backup_path(path, i)) {
+            Ok(bytes) if is_valid(&bytes) => {
+                write_path(path, &bytes, 0)?;
+                return Ok(bytes);
+            },
+            _ => continue,
+        }
+    }
+    Err(error)
+}
+
+pub(crate) fn write(file: &File, bytes: &[u8], options: &AtomicWriteOptions) -> Result<(), IoError> {
+    write_path(&PathBuf::from(file.to_path_object()), bytes, options.m_backups.get())
+}
+
+pub(crate) async fn write_async(file: &File, bytes: Vec<u8>, options: &AtomicWriteOptions) -> Result<(), IoError> {
+    let path = PathBuf::from(file.to_path_object());
+    let backups = options.m_backups.get();
+    blocking(move || write_path(&path, &bytes, backups)).await
+}
+
+pub(crate) fn read(file: &File, options: &AtomicWriteOptions, is_valid: impl Fn(&[u8]) -> bool) -> Result<Vec<u8>, IoError> {
+    read_path(&PathBuf::from(file.to_path_object()), options.m_backups.get(), is_valid)
+}
+
+pub(crate) async fn read_async(file: &File, options: &AtomicWriteOptions, is_valid: impl Fn(&[u8]) -> bool + Send + 'static) -> Result<Vec<u8>, IoError> {
+    let path = PathBuf::from(file.to_path_object());
+    let backups = options.m_backups.get();
+    blocking(move || read_path(&path, backups, is_valid)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    #[test]
+    fn backups() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("save.dat");
+        let options = AtomicWriteOptions::new();
+        options.backups(2);
+        for contents in ["1", "2", "3", "4"] {
+            file.write_atomic(contents, &options).unwrap();
+        }
+        assert_eq!(file.read_utf8().unwrap(), "4");
+        assert_eq!(temp.file().resolve_path("save.dat.bak1").read_utf8().unwrap(), "3");
+        assert_eq!(temp.file().resolve_path("save.dat.bak2").read_utf8().unwrap(), "2");
+        assert!(!temp.file().resolve_path("save.dat.bak3").exists());
+        // only the file and its backups are left
+        assert_eq!(temp.file().get_directory_listing().unwrap().len(), 3);
+
+        // without backups, previous ones are left as they are
+        file.write_atomic("5", &AtomicWriteOptions::new()).unwrap();
+        assert_eq!(file.read_utf8().unwrap(), "5");
+        assert_eq!(temp.file().resolve_path("save.dat.bak1").read_utf8().unwrap(), "3");
+    }
+
+    #[test]
+    fn recovery() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("save.dat");
+        let options = AtomicWriteOptions::new();
+        options.backups(2);
+        let is_valid = |bytes: &[u8]| bytes.starts_with(b"ok");
+        file.write_atomic("ok 1", &options).unwrap();
+        file.write_atomic("ok 2", &options).unwrap();
+        assert_eq!(file.read_recovering(&options, is_valid).unwrap(), b"ok 2");
+
+        // the latest valid backup is restored
+        file.write("corrupt").unwrap();
+        assert_eq!(file.read_recovering(&options, is_valid).unwrap(), b"ok 1");
+        assert_eq!(file.read_utf8().unwrap(), "ok 1");
+        temp.file().resolve_path("save.dat.bak1").write("corrupt").unwrap();
+        file.delete_file().unwrap();
+        assert!(file.read_recovering(&options, is_valid).is_err());
+
+        temp.file().resolve_path("save.dat.bak1").write("ok 3").unwrap();
+        assert_eq!(file.read_recovering(&options, is_valid).unwrap(), b"ok 3");
+        file.write("corrupt").unwrap();
+        let r = file.read_recovering(&AtomicWriteOptions::new(), is_valid);
+        assert!(matches!(r, Err(error) if error.kind() == IoErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn stale_temp_files() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("save.dat");
+        file.write_atomic("ok", &AtomicWriteOptions::new()).unwrap();
+        let stale = temp.file().resolve_path(".save.dat.tmp-stale");
+        let recent = temp.file().resolve_path(".save.dat.tmp-recent");
+        let other = temp.file().resolve_path(".other.dat.tmp-stale");
+        for file in [&stale, &recent, &other] {
+            file.write("partial").unwrap();
+        }
+        let date = SystemTime::now() - STALE_TEMP_AGE - Duration::from_secs(1);
+        for file in [&stale, &other] {
+            std::fs::File::options().write(true).open(file.to_path_object()).unwrap().set_modified(date).unwrap();
+        }
+
+        file.read_recovering(&AtomicWriteOptions::new(), |_| true).unwrap();
+        assert!(!stale.exists());
+        assert!(recent.exists());
+        assert!(other.exists());
+    }
+
+    #[tokio::test]
+    async fn write_read_async() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("save.dat");
+        let options = AtomicWriteOptions::new();
+        options.backups(1);
+        file.write_atomic_async("ok 1", &options).await.unwrap();
+        file.write_atomic_async("ok 2", &options).await.unwrap();
+        file.write("corrupt").unwrap();
+        assert_eq!(file.read_recovering_async(&options, |bytes| bytes.starts_with(b"ok")).await.unwrap(), b"ok 1");
+    }
+}
+
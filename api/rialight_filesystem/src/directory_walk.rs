@@ -0,0 +1,571 @@
+use std::{cell::{Cell, RefCell}, collections::HashSet, path::{Path, PathBuf}};
+use futures_util::Stream;
+use rialight_util::AnyStringType;
+use super::{File, IoError, IoErrorKind, VfsEntry, glob_helpers, vfs};
+
+/// Options for walking a directory with `File::walk` or `File::walk_async`.
+///
+/// Include and exclude filters are glob patterns. A pattern containing `/`
+/// is matched against the path of an entry relative to the walked directory;
+/// otherwise it is matched against the entry name, at any depth.
+pub struct DirectoryWalkOptions {
+    m_max_depth: Cell<Option<usize>>,
+    m_follow_symbolic_links: Cell<bool>,
+    m_sorted: Cell<bool>,
+    m_include: RefCell<Vec<String>>,
+    m_exclude: RefCell<Vec<String>>,
+    m_mounts: Cell<bool>,
+}
+
+impl Clone for DirectoryWalkOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_max_depth: self.m_max_depth.clone(),
+            m_follow_symbolic_links: self.m_follow_symbolic_links.clone(),
+            m_sorted: self.m_sorted.clone(),
+            m_include: self.m_include.clone(),
+            m_exclude: self.m_exclude.clone(),
+            m_mounts: self.m_mounts.clone(),
+        }
+    }
+}
+
+impl DirectoryWalkOptions {
+    pub fn new() -> Self {
+        DirectoryWalkOptions {
+            m_max_depth: Cell::new(None),
+            m_follow_symbolic_links: Cell::new(false),
+            m_sorted: Cell::new(false),
+            m_include: RefCell::new(vec![]),
+            m_exclude: RefCell::new(vec![]),
+            m_mounts: Cell::new(true),
+        }
+    }
+
+    /// Maximum depth of the yielded entries. Entries directly
+    /// inside the walked directory have depth 1. Unlimited by default.
+    pub fn max_depth(&self, value: usize) -> &Self {
+        self.m_max_depth.set(Some(value));
+        self
+    }
+
+    /// Whether to descend into directories referenced by symbolic links.
+    /// Defaults to `false`. Directories already visited are reported as errors.
+    pub fn follow_symbolic_links(&self, value: bool) -> &Self {
+        self.m_follow_symbolic_links.set(value);
+        self
+    }
+
+    /// Whether to yield the entries of each directory sorted by name.
+    /// Defaults to `false`, in which case the order is the one of the operating system.
+    pub fn sorted(&self, value: bool) -> &Self {
+        self.m_sorted.set(value);
+        self
+    }
+
+    /// Patterns of which one must match an entry for it to be yielded.
+    /// Directories are descended into regardless. Everything is included by default.
+    pub fn include(&self, list: Vec<impl AnyStringType>) -> &Self {
+        self.m_include.replace(list.iter().map(|p| p.convert().to_owned()).collect());
+        self
+    }
+
+    /// Patterns matching entries to skip. Excluded directories are not descended into.
+    pub fn exclude(&self, list: Vec<impl AnyStringType>) -> &Self {
+        self.m_exclude.replace(list.iter().map(|p| p.convert().to_owned()).collect());
+        self
+    }
+
+    /// Whether to yield the entries of mounts of the virtual file system
+    /// over those of the disk. Defaults to `true`.
+    pub fn mounts(&self, value: bool) -> &Self {
+        self.m_mounts.set(value);
+        self
+    }
+}
+
+impl Default for DirectoryWalkOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An entry yielded by walking a directory.
+#[derive(Clone)]
+pub struct DirectoryEntry {
+    m_file: File,
+    m_relative_path: String,
+    m_depth: usize,
+    m_kind: EntryKind,
+    m_symbolic_link: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum EntryKind {
+    Directory,
+    File,
+    Other,
+}
+
+impl DirectoryEntry {
+    /// The file or directory.
+    pub fn file(&self) -> File {
+        self.m_file.clone()
+    }
+
+    /// Path relative to the walked directory, separated by `/`.
+    pub fn relative_path(&self) -> String {
+        self.m_relative_path.clone()
+    }
+
+    /// Depth in the walked directory, starting at 1.
+    pub fn depth(&self) -> usize {
+        self.m_depth
+    }
+
+    /// Determines whether the entry is a directory, or
+    /// a symbolic link to a directory when links are followed.
+    pub fn is_directory(&self) -> bool {
+        self.m_kind == EntryKind::Directory
+    }
+
+    /// Determines whether the entry is a file, or
+    /// a symbolic link to a file when links are followed.
+    pub fn is_file(&self) -> bool {
+        self.m_kind == EntryKind::File
+    }
+
+    /// Determines whether the entry is a symbolic link.
+    pub fn is_symbolic_link(&self) -> bool {
+        self.m_symbolic_link
+    }
+}
+
+/// Recursive iterator over a directory, as returned by `File::walk`.
+///
+/// Directories are yielded before their contents. A failure to read
+/// an entry is yielded as an error and the walk continues past it.
+pub struct DirectoryWalk {
+    m_state: WalkState,
+}
+
+/// Recursive walk over a directory, as returned by `File::walk_async`.
+///
+/// Directories are yielded before their contents. A failure to read
+/// an entry is yielded as an error and the walk continues past it.
+pub struct AsyncDirectoryWalk {
+    m_state: WalkState,
+}
+
+struct WalkState {
+    m_options: DirectoryWalkOptions,
+    m_stack: Vec<WalkItem>,
+    m_visited: HashSet<PathBuf>,
+}
+
+enum WalkItem {
+    Entry(DirectoryEntry),
+    Directory(PendingDirectory),
+    Error(IoError),
+}
+
+struct PendingDirectory {
+    m_file: File,
+    m_relative_path: String,
+    m_depth: usize,
+}
+
+struct ChildEntry {
+    m_name: String,
+    m_kind: EntryKind,
+    m_symbolic_link: bool,
+}
+
+impl WalkState {
+    fn new(directory: &File, options: &DirectoryWalkOptions) -> Self {
+        WalkState {
+            m_options: options.clone(),
+            m_stack: vec![WalkItem::Directory(PendingDirectory {
+                m_file: directory.clone(),
+                m_relative_path: "".to_owned(),
+                m_depth: 0,
+            })],
+            m_visited: HashSet::new(),
+        }
+    }
+
+    /// Records a directory as visited, failing if it was already
+    /// visited through a symbolic link.
+    fn visit(&mut self, canonical_path: Result<PathBuf, IoError>, directory: &PendingDirectory) -> Result<(), IoError> {
+        if !self.m_options.m_follow_symbolic_links.get() {
+            return Ok(());
+        }
+        if !self.m_visited.insert(canonical_path?) {
+            return Err(IoError::new(IoErrorKind::Other, format!("file system loop at {}", directory.m_file.url())));
+        }
+        Ok(())
+    }
+
+    fn push_children(&mut self, directory: PendingDirectory, mut children: Vec<Result<ChildEntry, IoError>>) {
+        if self.m_options.m_sorted.get() {
+            children.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => a.m_name.cmp(&b.m_name),
+                (a, b) => a.is_ok().cmp(&b.is_ok()),
+            });
+        }
+        let depth = directory.m_depth + 1;
+        let include = self.m_options.m_include.borrow().clone();
+        let exclude = self.m_options.m_exclude.borrow().clone();
+        let max_depth = self.m_options.m_max_depth.get();
+        for child in children.into_iter().rev() {
+            let child = match child {
+                Ok(child) => child,
+                Err(error) => {
+                    self.m_stack.push(WalkItem::Error(error));
+                    continue;
+                },
+            };
+            let relative_path = if directory.m_relative_path.is_empty() { child.m_name.clone() } else { directory.m_relative_path.clone() + "/" + &child.m_name };
+            if exclude.iter().any(|p| pattern_matches(p, &child.m_name, &relative_path)) {
+                continue;
+            }
+            let file = directory.m_file.resolve_path(child.m_name.as_str());
+            if child.m_kind == EntryKind::Directory
+            && (!child.m_symbolic_link || self.m_options.m_follow_symbolic_links.get())
+            && max_depth.map(|m| depth < m).unwrap_or(true)
+            {
+                self.m_stack.push(WalkItem::Directory(PendingDirectory {
+                    m_file: file.clone(),
+                    m_relative_path: relative_path.clone(),
+                    m_depth: depth,
+                }));
+            }
+            if include.is_empty() || include.iter().any(|p| pattern_matches(p, &child.m_name, &relative_path)) {
+                self.m_stack.push(WalkItem::Entry(DirectoryEntry {
+                    m_file: file,
+                    m_relative_path: relative_path,
+                    m_depth: depth,
+                    m_kind: child.m_kind,
+                    m_symbolic_link: child.m_symbolic_link,
+                }));
+            }
+        }
+    }
+}
+
+fn pattern_matches(pattern: &str, name: &str, relative_path: &str) -> bool {
+    if pattern.contains('/') {
+        glob_helpers::matches(pattern, relative_path)
+    } else {
+        glob_helpers::matches(pattern, name)
+    }
+}
+
+fn entry_name(path: &Path) -> Result<String, IoError> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_owned())
+        .ok_or_else(|| IoError::new(IoErrorKind::InvalidData, format!("file name is not valid UTF-8: {}", path.display())))
+}
+
+fn entry_kind(file_type: std::fs::FileType) -> EntryKind {
+    if file_type.is_dir() {
+        EntryKind::Directory
+    } else if file_type.is_file() {
+        EntryKind::File
+    } else {
+        EntryKind::Other
+    }
+}
+
+fn child_entry(entry: Result<std::fs::DirEntry, IoError>, follow_symbolic_links: bool) -> Result<ChildEntry, IoError> {
+    let entry = entry?;
+    let name = entry_name(&entry.path())?;
+    let file_type = entry.file_type()?;
+    let symbolic_link = file_type.is_symlink();
+    let kind = if symbolic_link && follow_symbolic_links {
+        entry_kind(std::fs::metadata(entry.path())?.file_type())
+    } else {
+        entry_kind(file_type)
+    };
+    Ok(ChildEntry { m_name: name, m_kind: kind, m_symbolic_link: symbolic_link })
+}
+
+async fn async_child_entry(entry: &tokio::fs::DirEntry, follow_symbolic_links: bool) -> Result<ChildEntry, IoError> {
+    let name = entry_name(&entry.path())?;
+    let file_type = entry.file_type().await?;
+    let symbolic_link = file_type.is_symlink();
+    let kind = if symbolic_link && follow_symbolic_links {
+        entry_kind(tokio::fs::metadata(entry.path()).await?.file_type())
+    } else {
+        entry_kind(file_type)
+    };
+    Ok(ChildEntry { m_name: name, m_kind: kind, m_symbolic_link: symbolic_link })
+}
+
+/// Overlays the entries of mounts of the virtual file system on those of the disk.
+fn merge_mounted(children: &mut Vec<Result<ChildEntry, IoError>>, mounted: Vec<(String, VfsEntry)>) {
+    for (name, entry) in mounted {
+        let kind = if entry == VfsEntry::Directory { EntryKind::Directory } else { EntryKind::File };
+        match children.iter_mut().find(|child| matches!(child, Ok(child) if child.m_name == name)) {
+            Some(Ok(child)) => {
+                child.m_kind = kind;
+                child.m_symbolic_link = false;
+            },
+            _ => children.push(Ok(ChildEntry { m_name: name, m_kind: kind, m_symbolic_link: false })),
+        }
+    }
+}
+
+impl DirectoryWalk {
+    pub(crate) fn new(directory: &File, options: &DirectoryWalkOptions) -> Self {
+        DirectoryWalk { m_state: WalkState::new(directory, options) }
+    }
+
+    fn read_directory(&mut self, directory: &PendingDirectory) -> Result<Vec<Result<ChildEntry, IoError>>, IoError> {
+        let path = directory.m_file.to_path_object();
+        let mounted = if self.m_state.m_options.m_mounts.get() { vfs::list(&directory.m_file).transpose()? } else { None };
+        let follow = self.m_state.m_options.m_follow_symbolic_links.get();
+        let mut r = vec![];
+        match std::fs::read_dir(&path) {
+            Ok(entries) => {
+                self.m_state.visit(std::fs::canonicalize(&path), directory)?;
+                for entry in entries {
+                    r.push(child_entry(entry, follow));
+                }
+            },
+            // a directory may exist only in mounts
+            Err(error) => if mounted.is_none() {
+                return Err(error);
+            },
+        }
+        merge_mounted(&mut r, mounted.unwrap_or_default());
+        Ok(r)
+    }
+}
+
+impl Iterator for DirectoryWalk {
+    type Item = Result<DirectoryEntry, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.m_state.m_stack.pop()? {
+                WalkItem::Entry(entry) => return Some(Ok(entry)),
+                WalkItem::Error(error) => return Some(Err(error)),
+                WalkItem::Directory(directory) => {
+                    match self.read_directory(&directory) {
+                        Ok(children) => self.m_state.push_children(directory, children),
+                        Err(error) => return Some(Err(error)),
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl AsyncDirectoryWalk {
+    pub(crate) fn new(directory: &File, options: &DirectoryWalkOptions) -> Self {
+        AsyncDirectoryWalk { m_state: WalkState::new(directory, options) }
+    }
+
+    /// Yields the next entry, or `None` when the walk is complete.
+    pub async fn next_entry(&mut self) -> Option<Result<DirectoryEntry, IoError>> {
+        loop {
+            match self.m_state.m_stack.pop()? {
+                WalkItem::Entry(entry) => return Some(Ok(entry)),
+                WalkItem::Error(error) => return Some(Err(error)),
+                WalkItem::Directory(directory) => {
+                    match self.read_directory(&directory).await {
+                        Ok(children) => self.m_state.push_children(directory, children),
+                        Err(error) => return Some(Err(error)),
+                    }
+                },
+            }
+        }
+    }
+
+    /// Converts the walk into a `Stream` of entries.
+    pub fn into_stream(self) -> impl Stream<Item = Result<DirectoryEntry, IoError>> {
+        futures_util::stream::unfold(self, |mut walk| async move {
+            walk.next_entry().await.map(|entry| (entry, walk))
+        })
+    }
+
+    async fn read_directory(&mut self, directory: &PendingDirectory) -> Result<Vec<Result<ChildEntry, IoError>>, IoError> {
+        let path = directory.m_file.to_path_object();
+        let mounted = if self.m_state.m_options.m_mounts.get() { vfs::list_async(&directory.m_file).await.transpose()? } else { None };
+        let follow = self.m_state.m_options.m_follow_symbolic_links.get();
+        let mut r = vec![];
+        let mut entries = match tokio::fs::read_dir(&path).await {
+            Ok(entries) => entries,
+            // a directory may exist only in mounts
+            Err(error) => match mounted {
+                Some(mounted) => {
+                    merge_mounted(&mut r, mounted);
+                    return Ok(r);
+                },
+                None => return Err(error),
+            },
+        };
+        self.m_state.visit(tokio::fs::canonicalize(&path).await, directory)?;
+        loop {
+            let entry = match entries.next_entry().await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(error) => {
+                    // the listing cannot continue past a failed entry
+                    r.push(Err(error));
+                    break;
+                },
+            };
+            r.push(async_child_entry(&entry, follow).await);
+        }
+        merge_mounted(&mut r, mounted.unwrap_or_default());
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates `walk/a.txt`, `walk/b/c.rs`, `walk/b/d/e.txt` and `walk/z.log`,
+    /// with `walk/link` linking to `outside` and `walk/b/d/loop` to `walk/b`.
+    fn create_tree(root: &File) -> File {
+        let walk = root.resolve_path("walk");
+        walk.resolve_path("b/d").create_directory().unwrap();
+        root.resolve_path("outside").create_directory().unwrap();
+        for name in ["walk/a.txt", "walk/b/c.rs", "walk/b/d/e.txt", "walk/z.log", "outside/f.txt"] {
+            root.resolve_path(name).write(name).unwrap();
+        }
+        #[cfg(unix)] {
+            std::os::unix::fs::symlink("../outside", walk.resolve_path("link").to_path_object()).unwrap();
+            std::os::unix::fs::symlink("..", walk.resolve_path("b/d/loop").to_path_object()).unwrap();
+        }
+        walk
+    }
+
+    fn paths(walk: DirectoryWalk) -> Vec<String> {
+        walk.map(|entry| entry.map(|entry| entry.relative_path()).unwrap_or_else(|error| format!("error: {}", error.kind()))).collect()
+    }
+
+    #[test]
+    fn sorted() {
+        let root = File::create_temp_directory().unwrap();
+        let walk = create_tree(&root.file());
+        let r = paths(walk.walk(DirectoryWalkOptions::new().sorted(true)));
+        #[cfg(unix)]
+        assert_eq!(r, ["a.txt", "b", "b/c.rs", "b/d", "b/d/e.txt", "b/d/loop", "link", "z.log"]);
+        #[cfg(not(unix))]
+        assert_eq!(r, ["a.txt", "b", "b/c.rs", "b/d", "b/d/e.txt", "z.log"]);
+
+        let entries: Vec<DirectoryEntry> = walk.walk(DirectoryWalkOptions::new().sorted(true)).map(|e| e.unwrap()).collect();
+        assert!(entries[0].is_file() && !entries[0].is_directory());
+        assert_eq!(entries[0].depth(), 1);
+        assert_eq!(entries[0].file().read_utf8().unwrap(), "walk/a.txt");
+        assert!(entries[1].is_directory());
+        assert_eq!(entries[4].depth(), 3);
+
+        // unsorted walks still yield directories before their contents
+        let mut r = paths(walk.walk(&DirectoryWalkOptions::new()));
+        let b = r.iter().position(|p| p == "b").unwrap();
+        assert!(r.iter().position(|p| p == "b/d/e.txt").unwrap() > b);
+        r.sort();
+        assert_eq!(r, paths(walk.walk(DirectoryWalkOptions::new().sorted(true))));
+    }
+
+    #[test]
+    fn max_depth() {
+        let root = File::create_temp_directory().unwrap();
+        let walk = create_tree(&root.file());
+        let r = paths(walk.walk(DirectoryWalkOptions::new().sorted(true).max_depth(1)));
+        assert!(r.iter().all(|p| !p.contains('/')));
+        assert!(r.contains(&"b".to_owned()));
+        let r = paths(walk.walk(DirectoryWalkOptions::new().sorted(true).max_depth(2)));
+        assert!(r.contains(&"b/d".to_owned()));
+        assert!(!r.contains(&"b/d/e.txt".to_owned()));
+    }
+
+    #[test]
+    fn filters() {
+        let root = File::create_temp_directory().unwrap();
+        let walk = create_tree(&root.file());
+        // directories are descended into even if not included
+        assert_eq!(paths(walk.walk(DirectoryWalkOptions::new().sorted(true).include(vec!["*.txt"]))), ["a.txt", "b/d/e.txt"]);
+        assert_eq!(paths(walk.walk(DirectoryWalkOptions::new().sorted(true).include(vec!["b/*"]))), ["b/c.rs", "b/d"]);
+        assert_eq!(paths(walk.walk(DirectoryWalkOptions::new().sorted(true).include(vec!["*.txt", "*.rs"]).exclude(vec!["d"]))), ["a.txt", "b/c.rs"]);
+        assert_eq!(paths(walk.walk(DirectoryWalkOptions::new().sorted(true).include(vec!["*.txt"]).exclude(vec!["b/d/e.txt"]))), ["a.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symbolic_links() {
+        let root = File::create_temp_directory().unwrap();
+        let walk = create_tree(&root.file());
+        let entries: Vec<DirectoryEntry> = walk.walk(DirectoryWalkOptions::new().sorted(true)).map(|e| e.unwrap()).collect();
+        let link = entries.iter().find(|e| e.relative_path() == "link").unwrap();
+        assert!(link.is_symbolic_link());
+        assert!(!link.is_directory());
+
+        let r: Vec<Result<DirectoryEntry, IoError>> = walk.walk(DirectoryWalkOptions::new().sorted(true).follow_symbolic_links(true)).collect();
+        let link = r.iter().flatten().find(|e| e.relative_path() == "link").unwrap();
+        assert!(link.is_symbolic_link());
+        assert!(link.is_directory());
+        assert!(r.iter().flatten().any(|e| e.relative_path() == "link/f.txt"));
+        // the link back to b is yielded, but not descended into again
+        assert!(r.iter().flatten().any(|e| e.relative_path() == "b/d/loop" && e.is_directory()));
+        assert!(!r.iter().flatten().any(|e| e.relative_path().starts_with("b/d/loop/")));
+        assert_eq!(r.iter().filter(|e| e.is_err()).count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let root = File::create_temp_directory().unwrap();
+        let directory = PathBuf::from(root.file().to_path_object());
+        std::fs::write(directory.join("a.txt"), "").unwrap();
+        if std::fs::write(directory.join(std::ffi::OsStr::from_bytes(b"\xff.txt")), "").is_err() {
+            // the file system only allows UTF-8 names
+            return;
+        }
+        let listing = root.file().get_directory_listing().unwrap();
+        assert_eq!(listing.len(), 1);
+        assert!(listing[0] == root.file().resolve_path("a.txt"));
+        let r: Vec<Result<DirectoryEntry, IoError>> = root.file().walk(&DirectoryWalkOptions::new()).collect();
+        assert_eq!(r.iter().filter(|e| e.is_ok()).count(), 1);
+        assert_eq!(r.iter().filter(|e| e.is_err()).count(), 1);
+    }
+
+    #[test]
+    fn directory_listing() {
+        let (root, mounted) = (File::create_temp_directory().unwrap(), File::create_temp_directory().unwrap());
+        root.file().resolve_path("a.txt").write("alpha").unwrap();
+        mounted.file().resolve_path("a.txt").write("mounted").unwrap();
+        mounted.file().resolve_path("b.txt").write("beta").unwrap();
+        let id = crate::Vfs::mount(&root.file(), crate::VfsDirectory::new(&mounted.file()), 0);
+        let listing = root.file().get_directory_listing();
+        crate::Vfs::unmount(id);
+        // entries keep the scheme of the directory, whether on the disk or mounted
+        let listing = listing.unwrap();
+        assert_eq!(listing.len(), 2);
+        assert!(listing.contains(&root.file().resolve_path("a.txt")));
+        assert!(listing.contains(&root.file().resolve_path("b.txt")));
+        assert!(listing.iter().all(|file| file.url().starts_with("temp:")));
+    }
+
+    #[tokio::test]
+    async fn walk_async() {
+        let root = File::create_temp_directory().unwrap();
+        let walk = create_tree(&root.file());
+        let options = DirectoryWalkOptions::new();
+        options.sorted(true).exclude(vec!["loop", "link"]);
+        let mut r = vec![];
+        let mut entries = walk.walk_async(&options);
+        while let Some(entry) = entries.next_entry().await {
+            r.push(entry.unwrap().relative_path());
+        }
+        assert_eq!(r, ["a.txt", "b", "b/c.rs", "b/d", "b/d/e.txt", "z.log"]);
+        assert!(root.file().resolve_path("missing").walk_async(&options).next_entry().await.unwrap().is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fs::TryLockError, path::{Path, PathBuf}, time::{Duration, Instant}};
+use super::{File, IoError, IoErrorKind, blocking};
+
+/// The kind of advisory lock taken with `File::lock`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileLockMode {
+    /// Any number of shared locks may be held together,
+    /// such as by readers of a file.
+    Shared,
+    /// An exclusive lock is only held when no other lock is,
+    /// such as by a writer of a file.
+    Exclusive,
+}
+
+/// An advisory lock on a file, as returned by `File::lock`,
+/// released when the `FileLock` is dropped.
+pub struct FileLock {
+    m_file: File,
+    m_mode: FileLockMode,
+    m_handle: std::fs::File,
+}
+
+impl FileLock {
+    /// The locked file.
+    pub fn file(&self) -> File {
+        self.m_file.clone()
+    }
+
+    /// Whether the lock is shared or exclusive.
+    pub fn mode(&self) -> FileLockMode {
+        self.m_mode
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // closing the handle releases the lock as well
+        let _ = self.m_handle.unlock();
+    }
+}
+
+// bounds of the delay between attempts when waiting with a timeout
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Opens the file to lock, creating it if it does not exist.
+fn open(path: &Path) -> Result<std::fs::File, IoError> {
+    match std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path) {
+        // read-only files can still be locked
+        Err(error) if error.kind() == IoErrorKind::PermissionDenied => std::fs::File::open(path),
+        r => r,
+    }
+}
+
+fn try_lock_handle(handle: &std::fs::File, mode: FileLockMode) -> Result<bool, IoError> {
+    let r = match mode {
+        FileLockMode::Shared => handle.try_lock_shared(),
+        FileLockMode::Exclusive => handle.try_lock(),
+    };
+    match r {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(error)) => Err(error),
+    }
+}
+
+fn lock_path(file: File, path: PathBuf, mode: FileLockMode) -> Result<FileLock, IoError> {
+    let handle = open(&path)?;
+    match mode {
+        FileLockMode::Shared => handle.lock_shared()?,
+        FileLockMode::Exclusive => handle.lock()?,
+    }
+    Ok(FileLock { m_file: file, m_mode: mode, m_handle: handle })
+}
+
+fn lock_path_timeout(file: File, path: PathBuf, mode: FileLockMode, timeout: Duration) -> Result<Option<FileLock>, IoError> {
+    let handle = open(&path)?;
+    let deadline = Instant::now() + timeout;
+    let mut delay = MIN_RETRY_DELAY;
+    loop {
+        if try_lock_handle(&handle, mode)? {
+            return Ok(Some(FileLock { m_file: file, m_mode: mode, m_handle: handle }));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        std::thread::sleep(delay.min(deadline - now));
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+pub(crate) fn lock(file: &File, mode: FileLockMode) -> Result<FileLock, IoError> {
+    lock_path(file.clone(), PathBuf::from(file.to_path_object()), mode)
+}
+
+pub(crate) fn try_lock(file: &File, mode: FileLockMode) -> Result<Option<FileLock>, IoError> {
+    lock_path_timeout(file.clone(), PathBuf::from(file.to_path_object()), mode, Duration::ZERO)
+}
+
+pub(crate) fn lock_timeout(file: &File, mode: FileLockMode, timeout: Duration) -> Result<Option<FileLock>, IoError> {
+    lock_path_timeout(file.clone(), PathBuf::from(file.to_path_object()), mode, timeout)
+}
+
+pub(crate) async fn lock_async(file: &File, mode: FileLockMode) -> Result<FileLock, IoError> {
+    let (file, path) = (file.clone(), PathBuf::from(file.to_path_object()));
+    blocking(move || lock_path(file, path, mode)).await
+}
+
+pub(crate) async fn lock_timeout_async(file: &File, mode: FileLockMode, timeout: Duration) -> Result<Option<FileLock>, IoError> {
+    let (file, path) = (file.clone(), PathBuf::from(file.to_path_object()));
+    blocking(move || lock_path_timeout(file, path, mode, timeout)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_and_exclusive() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("a.lock");
+        let first = file.lock(FileLockMode::Shared).unwrap();
+        assert!(file.exists());
+        let second = file.try_lock(FileLockMode::Shared).unwrap().unwrap();
+        assert_eq!(second.mode(), FileLockMode::Shared);
+        assert!(file.try_lock(FileLockMode::Exclusive).unwrap().is_none());
+        drop(first);
+        assert!(file.try_lock(FileLockMode::Exclusive).unwrap().is_none());
+        drop(second);
+
+        let exclusive = file.try_lock(FileLockMode::Exclusive).unwrap().unwrap();
+        assert!(exclusive.file() == file);
+        assert!(file.try_lock(FileLockMode::Shared).unwrap().is_none());
+        assert!(file.try_lock(FileLockMode::Exclusive).unwrap().is_none());
+        drop(exclusive);
+        assert!(file.try_lock(FileLockMode::Shared).unwrap().is_some());
+    }
+
+    #[test]
+    fn timeout() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("a.lock");
+        let exclusive = file.lock(FileLockMode::Exclusive).unwrap();
+        let start = Instant::now();
+        assert!(file.lock_timeout(FileLockMode::Shared, Duration::from_millis(50)).unwrap().is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // released while waiting
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            drop(exclusive);
+        });
+        assert!(file.lock_timeout(FileLockMode::Exclusive, Duration::from_secs(10)).unwrap().is_some());
+        release.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn lock_async() {
+        let temp = File::create_temp_directory().unwrap();
+        let file = temp.file().resolve_path("a.lock");
+        let shared = file.lock_async(FileLockMode::Shared).await.unwrap();
+        assert!(file.lock_timeout_async(FileLockMode::Exclusive, Duration::from_millis(10)).await.unwrap().is_none());
+        drop(shared);
+        let exclusive = file.lock_async(FileLockMode::Exclusive).await.unwrap();
+        assert_eq!(exclusive.mode(), FileLockMode::Exclusive);
+    }
+}
+
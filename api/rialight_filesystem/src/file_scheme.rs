@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, sync::RwLock};
+use rialight_util::AnyStringType;
+use super::{File, FileScheme, IoError, IoErrorKind, Vfs, VfsMountId, VfsProvider};
+
+/// Registry of custom URL schemes of `File`, such as `cache:` or `mods:`.
+///
+/// A custom scheme maps either to a base directory, like `app:` maps to
+/// the application installation directory, or to a `VfsProvider`, mounted
+/// at the root of the scheme with the lowest priority. Once registered,
+/// `File::new("mods://core/items.json")` refers to the file `core/items.json`
+/// of the scheme's target wherever a `File` is accepted.
+///
+/// Schemes must be registered before constructing `File` objects with them;
+/// until then, a URL with an unknown scheme is taken as a path.
+/// Files of a provider scheme, or of a scheme that has been unregistered,
+/// have no native path: only the virtual file system can read them.
+///
+/// ```ignore
+/// FileSchemes::register_directory("cache", &File::new("app-storage://cache"))?;
+/// FileSchemes::register_provider("mods", VfsZip::open(&File::new("app-storage://mods.zip"))?)?;
+/// let items = File::new("mods://core/items.json").read_utf8()?;
+/// ```
+pub struct FileSchemes;
+
+enum SchemeTarget {
+    Directory(String),
+    Provider(VfsMountId),
+}
+
+static SCHEMES: RwLock<BTreeMap<String, SchemeTarget>> = RwLock::new(BTreeMap::new());
+
+impl FileSchemes {
+    /// Registers a scheme whose files are resolved against a directory.
+    /// Registering a scheme again replaces its target.
+    ///
+    /// Scheme names are case-insensitive. They must start with a letter,
+    /// followed by at least one letter, digit, `+`, `-` or `.`,
+    /// and must not be `file`, `app`, `app-storage` or `temp`.
+    pub fn register_directory(name: impl AnyStringType, directory: &File) -> Result<(), IoError> {
+        let name = validate_name(name.convert())?;
+        let target = SchemeTarget::Directory(directory.to_path_object());
+        FileSchemes::replace(name, Some(target));
+        Ok(())
+    }
+
+    /// Registers a scheme whose files are served by a provider of the
+    /// virtual file system. Registering a scheme again replaces its target.
+    ///
+    /// Mounts made with `Vfs::mount` at locations of the scheme
+    /// overlay the provider.
+    pub fn register_provider(name: impl AnyStringType, provider: impl VfsProvider + 'static) -> Result<(), IoError> {
+        let name = validate_name(name.convert())?;
+        let root = File { m_scheme: FileScheme::Custom(name.clone()), m_path: "/".to_owned() };
+        let target = SchemeTarget::Provider(Vfs::mount(&root, provider, i32::MIN));
+        FileSchemes::replace(name, Some(target));
+        Ok(())
+    }
+
+    /// Removes a scheme, returning whether it was registered.
+    pub fn unregister(name: impl AnyStringType) -> bool {
+        FileSchemes::replace(name.convert().to_lowercase(), None)
+    }
+
+    /// Determines whether a scheme is registered.
+    pub fn is_registered(name: impl AnyStringType) -> bool {
+        SCHEMES.read().unwrap().contains_key(&name.convert().to_lowercase())
+    }
+
+    fn replace(name: String, target: Option<SchemeTarget>) -> bool {
+        let previous = {
+            let mut schemes = SCHEMES.write().unwrap();
+            match target {
+                Some(target) => schemes.insert(name, target),
+                None => schemes.remove(&name),
+            }
+        };
+        if let Some(SchemeTarget::Provider(id)) = previous {
+            Vfs::unmount(id);
+        }
+        previous.is_some()
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, IoError> {
+    let name = name.to_lowercase();
+    let mut chars = name.chars();
+    let valid = chars.next().map(|c| c.is_ascii_alphabetic()) == Some(true)
+        // single letters are Windows drive letters
+        && name.len() > 1
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        && !["file", "app", "app-storage", "temp"].contains(&name.as_str());
+    if !valid {
+        return Err(IoError::new(IoErrorKind::InvalidInput, format!("invalid scheme name: {}", name)));
+    }
+    Ok(name)
+}
+
+/// Splits a URL into its registered custom scheme and the rest, if any.
+pub(crate) fn split(url: &str) -> Option<(String, &str)> {
+    let (name, rest) = url.split_once(':')?;
+    let name = name.to_lowercase();
+    if !SCHEMES.read().unwrap().contains_key(&name) {
+        return None;
+    }
+    Some((name, rest))
+}
+
+/// The native base directory of a directory scheme.
+pub(crate) fn directory(name: &str) -> Option<String> {
+    match SCHEMES.read().unwrap().get(name) {
+        Some(SchemeTarget::Directory(directory)) => Some(directory.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VfsDirectory;
+
+    // the registry is shared by the tests, so that each uses its own schemes
+
+    #[test]
+    fn register_directory() {
+        let temp = File::create_temp_directory().unwrap();
+        temp.file().resolve_path("a.txt").write("alpha").unwrap();
+        FileSchemes::register_directory("Test-Directory", &temp.file()).unwrap();
+        assert!(FileSchemes::is_registered("test-directory"));
+        assert_eq!(File::new("test-directory://a.txt").read_utf8().unwrap(), "alpha");
+        assert_eq!(File::new("TEST-DIRECTORY://a.txt").read_utf8().unwrap(), "alpha");
+        File::new("test-directory://b.txt").write("beta").unwrap();
+        assert_eq!(temp.file().resolve_path("b.txt").read_utf8().unwrap(), "beta");
+
+        assert!(FileSchemes::unregister("test-directory"));
+        assert!(!FileSchemes::unregister("test-directory"));
+        assert!(!FileSchemes::is_registered("test-directory"));
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["file", "APP", "app-storage", "temp", "c", "1a", "a b", "a_b", "a:", ""] {
+            let temp = File::create_temp_directory().unwrap();
+            let r = FileSchemes::register_directory(name, &temp.file());
+            assert!(matches!(r, Err(error) if error.kind() == IoErrorKind::InvalidInput), "{}", name);
+            assert!(!FileSchemes::is_registered(name));
+        }
+    }
+
+    #[test]
+    fn register_provider() {
+        let (first, second) = (File::create_temp_directory().unwrap(), File::create_temp_directory().unwrap());
+        first.file().resolve_path("a.txt").write("first").unwrap();
+        first.file().resolve_path("b.txt").write("first").unwrap();
+        second.file().resolve_path("a.txt").write("second").unwrap();
+
+        FileSchemes::register_provider("test-provider", VfsDirectory::new(&first.file())).unwrap();
+        assert_eq!(File::new("test-provider://a.txt").read_utf8().unwrap(), "first");
+        assert!(File::new("test-provider://b.txt").exists());
+
+        // the previous provider is unmounted
+        FileSchemes::register_provider("test-provider", VfsDirectory::new(&second.file())).unwrap();
+        assert_eq!(File::new("test-provider://a.txt").read_utf8().unwrap(), "second");
+        assert!(!File::new("test-provider://b.txt").exists());
+
+        assert!(FileSchemes::unregister("test-provider"));
+        assert!(File::new("test-provider://a.txt").read_utf8().is_err());
+    }
+}
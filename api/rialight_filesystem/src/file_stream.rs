@@ -0,0 +1,436 @@
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use super::IoError;
+
+/// Mode in which a `FileStream` is opened.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum FileMode {
+    /// Reads an existing file.
+    Read,
+    /// Writes a file, creating it or truncating it.
+    Write,
+    /// Writes at the end of a file, creating it if necessary.
+    Append,
+    /// Reads and writes a file, creating it if necessary, without truncating it.
+    Update,
+}
+
+impl FileMode {
+    fn std_options(&self) -> std::fs::OpenOptions {
+        let mut r = std::fs::OpenOptions::new();
+        match self {
+            FileMode::Read => r.read(true),
+            FileMode::Write => r.write(true).create(true).truncate(true),
+            FileMode::Append => r.read(true).append(true).create(true),
+            FileMode::Update => r.read(true).write(true).create(true).truncate(false),
+        };
+        r
+    }
+}
+
+/// An open file, read and written in parts, as returned by `File::open`.
+///
+/// Reads are buffered. Writing or seeking discards what was read ahead,
+/// so reads and writes may be mixed. `FileStream` also implements the
+/// `Read`, `BufRead`, `Write` and `Seek` traits.
+pub struct FileStream {
+    m_file: BufReader<std::fs::File>,
+    m_mode: FileMode,
+}
+
+impl FileStream {
+    pub(crate) fn open(path: &str, mode: FileMode) -> Result<Self, IoError> {
+        Ok(FileStream { m_file: BufReader::new(mode.std_options().open(path)?), m_mode: mode })
+    }
+
+    /// The mode in which the stream was opened.
+    pub fn mode(&self) -> FileMode {
+        self.m_mode
+    }
+
+    /// Reads bytes into a buffer, returning how many were read.
+    /// Zero is returned at the end of the file.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        self.m_file.read(buffer)
+    }
+
+    /// Reads exactly `length` bytes, failing at the end of the file.
+    pub fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, IoError> {
+        let mut r = vec![0; length];
+        self.m_file.read_exact(&mut r)?;
+        Ok(r)
+    }
+
+    /// Reads the remaining bytes of the file.
+    pub fn read_to_end(&mut self) -> Result<Vec<u8>, IoError> {
+        let mut r = vec![];
+        self.m_file.read_to_end(&mut r)?;
+        Ok(r)
+    }
+
+    /// Reads a line as UTF-8, without its line terminator,
+    /// or `None` at the end of the file.
+    pub fn read_utf8_line(&mut self) -> Result<Option<String>, IoError> {
+        let mut r = String::new();
+        if self.m_file.read_line(&mut r)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(trim_line_terminator(r)))
+    }
+
+    /// Reads bytes at an offset of the file without moving the position.
+    pub fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<usize, IoError> {
+        #[cfg(unix)] {
+            use std::os::unix::fs::FileExt;
+            self.m_file.get_ref().read_at(buffer, offset)
+        }
+        #[cfg(not(unix))] {
+            let position = self.position()?;
+            self.m_file.seek(SeekFrom::Start(offset))?;
+            let r = self.m_file.read(buffer);
+            self.m_file.seek(SeekFrom::Start(position))?;
+            r
+        }
+    }
+
+    /// Writes bytes at the position, or at the end of the file in `Append` mode.
+    pub fn write<B: AsRef<[u8]>>(&mut self, b: B) -> Result<(), IoError> {
+        self.discard_read_ahead()?;
+        self.m_file.get_mut().write_all(b.as_ref())
+    }
+
+    /// Writes bytes at an offset of the file without moving the position.
+    pub fn write_at<B: AsRef<[u8]>>(&mut self, offset: u64, b: B) -> Result<(), IoError> {
+        self.discard_read_ahead()?;
+        #[cfg(unix)] {
+            use std::os::unix::fs::FileExt;
+            self.m_file.get_ref().write_all_at(b.as_ref(), offset)
+        }
+        #[cfg(not(unix))] {
+            let position = self.position()?;
+            self.m_file.seek(SeekFrom::Start(offset))?;
+            let r = self.m_file.get_mut().write_all(b.as_ref());
+            self.m_file.seek(SeekFrom::Start(position))?;
+            r
+        }
+    }
+
+    /// Moves the position, returning it as an offset from the start of the file.
+    pub fn seek(&mut self, position: SeekFrom) -> Result<u64, IoError> {
+        self.m_file.seek(position)
+    }
+
+    /// The position as an offset from the start of the file.
+    pub fn position(&mut self) -> Result<u64, IoError> {
+        self.m_file.stream_position()
+    }
+
+    /// Size of the file in bytes.
+    pub fn size(&self) -> Result<u64, IoError> {
+        Ok(self.m_file.get_ref().metadata()?.len())
+    }
+
+    /// Truncates or extends the file to the given size.
+    pub fn set_size(&mut self, size: u64) -> Result<(), IoError> {
+        self.discard_read_ahead()?;
+        self.m_file.get_ref().set_len(size)
+    }
+
+    /// Flushes the data written to the operating system and waits
+    /// until it reaches the storage device.
+    pub fn sync(&mut self) -> Result<(), IoError> {
+        self.m_file.get_ref().sync_all()
+    }
+
+    fn discard_read_ahead(&mut self) -> Result<(), IoError> {
+        if !self.m_file.buffer().is_empty() {
+            let position = self.m_file.stream_position()?;
+            self.m_file.seek(SeekFrom::Start(position))?;
+        }
+        Ok(())
+    }
+}
+
+impl Read for FileStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.m_file.read(buf)
+    }
+}
+
+impl BufRead for FileStream {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.m_file.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.m_file.consume(amt)
+    }
+}
+
+impl Write for FileStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.discard_read_ahead()?;
+        self.m_file.get_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.m_file.get_mut().flush()
+    }
+}
+
+impl Seek for FileStream {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.m_file.seek(pos)
+    }
+}
+
+/// An open file, read and written in parts asynchronously, as returned by `File::open_async`.
+///
+/// Reads are buffered. Writing or seeking discards what was read ahead,
+/// so reads and writes may be mixed.
+pub struct AsyncFileStream {
+    m_file: tokio::io::BufReader<tokio::fs::File>,
+    m_mode: FileMode,
+}
+
+impl AsyncFileStream {
+    pub(crate) async fn open(path: &str, mode: FileMode) -> Result<Self, IoError> {
+        let file = tokio::fs::OpenOptions::from(mode.std_options()).open(path).await?;
+        Ok(AsyncFileStream { m_file: tokio::io::BufReader::new(file), m_mode: mode })
+    }
+
+    /// The mode in which the stream was opened.
+    pub fn mode(&self) -> FileMode {
+        self.m_mode
+    }
+
+    /// Reads bytes into a buffer, returning how many were read.
+    /// Zero is returned at the end of the file.
+    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<usize, IoError> {
+        self.m_file.read(buffer).await
+    }
+
+    /// Reads exactly `length` bytes, failing at the end of the file.
+    pub async fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, IoError> {
+        let mut r = vec![0; length];
+        self.m_file.read_exact(&mut r).await?;
+        Ok(r)
+    }
+
+    /// Reads the remaining bytes of the file.
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>, IoError> {
+        let mut r = vec![];
+        self.m_file.read_to_end(&mut r).await?;
+        Ok(r)
+    }
+
+    /// Reads a line as UTF-8, without its line terminator,
+    /// or `None` at the end of the file.
+    pub async fn read_utf8_line(&mut self) -> Result<Option<String>, IoError> {
+        let mut r = String::new();
+        if self.m_file.read_line(&mut r).await? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(trim_line_terminator(r)))
+    }
+
+    /// Reads bytes at an offset of the file without moving the position.
+    pub async fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<usize, IoError> {
+        let position = self.position().await?;
+        self.m_file.seek(SeekFrom::Start(offset)).await?;
+        let r = self.m_file.read(buffer).await;
+        self.m_file.seek(SeekFrom::Start(position)).await?;
+        r
+    }
+
+    /// Writes bytes at the position, or at the end of the file in `Append` mode.
+    pub async fn write<B: AsRef<[u8]>>(&mut self, b: B) -> Result<(), IoError> {
+        self.discard_read_ahead().await?;
+        self.m_file.get_mut().write_all(b.as_ref()).await?;
+        // completes the write, which must be done before seeking
+        self.m_file.get_mut().flush().await
+    }
+
+    /// Writes bytes at an offset of the file without moving the position.
+    pub async fn write_at<B: AsRef<[u8]>>(&mut self, offset: u64, b: B) -> Result<(), IoError> {
+        let position = self.position().await?;
+        self.m_file.seek(SeekFrom::Start(offset)).await?;
+        let r = self.m_file.get_mut().write_all(b.as_ref()).await;
+        self.m_file.get_mut().flush().await?;
+        self.m_file.seek(SeekFrom::Start(position)).await?;
+        r
+    }
+
+    /// Moves the position, returning it as an offset from the start of the file.
+    pub async fn seek(&mut self, position: SeekFrom) -> Result<u64, IoError> {
+        self.m_file.seek(position).await
+    }
+
+    /// The position as an offset from the start of the file.
+    pub async fn position(&mut self) -> Result<u64, IoError> {
+        // seeking discards what was read ahead, as does writing
+        self.m_file.seek(SeekFrom::Current(0)).await
+    }
+
+    /// Size of the file in bytes.
+    pub async fn size(&self) -> Result<u64, IoError> {
+        Ok(self.m_file.get_ref().metadata().await?.len())
+    }
+
+    /// Truncates or extends the file to the given size.
+    pub async fn set_size(&mut self, size: u64) -> Result<(), IoError> {
+        self.discard_read_ahead().await?;
+        self.m_file.get_ref().set_len(size).await
+    }
+
+    /// Flushes the data written to the operating system.
+    pub async fn flush(&mut self) -> Result<(), IoError> {
+        self.m_file.get_mut().flush().await
+    }
+
+    /// Flushes the data written to the operating system and waits
+    /// until it reaches the storage device.
+    pub async fn sync(&mut self) -> Result<(), IoError> {
+        self.m_file.get_mut().flush().await?;
+        self.m_file.get_ref().sync_all().await
+    }
+
+    async fn discard_read_ahead(&mut self) -> Result<(), IoError> {
+        if !self.m_file.buffer().is_empty() {
+            self.m_file.seek(SeekFrom::Current(0)).await?;
+        }
+        Ok(())
+    }
+}
+
+fn trim_line_terminator(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::File;
+
+    #[test]
+    fn read_write_seek() {
+        let directory = File::create_temp_directory().unwrap();
+        let file = directory.file().resolve_path("stream.txt");
+        let mut stream = file.open(FileMode::Write).unwrap();
+        assert_eq!(stream.mode(), FileMode::Write);
+        stream.write("first line\r\nsecond line\nthird").unwrap();
+        drop(stream);
+
+        let mut stream = file.open(FileMode::Update).unwrap();
+        assert_eq!(stream.read_utf8_line().unwrap().as_deref(), Some("first line"));
+        assert_eq!(stream.position().unwrap(), 12);
+        // the rest of the file was read ahead, and is discarded before writing
+        stream.write("SECOND").unwrap();
+        assert_eq!(stream.position().unwrap(), 18);
+        assert_eq!(stream.read_utf8_line().unwrap().as_deref(), Some(" line"));
+        assert_eq!(stream.read_to_end().unwrap(), b"third");
+        assert_eq!(stream.read_utf8_line().unwrap(), None);
+
+        assert_eq!(stream.seek(SeekFrom::Start(6)).unwrap(), 6);
+        assert_eq!(stream.read_bytes(4).unwrap(), b"line");
+        assert_eq!(stream.seek(SeekFrom::End(-5)).unwrap(), 24);
+        let mut buffer = [0; 3];
+        assert_eq!(stream.read_at(0, &mut buffer).unwrap(), 3);
+        assert_eq!(&buffer, b"fir");
+        assert_eq!(stream.position().unwrap(), 24);
+        stream.write_at(0, "F").unwrap();
+        assert_eq!(stream.read_to_end().unwrap(), b"third");
+        assert!(stream.read_bytes(1).is_err());
+
+        stream.set_size(5).unwrap();
+        assert_eq!(stream.size().unwrap(), 5);
+        stream.sync().unwrap();
+        drop(stream);
+        assert_eq!(file.read_utf8().unwrap(), "First");
+
+        let mut stream = file.open(FileMode::Append).unwrap();
+        stream.write(" line").unwrap();
+        drop(stream);
+        assert_eq!(file.read_utf8().unwrap(), "First line");
+        assert!(directory.file().resolve_path("missing.txt").open(FileMode::Read).is_err());
+    }
+
+    #[test]
+    fn discard_read_ahead_after_seek() {
+        let directory = File::create_temp_directory().unwrap();
+        let file = directory.file().resolve_path("stream.txt");
+        file.write("0123456789").unwrap();
+        let mut stream = file.open(FileMode::Update).unwrap();
+        assert_eq!(stream.read_bytes(2).unwrap(), b"01");
+        stream.seek(SeekFrom::Current(2)).unwrap();
+        assert_eq!(stream.read_bytes(1).unwrap(), b"4");
+        // the write goes at the position, not at the end of what was read ahead
+        Write::write_all(&mut stream, b"x").unwrap();
+        assert_eq!(stream.read_bytes(1).unwrap(), b"6");
+        stream.seek(SeekFrom::Start(0)).unwrap();
+        assert_eq!(stream.read_to_end().unwrap(), b"01234x6789");
+    }
+
+    #[tokio::test]
+    async fn read_write_seek_async() {
+        let directory = File::create_temp_directory().unwrap();
+        let file = directory.file().resolve_path("stream.txt");
+        let mut stream = file.open_async(FileMode::Write).await.unwrap();
+        assert_eq!(stream.mode(), FileMode::Write);
+        stream.write("first line\r\nsecond line\nthird").await.unwrap();
+        drop(stream);
+
+        let mut stream = file.open_async(FileMode::Update).await.unwrap();
+        assert_eq!(stream.read_utf8_line().await.unwrap().as_deref(), Some("first line"));
+        assert_eq!(stream.position().await.unwrap(), 12);
+        stream.write("SECOND").await.unwrap();
+        assert_eq!(stream.position().await.unwrap(), 18);
+        assert_eq!(stream.read_utf8_line().await.unwrap().as_deref(), Some(" line"));
+        assert_eq!(stream.read_to_end().await.unwrap(), b"third");
+        assert_eq!(stream.read_utf8_line().await.unwrap(), None);
+
+        assert_eq!(stream.seek(SeekFrom::Start(6)).await.unwrap(), 6);
+        assert_eq!(stream.read_bytes(4).await.unwrap(), b"line");
+        assert_eq!(stream.seek(SeekFrom::End(-5)).await.unwrap(), 24);
+        let mut buffer = [0; 3];
+        assert_eq!(stream.read_at(0, &mut buffer).await.unwrap(), 3);
+        assert_eq!(&buffer, b"fir");
+        assert_eq!(stream.position().await.unwrap(), 24);
+        stream.write_at(0, "F").await.unwrap();
+        assert_eq!(stream.read_to_end().await.unwrap(), b"third");
+        assert!(stream.read_bytes(1).await.is_err());
+
+        stream.set_size(5).await.unwrap();
+        assert_eq!(stream.size().await.unwrap(), 5);
+        stream.sync().await.unwrap();
+        drop(stream);
+        assert_eq!(file.read_utf8_async().await.unwrap(), "First");
+
+        let mut stream = file.open_async(FileMode::Append).await.unwrap();
+        stream.write(" line").await.unwrap();
+        stream.flush().await.unwrap();
+        drop(stream);
+        assert_eq!(file.read_utf8_async().await.unwrap(), "First line");
+    }
+
+    #[tokio::test]
+    async fn discard_read_ahead_after_seek_async() {
+        let directory = File::create_temp_directory().unwrap();
+        let file = directory.file().resolve_path("stream.txt");
+        file.write("0123456789").unwrap();
+        let mut stream = file.open_async(FileMode::Update).await.unwrap();
+        assert_eq!(stream.read_bytes(2).await.unwrap(), b"01");
+        stream.seek(SeekFrom::Current(2)).await.unwrap();
+        assert_eq!(stream.read_bytes(1).await.unwrap(), b"4");
+        stream.write("x").await.unwrap();
+        assert_eq!(stream.read_bytes(1).await.unwrap(), b"6");
+        stream.seek(SeekFrom::Start(0)).await.unwrap();
+        assert_eq!(stream.read_to_end().await.unwrap(), b"01234x6789");
+    }
+}
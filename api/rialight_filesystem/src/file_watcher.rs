@@ -0,0 +1,451 @@
+use std::{cell::Cell, collections::HashMap, path::{Path, PathBuf}, sync::mpsc, time::{Duration, Instant}};
+use notify::{Watcher, RecursiveMode, EventKind, event::{ModifyKind, RenameMode}};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, error::TryRecvError};
+use super::{File, IoError, IoErrorKind};
+
+/// Options for watching a file or directory with `File::watch`.
+pub struct FileWatchOptions {
+    m_recursive: Cell<bool>,
+    m_debounce: Cell<Duration>,
+    m_max_wait: Cell<Duration>,
+    m_polling: Cell<bool>,
+    m_poll_interval: Cell<Duration>,
+}
+
+impl Clone for FileWatchOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_recursive: self.m_recursive.clone(),
+            m_debounce: self.m_debounce.clone(),
+            m_max_wait: self.m_max_wait.clone(),
+            m_polling: self.m_polling.clone(),
+            m_poll_interval: self.m_poll_interval.clone(),
+        }
+    }
+}
+
+impl FileWatchOptions {
+    pub fn new() -> Self {
+        FileWatchOptions {
+            m_recursive: Cell::new(true),
+            m_debounce: Cell::new(Duration::from_millis(100)),
+            m_max_wait: Cell::new(Duration::from_secs(1)),
+            m_polling: Cell::new(false),
+            m_poll_interval: Cell::new(Duration::from_secs(1)),
+        }
+    }
+
+    /// Whether to watch the whole tree of a directory
+    /// instead of only its direct entries. Defaults to `true`.
+    pub fn recursive(&self, value: bool) -> &Self {
+        self.m_recursive.set(value);
+        self
+    }
+
+    /// How long the file system must stay quiet before the changes
+    /// observed are reported together. Defaults to 100 milliseconds.
+    pub fn debounce(&self, value: Duration) -> &Self {
+        self.m_debounce.set(value);
+        self
+    }
+
+    /// Longest time observed changes are held back while the file system
+    /// keeps changing, after which they are reported even if it has not
+    /// been quiet for the debounce duration. Defaults to 1 second.
+    pub fn max_wait(&self, value: Duration) -> &Self {
+        self.m_max_wait.set(value);
+        self
+    }
+
+    /// Whether to poll the file system instead of using operating system
+    /// notifications, such as for network drives. Polling is also used
+    /// when notifications are unavailable. Defaults to `false`.
+    pub fn polling(&self, value: bool) -> &Self {
+        self.m_polling.set(value);
+        self
+    }
+
+    /// Interval between scans when polling. Defaults to 1 second.
+    pub fn poll_interval(&self, value: Duration) -> &Self {
+        self.m_poll_interval.set(value);
+        self
+    }
+}
+
+impl Default for FileWatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A change observed by a `FileWatcher`.
+#[derive(Clone)]
+pub enum FileWatchEvent {
+    Created(File),
+    Modified(File),
+    Deleted(File),
+    /// A file or directory was renamed from the first path to the second.
+    Renamed(File, File),
+}
+
+/// Watches a file or directory for changes, as returned by `File::watch`.
+///
+/// Changes are reported in batches, once the file system stays quiet
+/// for the debounce duration, or after the maximum wait if it keeps
+/// changing. Changes to the same path within a batch are combined:
+/// for example, a file created and then modified is reported as created,
+/// and a file created and then deleted is not reported.
+/// Watching stops when the `FileWatcher` is dropped.
+pub struct FileWatcher {
+    // kept for its notifications; dropping it stops watching
+    #[allow(dead_code)]
+    m_watcher: Box<dyn Watcher + Send>,
+    m_receiver: UnboundedReceiver<Result<Vec<FileWatchEvent>, IoError>>,
+}
+
+impl FileWatcher {
+    pub(crate) fn new(file: &File, options: &FileWatchOptions) -> Result<Self, IoError> {
+        // event paths are canonical on some platforms
+        let root_path = std::fs::canonicalize(file.to_path_object())?;
+        let (raw_sender, raw_receiver) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mode = if options.m_recursive.get() { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        let mut native = None;
+        if !options.m_polling.get() {
+            if let Ok(mut watcher) = notify::RecommendedWatcher::new(raw_sender.clone(), notify::Config::default()) {
+                // watching fails when the limit of inotify watches is reached, for example
+                if watcher.watch(&root_path, mode).is_ok() {
+                    native = Some(watcher);
+                }
+            }
+        }
+        let watcher: Box<dyn Watcher + Send> = match native {
+            Some(watcher) => Box::new(watcher),
+            None => {
+                let mut watcher = poll_watcher(raw_sender, options)?;
+                watcher.watch(&root_path, mode).map_err(convert_error)?;
+                Box::new(watcher)
+            },
+        };
+
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (debounce, max_wait) = (options.m_debounce.get(), options.m_max_wait.get());
+        let root = Root { m_file: file.clone(), m_path: root_path };
+        std::thread::spawn(move || debounce_events(raw_receiver, sender, debounce, max_wait, root));
+
+        Ok(FileWatcher { m_watcher: watcher, m_receiver: receiver })
+    }
+
+    /// Blocks until the next batch of changes. Returns `None` once
+    /// watching has stopped. This must not be called from asynchronous code.
+    pub fn next_changes(&mut self) -> Option<Result<Vec<FileWatchEvent>, IoError>> {
+        self.m_receiver.blocking_recv()
+    }
+
+    /// Waits for the next batch of changes. Returns `None` once watching has stopped.
+    pub async fn next_changes_async(&mut self) -> Option<Result<Vec<FileWatchEvent>, IoError>> {
+        self.m_receiver.recv().await
+    }
+
+    /// Returns the next batch of changes if one is ready, without waiting.
+    pub fn try_next_changes(&mut self) -> Option<Result<Vec<FileWatchEvent>, IoError>> {
+        match self.m_receiver.try_recv() {
+            Ok(r) => Some(r),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+}
+
+fn poll_watcher(sender: mpsc::Sender<notify::Result<notify::Event>>, options: &FileWatchOptions) -> Result<notify::PollWatcher, IoError> {
+    let config = notify::Config::default().with_poll_interval(options.m_poll_interval.get());
+    notify::PollWatcher::new(sender, config).map_err(convert_error)
+}
+
+fn convert_error(error: notify::Error) -> IoError {
+    match error.kind {
+        notify::ErrorKind::Io(error) => error,
+        notify::ErrorKind::PathNotFound => IoError::new(IoErrorKind::NotFound, "path not found"),
+        kind => IoError::new(IoErrorKind::Other, format!("{:?}", kind)),
+    }
+}
+
+/// The watched file, used to convert event paths into `File` objects
+/// with the same scheme.
+struct Root {
+    m_file: File,
+    m_path: PathBuf,
+}
+
+impl Root {
+    fn file(&self, path: &Path) -> Option<File> {
+        let relative = path.strip_prefix(&self.m_path).ok()?;
+        let mut r = self.m_file.clone();
+        for component in relative.components() {
+            r = r.resolve_path(component.as_os_str().to_str()?);
+        }
+        Some(r)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Change {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// Changes of a batch, in order of first occurrence per path.
+struct Batch {
+    m_events: Vec<BatchEvent>,
+    m_changes: HashMap<PathBuf, usize>,
+    // sources of renames whose other half has not been seen, by tracker
+    m_rename_sources: HashMap<usize, PathBuf>,
+}
+
+enum BatchEvent {
+    Change(PathBuf, Option<Change>),
+    Renamed(PathBuf, PathBuf),
+}
+
+impl Batch {
+    fn new() -> Self {
+        Batch { m_events: vec![], m_changes: HashMap::new(), m_rename_sources: HashMap::new() }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.m_events.is_empty() && self.m_rename_sources.is_empty()
+    }
+
+    fn change(&mut self, path: PathBuf, change: Change) {
+        if let Some(&i) = self.m_changes.get(&path) {
+            if let BatchEvent::Change(_, previous) = &mut self.m_events[i] {
+                *previous = combine(*previous, change);
+            }
+            return;
+        }
+        self.m_changes.insert(path.clone(), self.m_events.len());
+        self.m_events.push(BatchEvent::Change(path, Some(change)));
+    }
+
+    fn rename(&mut self, from: PathBuf, to: PathBuf) {
+        // later changes to either path start a new entry
+        self.m_changes.remove(&from);
+        self.m_changes.remove(&to);
+        self.m_events.push(BatchEvent::Renamed(from, to));
+    }
+
+    fn add(&mut self, event: notify::Event) {
+        let tracker = event.attrs.tracker();
+        let mut paths = event.paths.into_iter();
+        match event.kind {
+            EventKind::Create(_) => paths.for_each(|p| self.change(p, Change::Created)),
+            EventKind::Remove(_) => paths.for_each(|p| self.change(p, Change::Deleted)),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                if let Some(tracker) = tracker {
+                    self.m_rename_sources.remove(&tracker);
+                }
+                if let (Some(from), Some(to)) = (paths.next(), paths.next()) {
+                    self.rename(from, to);
+                }
+            },
+            // halves of a rename are followed by an event with both paths,
+            // unless the file moved across the watched tree
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => match (tracker, paths.next()) {
+                (Some(tracker), Some(from)) => { self.m_rename_sources.insert(tracker, from); },
+                (None, Some(from)) => self.change(from, Change::Deleted),
+                _ => {},
+            },
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                if tracker.map(|t| self.m_rename_sources.contains_key(&t)) != Some(true) {
+                    paths.for_each(|p| self.change(p, Change::Created));
+                }
+            },
+            EventKind::Modify(ModifyKind::Name(_)) => paths.for_each(|p| {
+                let change = if p.exists() { Change::Created } else { Change::Deleted };
+                self.change(p, change);
+            }),
+            EventKind::Modify(_) | EventKind::Any => paths.for_each(|p| self.change(p, Change::Modified)),
+            EventKind::Access(_) | EventKind::Other => {},
+        }
+    }
+
+    fn into_events(mut self, root: &Root) -> Vec<FileWatchEvent> {
+        for (_, from) in std::mem::take(&mut self.m_rename_sources) {
+            self.change(from, Change::Deleted);
+        }
+        self.m_events.into_iter().filter_map(|event| match event {
+            BatchEvent::Change(path, Some(Change::Created)) => Some(FileWatchEvent::Created(root.file(&path)?)),
+            BatchEvent::Change(path, Some(Change::Modified)) => Some(FileWatchEvent::Modified(root.file(&path)?)),
+            BatchEvent::Change(path, Some(Change::Deleted)) => Some(FileWatchEvent::Deleted(root.file(&path)?)),
+            BatchEvent::Change(_, None) => None,
+            // a rename across the watched tree is a creation or a deletion
+            BatchEvent::Renamed(from, to) => match (root.file(&from), root.file(&to)) {
+                (Some(from), Some(to)) => Some(FileWatchEvent::Renamed(from, to)),
+                (None, Some(to)) => Some(FileWatchEvent::Created(to)),
+                (Some(from), None) => Some(FileWatchEvent::Deleted(from)),
+                (None, None) => None,
+            },
+        }).collect()
+    }
+}
+
+/// Combines two successive changes to the same path,
+/// returning `None` when they cancel out.
+fn combine(previous: Option<Change>, next: Change) -> Option<Change> {
+    match (previous, next) {
+        (Some(Change::Created), Change::Modified) => Some(Change::Created),
+        (Some(Change::Created), Change::Deleted) => None,
+        (Some(Change::Deleted), Change::Created) => Some(Change::Modified),
+        (_, next) => Some(next),
+    }
+}
+
+fn debounce_events(
+    raw_receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    sender: UnboundedSender<Result<Vec<FileWatchEvent>, IoError>>,
+    debounce: Duration,
+    max_wait: Duration,
+    root: Root,
+) {
+    // ends when the watcher, which owns the raw sender, is dropped
+    while let Ok(first) = raw_receiver.recv() {
+        let mut batch = Batch::new();
+        let deadline = Instant::now() + max_wait;
+        let mut next = Ok(first);
+        loop {
+            match next {
+                Ok(Ok(event)) => batch.add(event),
+                Ok(Err(error)) => {
+                    if sender.send(Err(convert_error(error))).is_err() {
+                        return;
+                    }
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            next = raw_receiver.recv_timeout(debounce.min(deadline - now));
+        }
+        if !batch.is_empty() {
+            let events = batch.into_events(&root);
+            if !events.is_empty() && sender.send(Ok(events)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+
+    fn root() -> Root {
+        Root { m_file: File::new("/w"), m_path: PathBuf::from("/w") }
+    }
+
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Event {
+        paths.iter().fold(notify::Event::new(kind), |e, p| e.add_path(PathBuf::from(p)))
+    }
+
+    fn describe(events: Vec<FileWatchEvent>) -> Vec<String> {
+        events.into_iter().map(|event| match event {
+            FileWatchEvent::Created(f) => format!("created {}", f.native_path()),
+            FileWatchEvent::Modified(f) => format!("modified {}", f.native_path()),
+            FileWatchEvent::Deleted(f) => format!("deleted {}", f.native_path()),
+            FileWatchEvent::Renamed(a, b) => format!("renamed {} {}", a.native_path(), b.native_path()),
+        }).collect()
+    }
+
+    #[test]
+    fn combined_changes() {
+        let mut batch = Batch::new();
+        batch.add(event(EventKind::Create(CreateKind::File), &["/w/a"]));
+        batch.add(event(EventKind::Modify(ModifyKind::Any), &["/w/a"]));
+        batch.add(event(EventKind::Create(CreateKind::File), &["/w/b"]));
+        batch.add(event(EventKind::Remove(RemoveKind::File), &["/w/b"]));
+        batch.add(event(EventKind::Remove(RemoveKind::File), &["/w/c"]));
+        batch.add(event(EventKind::Create(CreateKind::File), &["/w/c"]));
+        batch.add(event(EventKind::Access(notify::event::AccessKind::Any), &["/w/d"]));
+        batch.add(event(EventKind::Modify(ModifyKind::Any), &["/outside/e"]));
+        assert_eq!(describe(batch.into_events(&root())), ["created /w/a", "modified /w/c"]);
+    }
+
+    #[test]
+    fn renames() {
+        let mut batch = Batch::new();
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::From)), &["/w/a"]).set_tracker(1));
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), &["/w/b"]).set_tracker(1));
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &["/w/a", "/w/b"]).set_tracker(1));
+        // halves without the other are a deletion and a creation
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::From)), &["/w/c"]).set_tracker(2));
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::To)), &["/w/d"]).set_tracker(3));
+        // renames across the watched tree
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &["/outside/e", "/w/e"]));
+        batch.add(event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), &["/w/f", "/outside/f"]));
+        assert_eq!(describe(batch.into_events(&root())), [
+            "renamed /w/a /w/b",
+            "created /w/d",
+            "created /w/e",
+            "deleted /w/f",
+            "deleted /w/c",
+        ]);
+    }
+
+    /// Sends an event every 10 milliseconds for 600 milliseconds, returning the batch sizes.
+    fn debounce_continuous_changes(debounce: Duration, max_wait: Duration) -> Vec<usize> {
+        let (raw_sender, raw_receiver) = mpsc::channel();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let thread = std::thread::spawn(move || debounce_events(raw_receiver, sender, debounce, max_wait, root()));
+        for i in 0..60 {
+            raw_sender.send(Ok(event(EventKind::Create(CreateKind::File), &[&format!("/w/{}", i)]))).unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        drop(raw_sender);
+        thread.join().unwrap();
+        let mut r = vec![];
+        while let Ok(batch) = receiver.try_recv() {
+            r.push(batch.unwrap().len());
+        }
+        r
+    }
+
+    #[test]
+    fn max_wait() {
+        assert_eq!(debounce_continuous_changes(Duration::from_millis(200), Duration::from_secs(10)), [60]);
+        let r = debounce_continuous_changes(Duration::from_millis(200), Duration::from_millis(150));
+        assert!(r.len() >= 3, "{:?}", r);
+        assert_eq!(r.iter().sum::<usize>(), 60);
+    }
+
+    #[tokio::test]
+    async fn watch() {
+        for polling in [false, true] {
+            let directory = File::create_temp_directory().unwrap();
+            let options = FileWatchOptions::new();
+            options.debounce(Duration::from_millis(50)).polling(polling).poll_interval(Duration::from_millis(50));
+            let mut watcher = directory.file().watch(&options).unwrap();
+            let file = directory.file().resolve_path("a.txt");
+            file.write("a").unwrap();
+            next_changes_with(&mut watcher, &format!("created {}", file.native_path())).await;
+            file.delete_file().unwrap();
+            // the write may end with changes of its own before the deletion,
+            // and the directory may be reported as modified along with it
+            let events = next_changes_with(&mut watcher, &format!("deleted {}", file.native_path())).await;
+            let events: Vec<&String> = events.iter().filter(|e| e.ends_with(&file.native_path())).collect();
+            assert_eq!(events, [&format!("deleted {}", file.native_path())]);
+        }
+    }
+
+    /// Waits for the batch of changes with an event.
+    async fn next_changes_with(watcher: &mut FileWatcher, event: &str) -> Vec<String> {
+        loop {
+            let events = tokio::time::timeout(Duration::from_secs(10), watcher.next_changes_async()).await.unwrap().unwrap().unwrap();
+            let events = describe(events);
+            if events.iter().any(|e| e == event) {
+                return events;
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+use rialight_util::AnyStringType;
+use super::{File, FileScheme, IoError, IoErrorKind, DirectoryWalkOptions, glob_helpers};
+
+/// A glob pattern matching `File` objects.
+///
+/// The pattern is either a path or a URL with one of the schemes
+/// supported by `File`, such as `app://res/lang/**/*.json`.
+/// Relative patterns are resolved as `File::new` resolves paths,
+/// or against a directory when given to `File::glob`.
+/// Components are separated by `/` and support the following syntax:
+///
+/// - `**` as a whole component matches zero or more components.
+/// - `*` matches zero or more characters within a component.
+/// - `?` matches one character within a component.
+/// - `[abc]`, `[a-z]` and `[!a-z]` match one character of a class.
+/// - `{a,b}` matches either alternative, which may contain patterns.
+/// - `\` matches the next character literally.
+///
+/// A leading `!` negates the pattern.
+#[derive(Clone)]
+pub struct Glob {
+    m_pattern: String,
+    m_negated: bool,
+    m_alternatives: Vec<GlobAlternative>,
+}
+
+/// A pattern without braces, split into the directory
+/// preceding its first wildcard and the rest.
+#[derive(Clone)]
+struct GlobAlternative {
+    m_base: File,
+    m_rest: String,
+}
+
+impl Glob {
+    /// Constructs a new `Glob` object.
+    pub fn new(pattern: impl AnyStringType) -> Self {
+        Glob::parse(pattern.convert(), None)
+    }
+
+    pub(crate) fn parse(pattern: &str, directory: Option<&File>) -> Self {
+        let (negated, body) = match pattern.strip_prefix('!') {
+            Some(body) => (true, body),
+            None => (false, pattern),
+        };
+        let alternatives = glob_helpers::expand_braces(body).iter().map(|alternative| {
+            let (base, rest) = split_base(alternative);
+            let base = match directory {
+                Some(directory) if !has_scheme(alternative) => directory.resolve_path(base),
+                _ => File::new(base),
+            };
+            GlobAlternative { m_base: base, m_rest: rest.to_owned() }
+        }).collect();
+        Glob { m_pattern: pattern.to_owned(), m_negated: negated, m_alternatives: alternatives }
+    }
+
+    /// The pattern as given.
+    pub fn pattern(&self) -> String {
+        self.m_pattern.clone()
+    }
+
+    /// Determines whether the pattern starts with `!`.
+    pub fn is_negated(&self) -> bool {
+        self.m_negated
+    }
+
+    /// Determines whether the pattern matches a `File`, comparing URLs.
+    pub fn matches(&self, file: &File) -> bool {
+        let url = file.url();
+        let r = self.m_alternatives.iter().any(|alternative| {
+            let base = alternative.m_base.url();
+            if alternative.m_rest.is_empty() {
+                return url == base;
+            }
+            let prefix = if base.ends_with('/') { base } else { base + "/" };
+            match url.strip_prefix(&prefix) {
+                Some(relative) => glob_helpers::matches(&alternative.m_rest, relative),
+                None => false,
+            }
+        });
+        r != self.m_negated
+    }
+
+    /// Determines whether the pattern matches a `/`-separated
+    /// path, without resolving either.
+    pub fn matches_path(&self, path: impl AnyStringType) -> bool {
+        let body = if self.m_negated { &self.m_pattern[1..] } else { &self.m_pattern[..] };
+        glob_helpers::matches(body, path.convert()) != self.m_negated
+    }
+
+    /// Returns the existing files and directories matched by the pattern,
+    /// sorted by name within each directory.
+    ///
+    /// Fails with `InvalidInput` if the pattern is negated.
+    pub fn expand(&self) -> Result<Vec<File>, IoError> {
+        if self.m_negated {
+            return Err(IoError::new(IoErrorKind::InvalidInput, format!("cannot expand negated pattern {}", self.m_pattern)));
+        }
+        let mut r = vec![];
+        let mut found = HashSet::<String>::new();
+        for alternative in self.m_alternatives.iter() {
+            for file in alternative.expand()? {
+                if found.insert(file.url()) {
+                    r.push(file);
+                }
+            }
+        }
+        Ok(r)
+    }
+
+    /// Returns the existing files and directories matched by the pattern,
+    /// sorted by name within each directory.
+    ///
+    /// Fails with `InvalidInput` if the pattern is negated.
+    pub async fn expand_async(&self) -> Result<Vec<File>, IoError> {
+        if self.m_negated {
+            return Err(IoError::new(IoErrorKind::InvalidInput, format!("cannot expand negated pattern {}", self.m_pattern)));
+        }
+        let mut r = vec![];
+        let mut found = HashSet::<String>::new();
+        for alternative in self.m_alternatives.iter() {
+            for file in alternative.expand_async().await? {
+                if found.insert(file.url()) {
+                    r.push(file);
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+impl GlobAlternative {
+    fn walk_options(&self) -> DirectoryWalkOptions {
+        let options = DirectoryWalkOptions::new();
+        options.sorted(true);
+        if !self.m_rest.split('/').any(|c| c == "**") {
+            options.max_depth(self.m_rest.split('/').filter(|c| !c.is_empty()).count());
+        }
+        options
+    }
+
+    fn expand(&self) -> Result<Vec<File>, IoError> {
+        if self.m_rest.is_empty() {
+            return Ok(if self.m_base.exists() { vec![self.m_base.clone()] } else { vec![] });
+        }
+        if !self.m_base.is_directory() {
+            return Ok(vec![]);
+        }
+        let mut r = vec![];
+        for entry in self.m_base.walk(&self.walk_options()) {
+            let entry = entry?;
+            if glob_helpers::matches(&self.m_rest, &entry.relative_path()) {
+                r.push(entry.file());
+            }
+        }
+        Ok(r)
+    }
+
+    async fn expand_async(&self) -> Result<Vec<File>, IoError> {
+        if self.m_rest.is_empty() {
+            return Ok(if self.m_base.exists() { vec![self.m_base.clone()] } else { vec![] });
+        }
+        if !self.m_base.is_directory() {
+            return Ok(vec![]);
+        }
+        let mut r = vec![];
+        let mut walk = self.m_base.walk_async(&self.walk_options());
+        while let Some(entry) = walk.next_entry().await {
+            let entry = entry?;
+            if glob_helpers::matches(&self.m_rest, &entry.relative_path()) {
+                r.push(entry.file());
+            }
+        }
+        Ok(r)
+    }
+}
+
+/// A list of glob patterns where later patterns override earlier ones,
+/// so that negated patterns exclude what previous patterns matched,
+/// as in `["res/**", "!res/**/*.psd"]`.
+#[derive(Clone)]
+pub struct GlobSet {
+    m_globs: Vec<Glob>,
+}
+
+impl GlobSet {
+    /// Constructs a new `GlobSet` object.
+    pub fn new(patterns: Vec<impl AnyStringType>) -> Self {
+        GlobSet { m_globs: patterns.iter().map(|p| Glob::new(p.convert())).collect() }
+    }
+
+    /// Determines whether the last pattern matching a `File` is not negated.
+    pub fn matches(&self, file: &File) -> bool {
+        self.last_match(|glob| glob.matches(file))
+    }
+
+    /// Determines whether the last pattern matching a `/`-separated
+    /// path is not negated.
+    pub fn matches_path(&self, path: impl AnyStringType) -> bool {
+        let path = path.convert();
+        self.last_match(|glob| glob.matches_path(path))
+    }
+
+    fn last_match(&self, matches: impl Fn(&Glob) -> bool) -> bool {
+        // a negated glob matches what its pattern does not, so
+        // its pattern matching is the glob not matching
+        self.m_globs.iter().rev()
+            .find(|glob| matches(glob) != glob.m_negated)
+            .map(|glob| !glob.m_negated)
+            .unwrap_or(false)
+    }
+
+    /// Returns the existing files and directories matched by the set.
+    pub fn expand(&self) -> Result<Vec<File>, IoError> {
+        let mut r = vec![];
+        let mut found = HashSet::<String>::new();
+        for glob in self.m_globs.iter().filter(|glob| !glob.m_negated) {
+            for file in glob.expand()? {
+                if self.matches(&file) && found.insert(file.url()) {
+                    r.push(file);
+                }
+            }
+        }
+        Ok(r)
+    }
+
+    /// Returns the existing files and directories matched by the set.
+    pub async fn expand_async(&self) -> Result<Vec<File>, IoError> {
+        let mut r = vec![];
+        let mut found = HashSet::<String>::new();
+        for glob in self.m_globs.iter().filter(|glob| !glob.m_negated) {
+            for file in glob.expand_async().await? {
+                if self.matches(&file) && found.insert(file.url()) {
+                    r.push(file);
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+fn has_scheme(pattern: &str) -> bool {
+    FileScheme::split(pattern).is_some()
+}
+
+/// Splits a pattern before its first component with glob syntax.
+fn split_base(pattern: &str) -> (&str, &str) {
+    let mut start = 0;
+    for component in pattern.split('/') {
+        if glob_helpers::has_glob_syntax(component) {
+            return (&pattern[..start], &pattern[start..]);
+        }
+        start += component.len() + 1;
+    }
+    (pattern, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negation() {
+        let glob = Glob::new("!**/*.psd");
+        assert!(glob.is_negated());
+        assert_eq!(glob.pattern(), "!**/*.psd");
+        assert!(glob.matches_path("res/a.png"));
+        assert!(!glob.matches_path("res/a.psd"));
+        assert!(Glob::new("/res/**/*.png").matches(&File::new("/res/img/a.png")));
+        assert!(!Glob::new("/res/**/*.png").matches(&File::new("/other/a.png")));
+        assert!(Glob::new("!/res/**/*.png").matches(&File::new("/other/a.png")));
+        assert!(Glob::new("/res/{img,ui}/*.png").matches(&File::new("/res/ui/a.png")));
+    }
+
+    #[test]
+    fn glob_set_precedence() {
+        let set = GlobSet::new(vec!["res/**", "!res/**/*.psd", "res/keep/*.psd"]);
+        assert!(set.matches_path("res/a.png"));
+        assert!(!set.matches_path("res/img/a.psd"));
+        // later patterns override earlier ones
+        assert!(set.matches_path("res/keep/a.psd"));
+        assert!(!set.matches_path("other/a.png"));
+        assert!(!GlobSet::new(vec!["!res/**"]).matches_path("other/a.png"));
+        assert!(!GlobSet::new(Vec::<&str>::new()).matches_path("a"));
+    }
+
+    #[test]
+    fn expand() {
+        let root = File::create_temp_directory().unwrap();
+        let root = root.file();
+        for name in ["res/a.png", "res/b.psd", "res/img/c.png", "res/img/d.psd", "res/keep/e.psd", "other.png"] {
+            let file = root.resolve_path(name);
+            file.parent().unwrap().create_directory().unwrap();
+            file.write(name).unwrap();
+        }
+        let prefix = root.url() + "/";
+        let relative = |files: Vec<File>| -> Vec<String> { files.iter().map(|f| f.url().strip_prefix(&prefix).unwrap().to_owned()).collect() };
+        assert_eq!(relative(root.glob("res/*.png").unwrap()), ["res/a.png"]);
+        assert_eq!(relative(root.glob("res/**/*.png").unwrap()), ["res/a.png", "res/img/c.png"]);
+        assert_eq!(relative(root.glob("res/{img,keep}/*.psd").unwrap()), ["res/img/d.psd", "res/keep/e.psd"]);
+        assert_eq!(relative(root.glob("res/[a-b].*").unwrap()), ["res/a.png", "res/b.psd"]);
+        assert_eq!(relative(root.glob("res/img").unwrap()), ["res/img"]);
+        assert!(root.glob("missing/*").unwrap().is_empty());
+        assert!(matches!(root.glob("!res/*"), Err(error) if error.kind() == IoErrorKind::InvalidInput));
+
+        let base = root.url();
+        let set = GlobSet::new(vec![format!("{}/res/**", base), format!("!{}/res/**/*.psd", base), format!("{}/res/keep/*.psd", base)]);
+        assert_eq!(relative(set.expand().unwrap()), ["res/a.png", "res/img", "res/img/c.png", "res/keep", "res/keep/e.psd"]);
+    }
+
+    #[tokio::test]
+    async fn expand_async() {
+        let root = File::create_temp_directory().unwrap();
+        let root = root.file();
+        root.resolve_path("a").create_directory().unwrap();
+        root.resolve_path("a/x.txt").write("").unwrap();
+        root.resolve_path("y.txt").write("").unwrap();
+        let urls = |files: Vec<File>| -> Vec<String> { files.iter().map(|f| f.url()).collect() };
+        assert_eq!(urls(root.glob_async("**/*.txt").await.unwrap()), [root.url() + "/a/x.txt", root.url() + "/y.txt"]);
+        let set = GlobSet::new(vec![format!("{}/**/*.txt", root.url()), format!("!{}/a/**", root.url())]);
+        assert_eq!(urls(set.expand_async().await.unwrap()), [root.url() + "/y.txt"]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Matching of glob patterns against `/`-separated paths.
+//!
+//! - `**` as a whole component matches zero or more components.
+//! - `*` matches zero or more characters within a component.
+//! - `?` matches one character within a component.
+//! - `[abc]`, `[a-z]` and `[!a-z]` match one character of a class.
+//! - `{a,b}` matches either alternative, which may contain patterns.
+//! - `\` matches the next character literally.
+
+/// Determines whether a glob pattern matches a path.
+/// Both are split into components by `/`.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let path: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    expand_braces(pattern).iter().any(|pattern| {
+        let pattern: Vec<&str> = pattern.split('/').filter(|c| !c.is_empty()).collect();
+        matches_components(&pattern, &path)
+    })
+}
+
+/// Expands brace alternations into the patterns they stand for,
+/// such as `*.{png,jpg}` into `*.png` and `*.jpg`.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '{' => {
+                if let Some((alternatives, end)) = brace_alternatives(&chars, i) {
+                    let prefix: String = chars[..i].iter().collect();
+                    let suffix: String = chars[end + 1..].iter().collect();
+                    return alternatives.iter()
+                        .flat_map(|alternative| expand_braces(&(prefix.clone() + alternative + &suffix)))
+                        .collect();
+                }
+                i += 1;
+            },
+            _ => i += 1,
+        }
+    }
+    vec![pattern.to_owned()]
+}
+
+/// Splits the alternatives of the brace at `start` by its top-level commas,
+/// returning them with the position of the closing brace.
+fn brace_alternatives(chars: &[char], start: usize) -> Option<(Vec<String>, usize)> {
+    let mut alternatives = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                current.push(chars[i]);
+                current.push(chars[i + 1]);
+                i += 1;
+            },
+            '{' => {
+                depth += 1;
+                current.push('{');
+            },
+            '}' if depth == 0 => {
+                alternatives.push(current);
+                return Some((alternatives, i));
+            },
+            '}' => {
+                depth -= 1;
+                current.push('}');
+            },
+            ',' if depth == 0 => alternatives.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Determines whether a path component contains glob syntax.
+pub fn has_glob_syntax(component: &str) -> bool {
+    component.contains(['*', '?', '[', '{', '\\'])
+}
+
+fn matches_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(&"**") => {
+            // `**` consumes any number of components
+            (0..=path.len()).any(|i| matches_components(&pattern[1..], &path[i..]))
+        },
+        Some(component_pattern) => {
+            !path.is_empty()
+                && matches_component(&component_pattern.chars().collect::<Vec<_>>(), &path[0].chars().collect::<Vec<_>>())
+                && matches_components(&pattern[1..], &path[1..])
+        },
+    }
+}
+
+fn matches_component(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|i| matches_component(&pattern[1..], &name[i..])),
+        Some('?') => !name.is_empty() && matches_component(&pattern[1..], &name[1..]),
+        Some('[') => {
+            if let Some((matched, rest)) = name.first().and_then(|&c| match_class(&pattern[1..], c)) {
+                return matched && matches_component(rest, &name[1..]);
+            }
+            // an unterminated class is a literal `[`
+            name.first() == Some(&'[') && matches_component(&pattern[1..], &name[1..])
+        },
+        Some('\\') if pattern.len() > 1 => name.first() == Some(&pattern[1]) && matches_component(&pattern[2..], &name[1..]),
+        Some(&c) => name.first() == Some(&c) && matches_component(&pattern[1..], &name[1..]),
+    }
+}
+
+/// Matches a character against the class following `[`, returning
+/// whether it matched and the pattern after the closing `]`.
+fn match_class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let mut i = 0;
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let mut low = pattern[i];
+        if low == ']' && !first {
+            return Some((matched != negated, &pattern[i + 1..]));
+        }
+        first = false;
+        if low == '\\' && i + 1 < pattern.len() {
+            i += 1;
+            low = pattern[i];
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            let high = pattern[i + 2];
+            matched |= low <= c && c <= high;
+            i += 3;
+        } else {
+            matched |= low == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.json", "en.json"));
+        assert!(!matches("*.json", "lang/en.json"));
+        assert!(matches("lang/*/?.json", "lang/pt/a.json"));
+        assert!(!matches("lang/*/?.json", "lang/pt/ab.json"));
+        assert!(matches("**", ""));
+        assert!(matches("**/*.json", "en.json"));
+        assert!(matches("**/*.json", "lang/pt/en.json"));
+        assert!(matches("lang/**/en.json", "lang/en.json"));
+        assert!(matches("lang/**/en.json", "lang/a/b/en.json"));
+        assert!(!matches("lang/**/en.json", "res/lang/en.json"));
+        // `**` within a component is `*`
+        assert!(matches("a**b", "axyb"));
+        assert!(!matches("a**b", "ax/yb"));
+    }
+
+    #[test]
+    fn braces() {
+        assert_eq!(expand_braces("*.{png,jpg}"), ["*.png", "*.jpg"]);
+        assert_eq!(expand_braces("{a,b{c,d}}/x"), ["a/x", "bc/x", "bd/x"]);
+        assert_eq!(expand_braces("{a,}b"), ["ab", "b"]);
+        assert_eq!(expand_braces("\\{a,b}"), ["\\{a,b}"]);
+        assert_eq!(expand_braces("{a,b"), ["{a,b"]);
+        assert!(matches("img/*.{png,jpg}", "img/a.jpg"));
+        assert!(!matches("img/*.{png,jpg}", "img/a.gif"));
+        assert!(matches("{lang/**,res}/*.json", "lang/a/b.json"));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(matches("[abc].txt", "b.txt"));
+        assert!(!matches("[abc].txt", "d.txt"));
+        assert!(matches("[a-z]1", "q1"));
+        assert!(!matches("[a-z]1", "Q1"));
+        assert!(matches("[!a-z]1", "Q1"));
+        assert!(matches("[^a-z]1", "Q1"));
+        assert!(!matches("[!a-z]1", "q1"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[\\]]", "]"));
+        // an unterminated class is literal
+        assert!(matches("[ab", "[ab"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches("\\*.txt", "*.txt"));
+        assert!(!matches("\\*.txt", "a.txt"));
+        assert!(matches("a\\?", "a?"));
+        assert!(has_glob_syntax("a\\b"));
+        assert!(!has_glob_syntax("a.txt"));
+    }
+}
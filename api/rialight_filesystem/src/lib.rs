@@ -1,12 +1,43 @@
 #![allow(unused_assignments)]
 
-use std::{path::{Path, PathBuf}, time::SystemTime};
+use std::{collections::HashSet, path::{Path, PathBuf}, time::{Duration, SystemTime}};
 use lazy_regex::{regex_find, regex_replace, regex_is_match};
 use sv_str::SvStr;
 use rialight_util::{AnyStringType};
 use gc::{Trace, Finalize};
 
 mod path_helpers;
+mod glob_helpers;
+
+mod file_stream;
+pub use file_stream::{FileStream, AsyncFileStream, FileMode};
+
+mod directory_walk;
+pub use directory_walk::{DirectoryWalk, AsyncDirectoryWalk, DirectoryWalkOptions, DirectoryEntry};
+
+mod glob;
+pub use glob::{Glob, GlobSet};
+
+mod file_watcher;
+pub use file_watcher::{FileWatcher, FileWatchOptions, FileWatchEvent};
+
+mod vfs;
+pub use vfs::{Vfs, VfsMountId, VfsProvider, VfsEntry, VfsDirectory, VfsZip, VfsPack};
+
+mod archive;
+pub use archive::{ZipArchive, ZipArchiveWriter, ZipEntry, ZipEntryReader, ZipEntryOptions, ZipCompression};
+
+mod file_scheme;
+pub use file_scheme::FileSchemes;
+
+mod temp;
+pub use temp::{TempFile, TempDirectory};
+
+mod atomic_write;
+pub use atomic_write::AtomicWriteOptions;
+
+mod file_lock;
+pub use file_lock::{FileLock, FileLockMode};
 
 #[doc(hidden)]
 pub static mut APPLICATION_DIRECTORY: Option<String> = None;
@@ -16,6 +47,12 @@ pub static mut APPLICATION_STORAGE_DIRECTORY: Option<String> = None;
 pub type IoError = std::io::Error;
 pub type IoErrorKind = std::io::ErrorKind;
 
+/// Runs a blocking operation on the thread pool of the Tokio runtime
+/// for blocking operations.
+pub(crate) async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T, IoError> + Send + 'static) -> Result<T, IoError> {
+    tokio::task::spawn_blocking(f).await.unwrap_or_else(|error| Err(IoError::new(IoErrorKind::Other, error)))
+}
+
 /// Represents a path to a file or directory.
 ///
 /// # Constructing a `File` object
@@ -25,9 +62,15 @@ pub type IoErrorKind = std::io::ErrorKind;
 /// - `file:`
 /// - `app:` file in the application installation directory
 /// - `app-storage:` file in the application private directory
+/// - `temp:` file in a temporary directory of the running process,
+///   removed when the process exits normally on Unix and Windows
+/// - custom schemes registered with `FileSchemes`
 ///
 /// The `File` constructor performs implicit normalization of the
 /// given path argument.
+///
+/// Reading a `File` may be served by archives or directories
+/// mounted in the virtual file system; see `Vfs`.
 /// 
 #[derive(Clone, Eq, PartialEq, Trace, Finalize)]
 pub struct File {
@@ -40,15 +83,35 @@ enum FileScheme {
     File,
     App,
     AppStorage,
+    Temp,
+    Custom(String),
 }
 
 impl FileScheme {
     pub fn prefix(&self) -> String {
-        String::from(match self {
-            FileScheme::File => "file:",
-            FileScheme::App => "app:",
-            FileScheme::AppStorage => "app-storage:",
-        })
+        match self {
+            FileScheme::File => "file:".to_owned(),
+            FileScheme::App => "app:".to_owned(),
+            FileScheme::AppStorage => "app-storage:".to_owned(),
+            FileScheme::Temp => "temp:".to_owned(),
+            FileScheme::Custom(name) => name.clone() + ":",
+        }
+    }
+
+    /// Splits a URL into its scheme and the rest, if it has
+    /// a built-in or registered scheme.
+    pub(crate) fn split(url: &str) -> Option<(FileScheme, &str)> {
+        if let Some(rest) = url.strip_prefix("file:") {
+            Some((FileScheme::File, rest))
+        } else if let Some(rest) = url.strip_prefix("app:") {
+            Some((FileScheme::App, rest))
+        } else if let Some(rest) = url.strip_prefix("app-storage:") {
+            Some((FileScheme::AppStorage, rest))
+        } else if let Some(rest) = url.strip_prefix("temp:") {
+            Some((FileScheme::Temp, rest))
+        } else {
+            file_scheme::split(url).map(|(name, rest)| (FileScheme::Custom(name), rest))
+        }
     }
 }
 
@@ -59,14 +122,9 @@ impl File {
         let mut path = String::from("");
         let mut scheme: FileScheme = FileScheme::File;
 
-        if url_or_path.starts_with("file:") {
-            path = url_or_path[5..].to_owned();
-        } else if url_or_path.starts_with("app:") {
-            path = url_or_path[4..].to_owned();
-            scheme = FileScheme::App;
-        } else if url_or_path.starts_with("app-storage:") {
-            path = url_or_path[12..].to_owned();
-            scheme = FileScheme::AppStorage;
+        if let Some((s, rest)) = FileScheme::split(&url_or_path) {
+            path = rest.to_owned();
+            scheme = s;
         } else {
             path = url_or_path.to_owned();
         }
@@ -126,6 +184,26 @@ impl File {
         File { m_scheme: FileScheme::AppStorage, m_path: String::from("") }
     }
 
+    /// Returns a reference to the temporary directory of the running process.
+    /// This is equivalent to the URL `temp://`.
+    pub fn temp_directory() -> Self {
+        File { m_scheme: FileScheme::Temp, m_path: String::from("/") }
+    }
+
+    /// Creates an empty file with a unique name in the `temp:` directory,
+    /// only accessible by the current user. The file is deleted
+    /// when the returned `TempFile` is dropped.
+    pub fn create_temp_file() -> Result<TempFile, IoError> {
+        temp::create_file()
+    }
+
+    /// Creates an empty directory with a unique name in the `temp:` directory,
+    /// only accessible by the current user. The directory is deleted
+    /// with its contents when the returned `TempDirectory` is dropped.
+    pub fn create_temp_directory() -> Result<TempDirectory, IoError> {
+        temp::create_directory()
+    }
+
     /// The URL for this file path.
     pub fn url(&self) -> String {
         let path = self.m_path.replace("\\", "/");
@@ -236,6 +314,11 @@ impl File {
     /// Native path of the `File` object. If the `File` object
     /// was constructed from `app:` or `app-storage:` scheme,
     /// then its path is resolved to the internal application directory.
+    /// If it was constructed from `temp:` scheme, then its path is
+    /// resolved to the temporary directory of the running process.
+    /// If it was constructed from a custom scheme, then its path is
+    /// resolved to the scheme's directory, or is empty if the scheme
+    /// is served by a provider of the virtual file system.
     pub fn application_based_native_path(&self) -> PathBuf {
         PathBuf::from(self.to_path_object())
     }
@@ -249,6 +332,18 @@ impl File {
             let l = File::new(unsafe {APPLICATION_STORAGE_DIRECTORY.clone()}.unwrap_or("".to_owned()));
             let r = regex_replace!(r"^[\\/]", self.native_path().as_ref(), |_| "").to_owned().to_string();
             l.resolve_path(r).native_path().clone()
+        } else if self.m_scheme == FileScheme::Temp {
+            let Ok(l) = temp::root() else {
+                return String::new();
+            };
+            let r = regex_replace!(r"^[\\/]", self.native_path().as_ref(), |_| "").to_owned().to_string();
+            File::new(l).resolve_path(r).native_path().clone()
+        } else if let FileScheme::Custom(name) = &self.m_scheme {
+            let Some(directory) = file_scheme::directory(name) else {
+                return String::new();
+            };
+            let r = regex_replace!(r"^[\\/]", self.native_path().as_ref(), |_| "").to_owned().to_string();
+            File::new(directory).resolve_path(r).native_path().clone()
         } else {
             self.native_path().clone()
         }
@@ -256,17 +351,29 @@ impl File {
 
     /// Determines whether the referenced path exists.s
     pub fn exists(&self) -> bool {
-        Path::new(&self.to_path_object()).exists()
+        vfs::entry(self).is_some() || Path::new(&self.to_path_object()).exists()
     }
 
     /// Determines whether the referenced path is a directory.
     pub fn is_directory(&self) -> bool {
-        Path::new(&self.to_path_object()).is_dir()
+        match vfs::entry(self) {
+            Some(entry) => entry == VfsEntry::Directory,
+            None => Path::new(&self.to_path_object()).is_dir(),
+        }
     }
 
     /// Determines whether the referenced path is a file.
     pub fn is_file(&self) -> bool {
-        Path::new(&self.to_path_object()).is_file()
+        match vfs::entry(self) {
+            Some(entry) => entry != VfsEntry::Directory,
+            None => Path::new(&self.to_path_object()).is_file(),
+        }
+    }
+
+    /// Determines whether the referenced path is provided by
+    /// a mount of the virtual file system instead of the disk.
+    pub fn is_mounted(&self) -> bool {
+        vfs::entry(self).is_some()
     }
 
     /// Determines whether the referenced path is a symbolic link.
@@ -353,35 +460,149 @@ impl File {
 
     /// Read file contents as bytes.
     pub fn read_bytes(&self) -> Result<Vec<u8>, IoError> {
+        if let Some(r) = vfs::read(self) {
+            return r;
+        }
         Ok(std::fs::read(self.to_path_object())?)
     }
 
     /// Read file contents as bytes.
     pub async fn read_bytes_async(&self) -> Result<Vec<u8>, IoError> {
+        if let Some(r) = vfs::read_async(self).await {
+            return r;
+        }
         Ok(tokio::fs::read(self.to_path_object()).await?)
     }
 
     /// Read file contents as UTF-8 string.
     pub fn read_utf8(&self) -> Result<String, IoError> {
+        if let Some(r) = vfs::read(self) {
+            return String::from_utf8(r?).map_err(|error| IoError::new(IoErrorKind::InvalidData, error));
+        }
         Ok(std::fs::read_to_string(self.to_path_object())?)
     }
 
     /// Read file contents as UTF-8 string.
     pub async fn read_utf8_async(&self) -> Result<String, IoError> {
+        if let Some(r) = vfs::read_async(self).await {
+            return String::from_utf8(r?).map_err(|error| IoError::new(IoErrorKind::InvalidData, error));
+        }
         Ok(tokio::fs::read_to_string(self.to_path_object()).await?)
     }
 
+    /// Opens the file as a stream, for reading or writing it in parts.
+    /// Mounts of the virtual file system are not consulted.
+    pub fn open(&self, mode: FileMode) -> Result<FileStream, IoError> {
+        FileStream::open(&self.to_path_object(), mode)
+    }
+
+    /// Opens the file as a stream, for reading or writing it in parts.
+    /// Mounts of the virtual file system are not consulted.
+    pub async fn open_async(&self, mode: FileMode) -> Result<AsyncFileStream, IoError> {
+        AsyncFileStream::open(&self.to_path_object(), mode).await
+    }
+
     /// Returns a vector of `File` objects corresponding to files and directories
     /// in the directory represented by the `File` object.
+    /// Entries whose names are not valid UTF-8 are skipped,
+    /// as they cannot be represented by a `File`.
     pub fn get_directory_listing(&self) -> Result<Vec<File>, IoError> {
+        let mounted = vfs::list(self).transpose()?;
         let mut r = Vec::<File>::new();
-        for entry in std::fs::read_dir(self.to_path_object())? {
-            let entry = entry?;
-            r.push(File::new(entry.path().to_str().unwrap_or("")));
+        let mut names = HashSet::<String>::new();
+        match std::fs::read_dir(self.to_path_object()) {
+            Ok(entries) => for entry in entries {
+                let entry = entry?;
+                if let Ok(name) = entry.file_name().into_string() {
+                    r.push(self.resolve_path(name.as_str()));
+                    names.insert(name);
+                }
+            },
+            // a directory may exist only in mounts
+            Err(error) => if mounted.is_none() {
+                return Err(error);
+            },
+        }
+        for (name, _) in mounted.unwrap_or_default() {
+            if names.insert(name.clone()) {
+                r.push(self.resolve_path(name));
+            }
         }
         Ok(r.clone())
     }
 
+    /// Walks the directory represented by the `File` object recursively,
+    /// yielding its files and directories.
+    pub fn walk(&self, options: &DirectoryWalkOptions) -> DirectoryWalk {
+        DirectoryWalk::new(self, options)
+    }
+
+    /// Walks the directory represented by the `File` object recursively,
+    /// yielding its files and directories.
+    pub fn walk_async(&self, options: &DirectoryWalkOptions) -> AsyncDirectoryWalk {
+        AsyncDirectoryWalk::new(self, options)
+    }
+
+    /// Returns the existing files and directories matched by a glob pattern,
+    /// resolving a relative pattern against the directory represented by the `File` object.
+    /// See `Glob` for the pattern syntax.
+    pub fn glob(&self, pattern: impl AnyStringType) -> Result<Vec<File>, IoError> {
+        Glob::parse(pattern.convert(), Some(self)).expand()
+    }
+
+    /// Returns the existing files and directories matched by a glob pattern,
+    /// resolving a relative pattern against the directory represented by the `File` object.
+    /// See `Glob` for the pattern syntax.
+    pub async fn glob_async(&self, pattern: impl AnyStringType) -> Result<Vec<File>, IoError> {
+        Glob::parse(pattern.convert(), Some(self)).expand_async().await
+    }
+
+    /// Watches the file or directory represented by the `File` object for changes.
+    /// Operating system notifications are used where available, such as
+    /// inotify on Linux, and the file system is polled otherwise.
+    pub fn watch(&self, options: &FileWatchOptions) -> Result<FileWatcher, IoError> {
+        FileWatcher::new(self, options)
+    }
+
+    /// Takes an advisory lock on the file, creating it if it does not exist,
+    /// and waits until the lock is available. The lock is released when
+    /// the returned `FileLock` is dropped.
+    ///
+    /// Advisory locks only exclude other locks, including those taken by
+    /// the same process, and do not prevent reading or writing the file.
+    /// Since `write_atomic` replaces the file, guard a file written
+    /// atomically by locking a separate file, such as `project.lock`.
+    pub fn lock(&self, mode: FileLockMode) -> Result<FileLock, IoError> {
+        file_lock::lock(self, mode)
+    }
+
+    /// Takes an advisory lock on the file, creating it if it does not exist,
+    /// and waits until the lock is available. The lock is released when
+    /// the returned `FileLock` is dropped.
+    pub async fn lock_async(&self, mode: FileLockMode) -> Result<FileLock, IoError> {
+        file_lock::lock_async(self, mode).await
+    }
+
+    /// Takes an advisory lock on the file, creating it if it does not exist,
+    /// or returns `None` if the lock is not available.
+    pub fn try_lock(&self, mode: FileLockMode) -> Result<Option<FileLock>, IoError> {
+        file_lock::try_lock(self, mode)
+    }
+
+    /// Takes an advisory lock on the file, creating it if it does not exist,
+    /// waiting up to `timeout` for the lock to be available.
+    /// Returns `None` if the lock is still not available.
+    pub fn lock_timeout(&self, mode: FileLockMode, timeout: Duration) -> Result<Option<FileLock>, IoError> {
+        file_lock::lock_timeout(self, mode, timeout)
+    }
+
+    /// Takes an advisory lock on the file, creating it if it does not exist,
+    /// waiting up to `timeout` for the lock to be available.
+    /// Returns `None` if the lock is still not available.
+    pub async fn lock_timeout_async(&self, mode: FileLockMode, timeout: Duration) -> Result<Option<FileLock>, IoError> {
+        file_lock::lock_timeout_async(self, mode, timeout).await
+    }
+
     /// Deletes empty directory.
     pub fn delete_empty_directory(&self) -> Result<(), IoError> {
         std::fs::remove_dir(self.to_path_object())?;
@@ -444,6 +665,50 @@ impl File {
         Ok(())
     }
 
+    /// Writes bytes to a file so that, even if the application crashes
+    /// or the system loses power, the file has either its previous
+    /// or its new contents in full.
+    ///
+    /// The bytes are written and flushed to a temporary file next to the file,
+    /// which then replaces it. The previous contents are kept as backups
+    /// if `options` asks for them.
+    pub fn write_atomic<B: AsRef<[u8]>>(&self, b: B, options: &AtomicWriteOptions) -> Result<(), IoError> {
+        atomic_write::write(self, b.as_ref(), options)
+    }
+
+    /// Writes bytes to a file so that, even if the application crashes
+    /// or the system loses power, the file has either its previous
+    /// or its new contents in full.
+    ///
+    /// The bytes are written and flushed to a temporary file next to the file,
+    /// which then replaces it. The previous contents are kept as backups
+    /// if `options` asks for them.
+    pub async fn write_atomic_async<B: AsRef<[u8]>>(&self, b: B, options: &AtomicWriteOptions) -> Result<(), IoError> {
+        atomic_write::write_async(self, b.as_ref().to_vec(), options).await
+    }
+
+    /// Reads a file written with `write_atomic`. If the file is missing or
+    /// `is_valid` rejects its contents, the file is restored from the latest
+    /// backup that `is_valid` accepts, whose contents are returned.
+    /// Temporary files left by writes interrupted over an hour ago are removed.
+    ///
+    /// Fails with the error of reading the file if no backup is valid,
+    /// or with `InvalidData` if the file was rejected.
+    pub fn read_recovering(&self, options: &AtomicWriteOptions, is_valid: impl Fn(&[u8]) -> bool) -> Result<Vec<u8>, IoError> {
+        atomic_write::read(self, options, is_valid)
+    }
+
+    /// Reads a file written with `write_atomic`. If the file is missing or
+    /// `is_valid` rejects its contents, the file is restored from the latest
+    /// backup that `is_valid` accepts, whose contents are returned.
+    /// Temporary files left by writes interrupted over an hour ago are removed.
+    ///
+    /// Fails with the error of reading the file if no backup is valid,
+    /// or with `InvalidData` if the file was rejected.
+    pub async fn read_recovering_async(&self, options: &AtomicWriteOptions, is_valid: impl Fn(&[u8]) -> bool + Send + 'static) -> Result<Vec<u8>, IoError> {
+        atomic_write::read_async(self, options, is_valid).await
+    }
+
     /// Creation date.
     pub fn creation_date(&self) -> Result<SystemTime, IoError> {
         let r = std::fs::metadata(self.to_path_object())?;
@@ -470,12 +735,18 @@ impl File {
 
     /// Size of the file in bytes.
     pub fn size(&self) -> Result<i64, IoError> {
+        if let Some(VfsEntry::File { size }) = vfs::entry(self) {
+            return Ok(size as i64);
+        }
         let r = std::fs::metadata(self.to_path_object())?;
         Ok(r.len() as i64)
     }
 
     /// Size of the file in bytes.
     pub async fn size_async(&self) -> Result<i64, IoError> {
+        if let Some(VfsEntry::File { size }) = vfs::entry_async(self).await {
+            return Ok(size as i64);
+        }
         let r = tokio::fs::metadata(self.to_path_object()).await?;
         Ok(r.len() as i64)
     }
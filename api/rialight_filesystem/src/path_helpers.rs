@@ -384,6 +384,10 @@ pub fn posix_resolve<S1: AsRef<str>, S2: AsRef<str>>(left: S1, right: S2) -> Str
         }
         resolved_path = path.clone() + "/" + &resolved_path.clone();
         resolved_absolute = path.chars().next().unwrap_or('\x00') == '/';
+        // an absolute path discards the paths before it
+        if resolved_absolute {
+            break;
+        }
     }
 
     resolved_path = normalize_string(SvStr::from(resolved_path), !resolved_absolute, SvStr::from("/"), |code| code == '/').to_string();
@@ -566,4 +570,21 @@ fn last_index_of<T: PartialEq>(v: &Vec<T>, e: T) -> i64 {
         }
     }
     r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix_resolve_absolute() {
+        assert_eq!(posix_resolve("/a/b", "c"), "/a/b/c");
+        assert_eq!(posix_resolve("/a/b", "../c"), "/a/c");
+        // an absolute path discards the current directory and the paths before it
+        assert_eq!(posix_resolve("/a/b", "/c/d"), "/c/d");
+        assert_eq!(posix_resolve("a", "/c"), "/c");
+        assert_eq!(posix_resolve("/a", ""), "/a");
+        let current = std::env::current_dir().unwrap();
+        assert_eq!(posix_resolve("a", "b"), current.join("a/b").to_str().unwrap());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,304 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, atomic::{AtomicU64, Ordering}},
+    time::SystemTime,
+};
+use super::{File, FileScheme, IoError, IoErrorKind};
+
+/// A temporary file created by `File::create_temp_file`,
+/// deleted when the `TempFile` is dropped.
+pub struct TempFile {
+    m_file: Option<File>,
+}
+
+impl TempFile {
+    /// The temporary file, located in the `temp:` directory.
+    pub fn file(&self) -> File {
+        self.m_file.clone().unwrap()
+    }
+
+    /// Moves the temporary file to another location, so that it is
+    /// no longer deleted, and returns the new location. The file is
+    /// copied if the location is on another file system.
+    pub fn persist(mut self, to: &File) -> Result<File, IoError> {
+        move_path(&PathBuf::from(self.file().to_path_object()), &PathBuf::from(to.to_path_object()))?;
+        self.m_file = None;
+        Ok(to.clone())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(file) = self.m_file.take() {
+            let _ = file.delete_file();
+        }
+    }
+}
+
+/// A temporary directory created by `File::create_temp_directory`,
+/// deleted with its contents when the `TempDirectory` is dropped.
+pub struct TempDirectory {
+    m_file: Option<File>,
+}
+
+impl TempDirectory {
+    /// The temporary directory, located in the `temp:` directory.
+    pub fn file(&self) -> File {
+        self.m_file.clone().unwrap()
+    }
+
+    /// Moves the temporary directory to another location, so that it is
+    /// no longer deleted, and returns the new location. The directory is
+    /// copied if the location is on another file system.
+    pub fn persist(mut self, to: &File) -> Result<File, IoError> {
+        move_path(&PathBuf::from(self.file().to_path_object()), &PathBuf::from(to.to_path_object()))?;
+        self.m_file = None;
+        Ok(to.clone())
+    }
+}
+
+impl Drop for TempDirectory {
+    fn drop(&mut self) {
+        if let Some(file) = self.m_file.take() {
+            let _ = file.delete_all_directory();
+        }
+    }
+}
+
+// attempts at picking an unused name before giving up
+const MAX_ATTEMPTS: usize = 64;
+
+static ROOT: OnceLock<String> = OnceLock::new();
+// held while creating the root, so that a failure is retried on next use
+static ROOT_CREATION: Mutex<()> = Mutex::new(());
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A random name that is unlikely to exist.
+pub(crate) fn unique_name(prefix: &str) -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u32(std::process::id());
+    hasher.write_u128(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0));
+    format!("{}{:016x}", prefix, hasher.finish())
+}
+
+/// Creates a directory only accessible by the current user,
+/// failing if it already exists.
+fn create_private_directory(path: &Path) -> Result<(), IoError> {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(path)
+}
+
+/// Creates an empty file only accessible by the current user,
+/// failing if it already exists.
+fn create_private_file(path: &Path) -> Result<(), IoError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path).map(|_| ())
+}
+
+/// Calls `create` with unused names until it does not fail with `AlreadyExists`.
+fn create_unique(directory: &Path, prefix: &str, create: fn(&Path) -> Result<(), IoError>) -> Result<String, IoError> {
+    for _ in 0..MAX_ATTEMPTS {
+        let name = unique_name(prefix);
+        match create(&directory.join(&name)) {
+            Ok(()) => return Ok(name),
+            Err(error) if error.kind() == IoErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(IoError::new(IoErrorKind::AlreadyExists, "could not find an unused temporary name"))
+}
+
+/// Renames a file or directory, or copies and then removes it
+/// if the destination is on another file system.
+fn move_path(from: &Path, to: &Path) -> Result<(), IoError> {
+    match std::fs::rename(from, to) {
+        Err(error) if error.kind() == IoErrorKind::CrossesDevices => move_by_copying(from, to),
+        r => r,
+    }
+}
+
+fn move_by_copying(from: &Path, to: &Path) -> Result<(), IoError> {
+    let is_directory = std::fs::metadata(from)?.is_dir();
+    if let Err(error) = copy_all(from, to) {
+        let _ = if is_directory { std::fs::remove_dir_all(to) } else { std::fs::remove_file(to) };
+        return Err(error);
+    }
+    if is_directory { std::fs::remove_dir_all(from) } else { std::fs::remove_file(from) }
+}
+
+fn copy_all(from: &Path, to: &Path) -> Result<(), IoError> {
+    if !std::fs::metadata(from)?.is_dir() {
+        return std::fs::copy(from, to).map(|_| ());
+    }
+    std::fs::create_dir(to)?;
+    std::fs::set_permissions(to, std::fs::metadata(from)?.permissions())?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        copy_all(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+extern "C" fn remove_root() {
+    if let Some(root) = ROOT.get() {
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
+
+/// Removes the `temp:` directory when the process exits normally,
+/// not on a crash or a signal.
+#[cfg(any(unix, windows))]
+fn remove_root_at_exit() {
+    unsafe { libc::atexit(remove_root) };
+}
+
+// other targets, such as WebAssembly, have no exit handlers
+#[cfg(not(any(unix, windows)))]
+fn remove_root_at_exit() {
+}
+
+/// The native path of the `temp:` directory, created on first use
+/// inside the system temporary directory and removed when the process exits.
+pub(crate) fn root() -> Result<String, IoError> {
+    if let Some(root) = ROOT.get() {
+        return Ok(root.clone());
+    }
+    let _creation = ROOT_CREATION.lock().unwrap_or_else(|error| error.into_inner());
+    if let Some(root) = ROOT.get() {
+        return Ok(root.clone());
+    }
+    let system = std::env::temp_dir();
+    if system.to_str().is_none() {
+        return Err(IoError::new(IoErrorKind::InvalidData, "temporary directory path is not valid UTF-8"));
+    }
+    let name = create_unique(&system, &format!("rialight-{}-", std::process::id()), create_private_directory)?;
+    let root = system.join(name).into_os_string().into_string().unwrap();
+    let _ = ROOT.set(root.clone());
+    remove_root_at_exit();
+    Ok(root)
+}
+
+pub(crate) fn create_file() -> Result<TempFile, IoError> {
+    let name = create_unique(&PathBuf::from(root()?), "tmp-", create_private_file)?;
+    Ok(TempFile { m_file: Some(File { m_scheme: FileScheme::Temp, m_path: "/".to_owned() + &name }) })
+}
+
+pub(crate) fn create_directory() -> Result<TempDirectory, IoError> {
+    let name = create_unique(&PathBuf::from(root()?), "tmp-", create_private_directory)?;
+    Ok(TempDirectory { m_file: Some(File { m_scheme: FileScheme::Temp, m_path: "/".to_owned() + &name }) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delete_on_drop() {
+        let temp = File::create_temp_file().unwrap();
+        let path = temp.file().application_based_native_path();
+        assert!(path.is_file());
+        drop(temp);
+        assert!(!path.exists());
+
+        let temp = File::create_temp_directory().unwrap();
+        let path = temp.file().application_based_native_path();
+        temp.file().resolve_path("a.txt").write("alpha").unwrap();
+        assert!(path.is_dir());
+        drop(temp);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn persist() {
+        let directory = File::create_temp_directory().unwrap();
+        let temp = File::create_temp_file().unwrap();
+        temp.file().write("alpha").unwrap();
+        let to = directory.file().resolve_path("a.txt");
+        let persisted = temp.persist(&to).unwrap();
+        assert_eq!(persisted.read_utf8().unwrap(), "alpha");
+
+        let temp = File::create_temp_directory().unwrap();
+        temp.file().resolve_path("b.txt").write("beta").unwrap();
+        let to = directory.file().resolve_path("b");
+        temp.persist(&to).unwrap();
+        assert_eq!(to.resolve_path("b.txt").read_utf8().unwrap(), "beta");
+    }
+
+    #[test]
+    fn move_by_copying() {
+        let directory = File::create_temp_directory().unwrap();
+        let from = PathBuf::from(directory.file().resolve_path("from").to_path_object());
+        std::fs::create_dir_all(from.join("b")).unwrap();
+        std::fs::write(from.join("a.txt"), "alpha").unwrap();
+        std::fs::write(from.join("b/c.txt"), "gamma").unwrap();
+        let to = PathBuf::from(directory.file().resolve_path("to").to_path_object());
+        super::move_by_copying(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(std::fs::read_to_string(to.join("a.txt")).unwrap(), "alpha");
+        assert_eq!(std::fs::read_to_string(to.join("b/c.txt")).unwrap(), "gamma");
+
+        // a failed copy leaves the source in place
+        let to = PathBuf::from(directory.file().resolve_path("missing/to").to_path_object());
+        assert!(super::move_by_copying(&from.join("a.txt"), &to).is_err());
+        assert!(super::move_by_copying(&PathBuf::from(directory.file().resolve_path("to").to_path_object()), &to).is_err());
+        assert!(directory.file().resolve_path("to/a.txt").exists());
+    }
+
+    /// Persists to the memory file system of Linux, which is usually
+    /// not the one of the system temporary directory.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn persist_across_file_systems() {
+        use std::os::unix::fs::MetadataExt;
+        let shm = PathBuf::from("/dev/shm");
+        let (Ok(shm_metadata), Ok(root_metadata)) = (std::fs::metadata(&shm), std::fs::metadata(super::root().unwrap())) else {
+            return;
+        };
+        if shm_metadata.dev() == root_metadata.dev() {
+            return;
+        }
+        let temp = File::create_temp_file().unwrap();
+        temp.file().write("alpha").unwrap();
+        let from = temp.file().application_based_native_path();
+        let to = File::new(shm.join(unique_name("rialight-test-")).to_str().unwrap());
+        temp.persist(&to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(to.read_utf8().unwrap(), "alpha");
+        to.delete_file().unwrap();
+
+        let temp = File::create_temp_directory().unwrap();
+        temp.file().resolve_path("a.txt").write("alpha").unwrap();
+        let to = File::new(shm.join(unique_name("rialight-test-")).to_str().unwrap());
+        temp.persist(&to).unwrap();
+        assert_eq!(to.resolve_path("a.txt").read_utf8().unwrap(), "alpha");
+        to.delete_all_directory().unwrap();
+    }
+
+    #[test]
+    fn root() {
+        let temp = File::create_temp_file().unwrap();
+        let path = temp.file().application_based_native_path();
+        assert_eq!(path.parent().unwrap(), File::temp_directory().application_based_native_path());
+        assert_eq!(File::temp_directory().application_based_native_path(), PathBuf::from(super::root().unwrap()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: PathBuf| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let temp = File::create_temp_file().unwrap();
+        assert_eq!(mode(temp.file().application_based_native_path()), 0o600);
+        let temp = File::create_temp_directory().unwrap();
+        assert_eq!(mode(temp.file().application_based_native_path()), 0o700);
+        assert_eq!(mode(PathBuf::from(super::root().unwrap())), 0o700);
+    }
+}
@@ -0,0 +1,605 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    convert::TryInto,
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock, atomic::{AtomicU64, Ordering}},
+};
+use super::{File, IoError, IoErrorKind, DirectoryWalkOptions, blocking, archive::{convert_zip_error, read_zip_entry}};
+
+/// An entry of a `VfsProvider`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VfsEntry {
+    File { size: u64 },
+    Directory,
+}
+
+/// A source of files that can be mounted into the virtual file system
+/// with `Vfs::mount`.
+///
+/// Paths given to a provider are relative to its mount point, separated by `/`
+/// and without a leading slash. The empty path is the mount point itself.
+pub trait VfsProvider: Send + Sync {
+    /// The entry at a path, or `None` if there is none.
+    fn entry(&self, path: &str) -> Option<VfsEntry>;
+
+    /// Reads the contents of the file at a path.
+    fn read(&self, path: &str) -> Result<Vec<u8>, IoError>;
+
+    /// The names and entries directly inside the directory at a path.
+    fn list(&self, path: &str) -> Result<Vec<(String, VfsEntry)>, IoError>;
+}
+
+/// Identifies a mount, as returned by `Vfs::mount`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VfsMountId(u64);
+
+/// The virtual file system, where providers such as archives and directories
+/// are mounted at `File` locations.
+///
+/// Mounted files overlay the disk: reading a `File`, checking whether it exists,
+/// or listing and walking directories consults the mounts covering its URL first,
+/// from the highest priority to the lowest, and falls back to the disk.
+/// Between mounts of the same priority, the latest mount wins.
+/// Mounts are read-only; writing a `File` always writes to the disk.
+///
+/// ```ignore
+/// // release builds ship their assets in a pack, overridden by mods
+/// Vfs::mount(&File::new("app://"), VfsPack::open(&File::new("assets.rlpack"))?, 0);
+/// Vfs::mount(&File::new("app://res"), VfsDirectory::new(&File::new("app-storage://mods/res")), 10);
+/// let bytes = File::new("app://res/lang/en-US/_.json").read_bytes()?;
+/// ```
+pub struct Vfs;
+
+struct Mount {
+    m_id: u64,
+    m_prefix: String,
+    m_priority: i32,
+    m_provider: Arc<dyn VfsProvider>,
+}
+
+static MOUNTS: RwLock<Vec<Mount>> = RwLock::new(Vec::new());
+static NEXT_MOUNT_ID: AtomicU64 = AtomicU64::new(0);
+
+impl Vfs {
+    /// Mounts a provider at a location, such as `app://` or `app://res/lang`.
+    /// Mounts of higher priority overlay those of lower priority.
+    pub fn mount(at: &File, provider: impl VfsProvider + 'static, priority: i32) -> VfsMountId {
+        let id = NEXT_MOUNT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut mounts = MOUNTS.write().unwrap();
+        mounts.push(Mount { m_id: id, m_prefix: at.url(), m_priority: priority, m_provider: Arc::new(provider) });
+        mounts.sort_by(|a, b| b.m_priority.cmp(&a.m_priority).then(b.m_id.cmp(&a.m_id)));
+        VfsMountId(id)
+    }
+
+    /// Removes a mount, returning whether it was mounted.
+    pub fn unmount(id: VfsMountId) -> bool {
+        let mut mounts = MOUNTS.write().unwrap();
+        let length = mounts.len();
+        mounts.retain(|mount| mount.m_id != id.0);
+        mounts.len() != length
+    }
+}
+
+/// Path of a URL relative to a mount prefix, if the mount covers it.
+fn relative_to_prefix<'a>(prefix: &str, url: &'a str) -> Option<&'a str> {
+    if url == prefix || url.strip_suffix('/') == Some(prefix) {
+        return Some("");
+    }
+    if prefix.ends_with('/') {
+        url.strip_prefix(prefix)
+    } else {
+        url.strip_prefix(prefix)?.strip_prefix('/')
+    }
+}
+
+type Candidates = Vec<(Arc<dyn VfsProvider>, String)>;
+
+/// The providers covering a file with its path in each, in overlay order,
+/// and the names of mount points directly inside it.
+fn candidates(file: &File) -> (Candidates, Vec<String>) {
+    let url = file.url();
+    let mounts = MOUNTS.read().unwrap();
+    let mut providers = vec![];
+    let mut mount_points = vec![];
+    for mount in mounts.iter() {
+        if let Some(path) = relative_to_prefix(&mount.m_prefix, &url) {
+            providers.push((mount.m_provider.clone(), path.to_owned()));
+        } else if let Some(path) = relative_to_prefix(&url, &mount.m_prefix) {
+            if let Some(name) = path.split('/').find(|c| !c.is_empty()) {
+                mount_points.push(name.to_owned());
+            }
+        }
+    }
+    (providers, mount_points)
+}
+
+fn first_entry(candidates: &Candidates) -> Option<(usize, VfsEntry)> {
+    candidates.iter().enumerate().find_map(|(i, (provider, path))| provider.entry(path).map(|entry| (i, entry)))
+}
+
+/// The mounted entry of a file, if any.
+pub(crate) fn entry(file: &File) -> Option<VfsEntry> {
+    let (candidates, mount_points) = candidates(file);
+    entry_of(&candidates, &mount_points)
+}
+
+/// The mounted entry of a file, if any.
+pub(crate) async fn entry_async(file: &File) -> Option<VfsEntry> {
+    let (candidates, mount_points) = candidates(file);
+    if candidates.is_empty() && mount_points.is_empty() {
+        return None;
+    }
+    blocking(move || Ok(entry_of(&candidates, &mount_points))).await.ok().flatten()
+}
+
+fn entry_of(candidates: &Candidates, mount_points: &[String]) -> Option<VfsEntry> {
+    first_entry(candidates).map(|(_, entry)| entry)
+        .or(if mount_points.is_empty() { None } else { Some(VfsEntry::Directory) })
+}
+
+/// Reads a mounted file, or returns `None` if no mount provides it.
+pub(crate) fn read(file: &File) -> Option<Result<Vec<u8>, IoError>> {
+    let (candidates, _) = candidates(file);
+    match first_entry(&candidates)? {
+        (i, VfsEntry::File { .. }) => Some(candidates[i].0.read(&candidates[i].1)),
+        (_, VfsEntry::Directory) => None,
+    }
+}
+
+/// Reads a mounted file, or returns `None` if no mount provides it.
+pub(crate) async fn read_async(file: &File) -> Option<Result<Vec<u8>, IoError>> {
+    let (candidates, _) = candidates(file);
+    if candidates.is_empty() {
+        return None;
+    }
+    blocking(move || Ok(match first_entry(&candidates) {
+        Some((i, VfsEntry::File { .. })) => Some(candidates[i].0.read(&candidates[i].1)?),
+        _ => None,
+    })).await.transpose()
+}
+
+/// Lists a mounted directory, merging the mounts covering it,
+/// or returns `None` if no mount provides it.
+pub(crate) fn list(file: &File) -> Option<Result<Vec<(String, VfsEntry)>, IoError>> {
+    let (candidates, mount_points) = candidates(file);
+    list_of(&candidates, &mount_points)
+}
+
+/// Lists a mounted directory, merging the mounts covering it,
+/// or returns `None` if no mount provides it.
+pub(crate) async fn list_async(file: &File) -> Option<Result<Vec<(String, VfsEntry)>, IoError>> {
+    let (candidates, mount_points) = candidates(file);
+    if candidates.is_empty() && mount_points.is_empty() {
+        return None;
+    }
+    blocking(move || Ok(list_of(&candidates, &mount_points))).await.unwrap_or_else(|error| Some(Err(error)))
+}
+
+fn list_of(candidates: &Candidates, mount_points: &[String]) -> Option<Result<Vec<(String, VfsEntry)>, IoError>> {
+    let mut found = false;
+    let mut r: Vec<(String, VfsEntry)> = vec![];
+    let mut indices = HashMap::<String, usize>::new();
+    for (provider, path) in candidates.iter() {
+        if provider.entry(path) != Some(VfsEntry::Directory) {
+            continue;
+        }
+        found = true;
+        let entries = match provider.list(path) {
+            Ok(entries) => entries,
+            Err(error) => return Some(Err(error)),
+        };
+        // candidates are in overlay order, so earlier entries win
+        for (name, entry) in entries {
+            if !indices.contains_key(&name) {
+                indices.insert(name.clone(), r.len());
+                r.push((name, entry));
+            }
+        }
+    }
+    for name in mount_points {
+        match indices.get(name) {
+            Some(&i) => r[i].1 = VfsEntry::Directory,
+            None => {
+                indices.insert(name.clone(), r.len());
+                r.push((name.clone(), VfsEntry::Directory));
+            },
+        }
+    }
+    if !found && mount_points.is_empty() {
+        return None;
+    }
+    Some(Ok(r))
+}
+
+/// A directory of the disk mounted into the virtual file system,
+/// such as a mod overriding application resources.
+pub struct VfsDirectory {
+    m_path: PathBuf,
+}
+
+impl VfsDirectory {
+    /// Constructs a new `VfsDirectory` object.
+    pub fn new(directory: &File) -> Self {
+        VfsDirectory { m_path: PathBuf::from(directory.to_path_object()) }
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        path.split('/').filter(|c| !c.is_empty() && *c != "." && *c != "..").fold(self.m_path.clone(), |r, c| r.join(c))
+    }
+}
+
+impl VfsProvider for VfsDirectory {
+    fn entry(&self, path: &str) -> Option<VfsEntry> {
+        let metadata = std::fs::metadata(self.path(path)).ok()?;
+        Some(if metadata.is_dir() { VfsEntry::Directory } else { VfsEntry::File { size: metadata.len() } })
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, IoError> {
+        std::fs::read(self.path(path))
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<(String, VfsEntry)>, IoError> {
+        let mut r = vec![];
+        for entry in std::fs::read_dir(self.path(path))? {
+            let entry = entry?;
+            // names that are not valid UTF-8 cannot be addressed by a `File`
+            if let Ok(name) = entry.file_name().into_string() {
+                let metadata = entry.metadata()?;
+                r.push((name, if metadata.is_dir() { VfsEntry::Directory } else { VfsEntry::File { size: metadata.len() } }));
+            }
+        }
+        Ok(r)
+    }
+}
+
+/// Files of an archive by path, with the directories they imply.
+struct ArchiveIndex<T> {
+    m_files: HashMap<String, (T, u64)>,
+    m_directories: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<T> ArchiveIndex<T> {
+    fn new() -> Self {
+        let mut directories = BTreeMap::new();
+        directories.insert("".to_owned(), BTreeSet::new());
+        ArchiveIndex { m_files: HashMap::new(), m_directories: directories }
+    }
+
+    fn add_directory(&mut self, path: &str) {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        for i in 0..components.len() {
+            let parent = components[..i].join("/");
+            self.m_directories.entry(parent).or_default().insert(components[i].to_owned());
+            self.m_directories.entry(components[..=i].join("/")).or_default();
+        }
+    }
+
+    fn add_file(&mut self, path: &str, value: T, size: u64) {
+        let path = path.trim_matches('/');
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            self.add_directory(parent);
+        }
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        self.m_directories.entry(parent.to_owned()).or_default().insert(name.to_owned());
+        self.m_files.insert(path.to_owned(), (value, size));
+    }
+
+    fn entry(&self, path: &str) -> Option<VfsEntry> {
+        let path = path.trim_matches('/');
+        if let Some((_, size)) = self.m_files.get(path) {
+            return Some(VfsEntry::File { size: *size });
+        }
+        self.m_directories.get(path).map(|_| VfsEntry::Directory)
+    }
+
+    fn file(&self, path: &str) -> Result<&T, IoError> {
+        self.m_files.get(path.trim_matches('/')).map(|(value, _)| value)
+            .ok_or_else(|| IoError::new(IoErrorKind::NotFound, format!("no file {} in archive", path)))
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<(String, VfsEntry)>, IoError> {
+        let path = path.trim_matches('/');
+        let names = self.m_directories.get(path)
+            .ok_or_else(|| IoError::new(IoErrorKind::NotFound, format!("no directory {} in archive", path)))?;
+        Ok(names.iter().map(|name| {
+            let child = if path.is_empty() { name.clone() } else { path.to_owned() + "/" + name };
+            (name.clone(), self.entry(&child).unwrap_or(VfsEntry::Directory))
+        }).collect())
+    }
+}
+
+/// A zip archive mounted into the virtual file system.
+/// Stored and deflated entries are supported.
+pub struct VfsZip {
+    m_archive: Mutex<zip::ZipArchive<std::fs::File>>,
+    m_index: ArchiveIndex<usize>,
+}
+
+impl VfsZip {
+    /// Opens a zip archive, reading its central directory.
+    pub fn open(archive: &File) -> Result<Self, IoError> {
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(archive.to_path_object())?).map_err(convert_zip_error)?;
+        let mut index = ArchiveIndex::new();
+        for i in 0..zip.len() {
+            let entry = zip.by_index_raw(i).map_err(convert_zip_error)?;
+            // names escaping the archive root are ignored
+            let name = match entry.enclosed_name() {
+                Some(name) => name.components().filter_map(|c| c.as_os_str().to_str()).collect::<Vec<_>>().join("/"),
+                None => continue,
+            };
+            if entry.is_dir() {
+                index.add_directory(&name);
+            } else {
+                index.add_file(&name, i, entry.size());
+            }
+        }
+        Ok(VfsZip { m_archive: Mutex::new(zip), m_index: index })
+    }
+}
+
+impl VfsProvider for VfsZip {
+    fn entry(&self, path: &str) -> Option<VfsEntry> {
+        self.m_index.entry(path)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, IoError> {
+        let i = *self.m_index.file(path)?;
+        let mut archive = self.m_archive.lock().unwrap();
+        let entry = archive.by_index(i).map_err(convert_zip_error)?;
+        read_zip_entry(entry)
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<(String, VfsEntry)>, IoError> {
+        self.m_index.list(path)
+    }
+}
+
+const PACK_MAGIC: &[u8; 8] = b"RLPACK\0\0";
+const PACK_VERSION: u32 = 1;
+const PACK_HEADER_SIZE: usize = 20;
+const PACK_ENTRY_RECORD_SIZE: usize = 24;
+
+/// A Rialight pack mounted into the virtual file system.
+///
+/// A pack is an uncompressed archive of a directory tree, as created
+/// by `VfsPack::create`, whose files are read in place.
+///
+/// # Format
+///
+/// All integers are little-endian.
+///
+/// - Header: magic `RLPACK\0\0`, version (`u32`), entry count (`u32`) and path data length (`u32`).
+/// - Entry table: path offset and length (`u32` each) into the path data,
+///   then the file data offset from the start of the pack and the file size (`u64` each).
+/// - Path data: UTF-8 paths separated by `/`.
+/// - File data.
+pub struct VfsPack {
+    m_file: Mutex<std::fs::File>,
+    m_index: ArchiveIndex<u64>,
+}
+
+impl VfsPack {
+    /// Opens a pack, reading its entry table.
+    pub fn open(pack: &File) -> Result<Self, IoError> {
+        let mut file = std::fs::File::open(pack.to_path_object())?;
+        let file_len = file.metadata()?.len();
+        let invalid = || IoError::new(IoErrorKind::InvalidData, "invalid Rialight pack");
+        let mut header = [0u8; PACK_HEADER_SIZE];
+        file.read_exact(&mut header).map_err(|_| invalid())?;
+        if &header[0..8] != PACK_MAGIC {
+            return Err(invalid());
+        }
+        let version = read_u32(&header, 8);
+        if version != PACK_VERSION {
+            return Err(IoError::new(IoErrorKind::InvalidData, format!("unsupported Rialight pack version {}", version)));
+        }
+        let entry_count = read_u32(&header, 12) as usize;
+        let path_data_len = read_u32(&header, 16) as usize;
+        // the table is allocated only once known to fit in the pack
+        let entries_len = entry_count.checked_mul(PACK_ENTRY_RECORD_SIZE).ok_or_else(invalid)?;
+        let table_len = entries_len.checked_add(path_data_len).ok_or_else(invalid)?;
+        if table_len as u64 > file_len - PACK_HEADER_SIZE as u64 {
+            return Err(invalid());
+        }
+        let mut table = vec![0u8; table_len];
+        file.read_exact(&mut table).map_err(|_| invalid())?;
+        let (entries, path_data) = table.split_at(entries_len);
+
+        let mut index = ArchiveIndex::new();
+        for record in entries.chunks(PACK_ENTRY_RECORD_SIZE) {
+            let path_offset = read_u32(record, 0) as usize;
+            let path_len = read_u32(record, 4) as usize;
+            let path = path_data.get(path_offset..path_offset.checked_add(path_len).ok_or_else(invalid)?).ok_or_else(invalid)?;
+            let path = std::str::from_utf8(path).map_err(|_| invalid())?;
+            let (data_offset, size) = (read_u64(record, 8), read_u64(record, 16));
+            if data_offset.checked_add(size).map(|end| end > file_len) != Some(false) {
+                return Err(invalid());
+            }
+            index.add_file(path, data_offset, size);
+        }
+        Ok(VfsPack { m_file: Mutex::new(file), m_index: index })
+    }
+
+    /// Creates a pack from the files of a directory tree on the disk,
+    /// ignoring mounts of the virtual file system. The pack may be created
+    /// inside the directory, in which case a previous version of it is not packed.
+    pub fn create(directory: &File, pack: &File) -> Result<(), IoError> {
+        let options = DirectoryWalkOptions::new();
+        options.sorted(true).mounts(false);
+        let pack_path = std::fs::canonicalize(pack.to_path_object()).ok();
+        let mut files: Vec<(String, File)> = vec![];
+        for entry in directory.walk(&options) {
+            let entry = entry?;
+            if !entry.is_file() {
+                continue;
+            }
+            if pack_path.is_some() && std::fs::canonicalize(entry.file().to_path_object()).ok() == pack_path {
+                continue;
+            }
+            files.push((entry.relative_path(), entry.file()));
+        }
+
+        let mut path_data: Vec<u8> = vec![];
+        let mut sizes: Vec<u64> = vec![];
+        for (path, file) in files.iter() {
+            path_data.extend(path.as_bytes());
+            sizes.push(std::fs::metadata(file.to_path_object())?.len());
+        }
+        let mut r = std::io::BufWriter::new(std::fs::File::create(pack.to_path_object())?);
+        r.write_all(PACK_MAGIC)?;
+        r.write_all(&PACK_VERSION.to_le_bytes())?;
+        r.write_all(&(files.len() as u32).to_le_bytes())?;
+        r.write_all(&(path_data.len() as u32).to_le_bytes())?;
+        let mut path_offset = 0;
+        let mut data_offset = (PACK_HEADER_SIZE + files.len() * PACK_ENTRY_RECORD_SIZE + path_data.len()) as u64;
+        for ((path, _), size) in files.iter().zip(sizes.iter()) {
+            r.write_all(&(path_offset as u32).to_le_bytes())?;
+            r.write_all(&(path.len() as u32).to_le_bytes())?;
+            r.write_all(&data_offset.to_le_bytes())?;
+            r.write_all(&size.to_le_bytes())?;
+            path_offset += path.len();
+            data_offset += size;
+        }
+        r.write_all(&path_data)?;
+        for ((_, file), size) in files.iter().zip(sizes.iter()) {
+            let copied = std::io::copy(&mut std::fs::File::open(file.to_path_object())?.take(*size), &mut r)?;
+            if copied != *size {
+                return Err(IoError::new(IoErrorKind::UnexpectedEof, format!("{} changed while packing", file.url())));
+            }
+        }
+        r.flush()
+    }
+}
+
+impl VfsProvider for VfsPack {
+    fn entry(&self, path: &str) -> Option<VfsEntry> {
+        self.m_index.entry(path)
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, IoError> {
+        let offset = *self.m_index.file(path)?;
+        let size = match self.m_index.entry(path) {
+            Some(VfsEntry::File { size }) => size,
+            _ => 0,
+        };
+        // sizes were checked against the pack length when opening it
+        let mut r = vec![0u8; size as usize];
+        let mut file = self.m_file.lock().unwrap();
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut r)?;
+        Ok(r)
+    }
+
+    fn list(&self, path: &str) -> Result<Vec<(String, VfsEntry)>, IoError> {
+        self.m_index.list(path)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_tree(directory: &File) {
+        directory.resolve_path("a.txt").write("alpha").unwrap();
+        directory.resolve_path("b").create_directory().unwrap();
+        directory.resolve_path("b/c.txt").write("gamma").unwrap();
+    }
+
+    #[test]
+    fn pack() {
+        let temp = File::create_temp_directory().unwrap();
+        let directory = temp.file();
+        create_tree(&directory);
+        // created twice, so that the previous pack is in the directory
+        let pack = directory.resolve_path("assets.pack");
+        VfsPack::create(&directory, &pack).unwrap();
+        VfsPack::create(&directory, &pack).unwrap();
+        let pack = VfsPack::open(&pack).unwrap();
+        assert_eq!(pack.read("a.txt").unwrap(), b"alpha");
+        assert_eq!(pack.read("/b/c.txt").unwrap(), b"gamma");
+        assert_eq!(pack.entry("b"), Some(VfsEntry::Directory));
+        assert_eq!(pack.entry("assets.pack"), None);
+        let names: Vec<String> = pack.list("").unwrap().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["a.txt", "b"]);
+    }
+
+    #[test]
+    fn pack_ignores_mounts() {
+        let (temp, mounted) = (File::create_temp_directory().unwrap(), File::create_temp_directory().unwrap());
+        let directory = temp.file();
+        create_tree(&directory);
+        mounted.file().resolve_path("c.txt").write("mounted").unwrap();
+        mounted.file().resolve_path("d.txt").write("mounted").unwrap();
+        let id = Vfs::mount(&directory.resolve_path("b"), VfsDirectory::new(&mounted.file()), 0);
+        assert_eq!(directory.resolve_path("b/c.txt").read_utf8().unwrap(), "mounted");
+
+        let pack = temp.file().resolve_path("assets.pack");
+        let r = VfsPack::create(&directory, &pack);
+        Vfs::unmount(id);
+        r.unwrap();
+        let pack = VfsPack::open(&pack).unwrap();
+        assert_eq!(pack.read("b/c.txt").unwrap(), b"gamma");
+        assert_eq!(pack.entry("b/d.txt"), None);
+    }
+
+    #[test]
+    fn invalid_pack() {
+        let temp = File::create_temp_directory().unwrap();
+        let directory = temp.file();
+        create_tree(&directory);
+        let pack = temp.file().resolve_path("assets.pack");
+        VfsPack::create(&directory, &pack).unwrap();
+        let bytes = pack.read_bytes().unwrap();
+
+        let is_invalid = |bytes: &[u8]| {
+            pack.write(bytes).unwrap();
+            matches!(VfsPack::open(&pack), Err(error) if error.kind() == IoErrorKind::InvalidData)
+        };
+        // entry count and path data length beyond the pack
+        let mut b = bytes.clone();
+        b[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(is_invalid(&b));
+        let mut b = bytes.clone();
+        b[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(is_invalid(&b));
+        // file data beyond the pack
+        let mut b = bytes.clone();
+        b[PACK_HEADER_SIZE + 16..PACK_HEADER_SIZE + 24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(is_invalid(&b));
+        let mut b = bytes.clone();
+        b[PACK_HEADER_SIZE + 8..PACK_HEADER_SIZE + 16].copy_from_slice(&(bytes.len() as u64).to_le_bytes());
+        assert!(is_invalid(&b));
+        // truncated
+        assert!(is_invalid(&bytes[..bytes.len() - 1]));
+        assert!(is_invalid(&bytes[..PACK_HEADER_SIZE - 1]));
+        assert!(!is_invalid(&bytes));
+    }
+
+    #[test]
+    fn zip() {
+        let temp = File::create_temp_directory().unwrap();
+        let archive = temp.file().resolve_path("assets.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(archive.to_path_object()).unwrap());
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(&b"alpha".repeat(100)).unwrap();
+        writer.start_file("b/c.txt", options).unwrap();
+        writer.write_all(b"gamma").unwrap();
+        writer.finish().unwrap();
+
+        let zip = VfsZip::open(&archive).unwrap();
+        assert_eq!(zip.read("a.txt").unwrap(), b"alpha".repeat(100));
+        assert_eq!(zip.read("b/c.txt").unwrap(), b"gamma");
+        assert_eq!(zip.entry("b/c.txt"), Some(VfsEntry::File { size: 5 }));
+        assert!(zip.read("b").is_err());
+    }
+}
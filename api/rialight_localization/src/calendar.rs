@@ -0,0 +1,597 @@
+use super::date_time::{days_from_civil, civil_from_days, days_in_month};
+
+/// Rata Die (days since 0001-01-01 in the proleptic Gregorian calendar, from 1) of the Unix epoch.
+const UNIX_EPOCH_RATA_DIE: i64 = 719163;
+/// Rata Die of 1 Muharram 1 AH (July 16, 622 in the Julian calendar).
+const ISLAMIC_EPOCH: i64 = 227015;
+/// Rata Die of 1 Tishri 1 AM (October 7, 3761 BCE in the Julian calendar).
+const HEBREW_EPOCH: i64 = -1373427;
+
+/// First year of `UMM_AL_QURA_MONTHS`.
+const UMM_AL_QURA_FIRST_YEAR: i64 = 1300;
+/// Days since the Unix epoch of 1 Muharram 1300 AH (November 12, 1882) in the Umm al-Qura calendar.
+const UMM_AL_QURA_EPOCH: i64 = -31826;
+/// Umm al-Qura month lengths for the years 1300 to 1600 AH, as published by
+/// King Abdulaziz City for Science and Technology. Bit `n` is set
+/// if month `n + 1` has 30 days rather than 29.
+const UMM_AL_QURA_MONTHS: [u16; 301] = [
+    0x555, 0x2ab, 0x937, 0x2b6, 0x576, 0x36c, 0xb55, 0xaaa, 0x956, 0x49e,
+    0x95d, 0x2ba, 0x5b5, 0x3aa, 0xb4b, 0xa96, 0x52e, 0x2ad, 0x56d, 0xb5a,
+    0x752, 0xf25, 0xe8a, 0xd16, 0xa56, 0xab5, 0x6b4, 0xda9, 0xb92, 0xb25,
+    0x64b, 0xa9b, 0x35a, 0x6d9, 0x5d4, 0xda5, 0xd4a, 0xa95, 0x536, 0x975,
+    0x2f4, 0x6e9, 0x6d4, 0x6a9, 0x535, 0x25d, 0x4bd, 0x9ba, 0x3b4, 0xb69,
+    0xb2a, 0xa55, 0x4ad, 0xa5d, 0x2da, 0x6d9, 0xeaa, 0xe94, 0xd2a, 0xc56,
+    0x4ae, 0xa6d, 0x56a, 0xd55, 0xd4a, 0xa93, 0x52b, 0xa5b, 0x53a, 0x6b5,
+    0xea9, 0xd52, 0xd29, 0xa55, 0x4ad, 0x56d, 0xaea, 0x6e4, 0xed1, 0xda2,
+    0xaaa, 0x95a, 0x2da, 0x5b9, 0xbb2, 0x764, 0x6c9, 0x555, 0x2ab, 0x4db,
+    0xaba, 0x5b4, 0xda9, 0xd52, 0xaa5, 0x92d, 0x26d, 0x8ed, 0x2da, 0xad5,
+    0xaa5, 0xa4b, 0x497, 0x937, 0x2b6, 0x975, 0xd69, 0xd52, 0xc95, 0x92b,
+    0x25b, 0x4db, 0x9d5, 0x5d2, 0xda5, 0xd4a, 0xa95, 0x54d, 0xaad, 0x3aa,
+    0xbd2, 0xbc4, 0xb89, 0xa95, 0x52d, 0x5ad, 0xb6a, 0x6d4, 0xdc9, 0xd92,
+    0xaa6, 0x956, 0x2ae, 0x56d, 0x36a, 0xb55, 0xaaa, 0x94d, 0x49d, 0x95d,
+    0x2ba, 0x5b5, 0x5aa, 0xd55, 0xa9a, 0x92e, 0x26e, 0x55d, 0xada, 0x6d4,
+    0x6a5, 0xb27, 0xa4d, 0x4ad, 0x56d, 0xb5a, 0x754, 0xf49, 0xe92, 0xd26,
+    0xa56, 0x356, 0x6b5, 0xbaa, 0xb92, 0xb25, 0x68b, 0xa9b, 0x55a, 0xada,
+    0x5b4, 0xda9, 0xb52, 0xa9a, 0x536, 0x276, 0x575, 0xaf2, 0x6d4, 0x6a9,
+    0x555, 0x2ad, 0x4bd, 0x9ba, 0x574, 0xb69, 0xb52, 0xa95, 0x52d, 0xa5d,
+    0x4da, 0xad9, 0x6b2, 0xe95, 0xe2a, 0xc96, 0x92e, 0xaad, 0x56a, 0xd65,
+    0xd4a, 0xd15, 0x62b, 0xc5b, 0x53a, 0x6b5, 0xdb2, 0xd64, 0xd29, 0xa55,
+    0x4ad, 0x96d, 0xaea, 0x6e8, 0xed1, 0xda4, 0xd4a, 0xa6a, 0x2da, 0x5b9,
+    0xb72, 0xb68, 0x6d1, 0x655, 0x4ab, 0x95b, 0x2ba, 0x5b5, 0xda9, 0xd52,
+    0xca6, 0x94e, 0x46e, 0x95d, 0x4da, 0xad5, 0xaaa, 0xa4d, 0x49b, 0x937,
+    0x4b6, 0x975, 0xd6a, 0xd52, 0xaa5, 0x94b, 0x2ab, 0x55b, 0xad9, 0x5d2,
+    0xdc5, 0xd92, 0xb25, 0x555, 0xab5, 0x5b4, 0xba9, 0x7a2, 0x745, 0x593,
+    0xaab, 0x4d6, 0x9d6, 0x5d2, 0xba5, 0xb4a, 0xa95, 0x4ad, 0x15d, 0x2dd,
+    0x9da, 0x5b4, 0x5a9, 0x52d, 0x25b, 0x8b7, 0x176, 0x56d, 0xb6a, 0xaca,
+    0xa96, 0x52b, 0x15b, 0x2bb, 0x5b6, 0xdaa, 0xb94, 0xd46, 0xa8d, 0x52d,
+    0xa9d, 0x55a, 0x755, 0x749, 0xf13, 0xe4a, 0xa96, 0x556, 0x6b5, 0xbaa,
+    0xb94,
+];
+
+/// First days of the Japanese eras from Meiji, as Gregorian dates.
+const JAPANESE_ERAS: [(i64, u32, u32); 5] = [(1868, 10, 23), (1912, 7, 30), (1926, 12, 25), (1989, 1, 8), (2019, 5, 1)];
+
+/// Calendar system.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Calendar {
+    Gregorian,
+    /// Gregorian months and days with Japanese imperial eras from Meiji.
+    /// Earlier dates use the Gregorian era.
+    Japanese,
+    /// Thai solar calendar, counting years from 543 BCE.
+    Buddhist,
+    /// Solar Hijri calendar, as used in Iran and Afghanistan,
+    /// supported for years 1 to 3177 AP.
+    Persian,
+    /// Tabular Islamic calendar with the civil epoch.
+    IslamicCivil,
+    /// Umm al-Qura calendar of Saudi Arabia, whose months are computed from
+    /// astronomical conjunction and moonset and published as tables. Supported
+    /// for years 1300 to 1600 AH, outside of which the tabular civil calendar is used.
+    IslamicUmmAlQura,
+    Hebrew,
+    /// Republic of China calendar, as used in Taiwan, counting years from 1912.
+    Roc,
+}
+
+impl Calendar {
+    /// Identifies a calendar by BCP 47 identifier, as in the `-u-ca-` extension
+    /// of a language tag, such as `"buddhist"` or `"islamic-umalqura"`.
+    pub fn from_id(id: &str) -> Option<Calendar> {
+        match id.to_ascii_lowercase().as_ref() {
+            "gregory" | "gregorian" | "iso8601" => Some(Calendar::Gregorian),
+            "japanese" => Some(Calendar::Japanese),
+            "buddhist" => Some(Calendar::Buddhist),
+            "persian" => Some(Calendar::Persian),
+            "islamic" | "islamic-civil" | "islamicc" => Some(Calendar::IslamicCivil),
+            "islamic-umalqura" => Some(Calendar::IslamicUmmAlQura),
+            "hebrew" => Some(Calendar::Hebrew),
+            "roc" => Some(Calendar::Roc),
+            _ => None,
+        }
+    }
+
+    /// BCP 47 identifier of the calendar.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Calendar::Gregorian => "gregory",
+            Calendar::Japanese => "japanese",
+            Calendar::Buddhist => "buddhist",
+            Calendar::Persian => "persian",
+            Calendar::IslamicCivil => "islamic-civil",
+            Calendar::IslamicUmmAlQura => "islamic-umalqura",
+            Calendar::Hebrew => "hebrew",
+            Calendar::Roc => "roc",
+        }
+    }
+
+    /// Converts a Gregorian date to this calendar.
+    /// Returns `None` if the date is invalid or out of the supported range.
+    pub fn from_gregorian(&self, year: i64, month: u32, day: u32) -> Option<CalendarDate> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        let days = days_from_civil(year, month, day);
+        let date = |era: usize, year: i64, month: u32, day: u32, month_name_index: usize| {
+            Some(CalendarDate { m_calendar: *self, m_era: era, m_year: year, m_month: month, m_day: day, m_month_name_index: month_name_index })
+        };
+        match self {
+            Calendar::Gregorian => if year > 0 { date(1, year, month, day, month as usize - 1) } else { date(0, 1 - year, month, day, month as usize - 1) },
+            Calendar::Japanese => {
+                // era 0 is the Gregorian era, before Meiji
+                let era = JAPANESE_ERAS.iter().rposition(|(y, m, d)| days >= days_from_civil(*y, *m, *d)).map(|e| e + 1).unwrap_or(0);
+                let era_year = if era == 0 { year } else { year - JAPANESE_ERAS[era - 1].0 + 1 };
+                date(era, era_year, month, day, month as usize - 1)
+            },
+            Calendar::Buddhist => date(0, year + 543, month, day, month as usize - 1),
+            Calendar::Roc => if year > 1911 { date(1, year - 1911, month, day, month as usize - 1) } else { date(0, 1912 - year, month, day, month as usize - 1) },
+            Calendar::Persian => {
+                let (year, month, day) = persian_from_days(days)?;
+                date(0, year, month, day, month as usize - 1)
+            },
+            Calendar::IslamicCivil => {
+                let (year, month, day) = islamic_from_fixed(days + UNIX_EPOCH_RATA_DIE);
+                date(0, year, month, day, month as usize - 1)
+            },
+            Calendar::IslamicUmmAlQura => {
+                let (year, month, day) = umm_al_qura_from_days(days);
+                date(0, year, month, day, month as usize - 1)
+            },
+            Calendar::Hebrew => {
+                let (year, month, day) = hebrew_from_fixed(days + UNIX_EPOCH_RATA_DIE);
+                date(0, year, hebrew_ordinal_month(month, year), day, hebrew_month_name_index(month, year))
+            },
+        }
+    }
+}
+
+/// A date in a calendar system.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct CalendarDate {
+    m_calendar: Calendar,
+    m_era: usize,
+    m_year: i64,
+    m_month: u32,
+    m_day: u32,
+    m_month_name_index: usize,
+}
+
+impl CalendarDate {
+    /// Constructs a date from its era, year of the era, month and day.
+    /// The month is the ordinal month in the year from 1, which in Hebrew leap
+    /// years counts Adar I as the sixth month and Adar II as the seventh.
+    /// Returns `None` if the date does not exist.
+    pub fn new(calendar: Calendar, era: usize, year: i64, month: u32, day: u32) -> Option<CalendarDate> {
+        let (gregorian_year, gregorian_month, gregorian_day) = match calendar {
+            Calendar::Gregorian => (if era == 0 { 1 - year } else { year }, month, day),
+            Calendar::Japanese => (if era == 0 { year } else { JAPANESE_ERAS.get(era - 1)?.0 + year - 1 }, month, day),
+            Calendar::Buddhist => (year - 543, month, day),
+            Calendar::Roc => (if era == 0 { 1912 - year } else { year + 1911 }, month, day),
+            Calendar::Persian => civil_from_days(days_from_persian(year, month, day)?),
+            Calendar::IslamicCivil => {
+                if !(1..=12).contains(&month) || day == 0 || day > islamic_days_in_month(year, month) {
+                    return None;
+                }
+                civil_from_days(fixed_from_islamic(year, month, day) - UNIX_EPOCH_RATA_DIE)
+            },
+            Calendar::IslamicUmmAlQura => {
+                if !(1..=12).contains(&month) || day == 0 || day > umm_al_qura_days_in_month(year, month) {
+                    return None;
+                }
+                civil_from_days(days_from_umm_al_qura(year, month, day))
+            },
+            Calendar::Hebrew => {
+                let month = hebrew_month_from_ordinal(month, year)?;
+                if day == 0 || day > hebrew_last_day_of_month(month, year) {
+                    return None;
+                }
+                civil_from_days(fixed_from_hebrew(year, month, day) - UNIX_EPOCH_RATA_DIE)
+            },
+        };
+        // rejects dates outside their era, such as Reiwa 1, January 1
+        let r = calendar.from_gregorian(gregorian_year, gregorian_month, gregorian_day)?;
+        if r.m_era != era || r.m_year != year || r.m_month != month || r.m_day != day {
+            return None;
+        }
+        Some(r)
+    }
+
+    /// Gregorian year, month and day of the date.
+    pub fn to_gregorian(&self) -> (i64, u32, u32) {
+        match self.m_calendar {
+            Calendar::Gregorian => (if self.m_era == 0 { 1 - self.m_year } else { self.m_year }, self.m_month, self.m_day),
+            Calendar::Japanese => (if self.m_era == 0 { self.m_year } else { JAPANESE_ERAS[self.m_era - 1].0 + self.m_year - 1 }, self.m_month, self.m_day),
+            Calendar::Buddhist => (self.m_year - 543, self.m_month, self.m_day),
+            Calendar::Roc => (if self.m_era == 0 { 1912 - self.m_year } else { self.m_year + 1911 }, self.m_month, self.m_day),
+            Calendar::Persian => civil_from_days(days_from_persian(self.m_year, self.m_month, self.m_day).unwrap()),
+            Calendar::IslamicCivil => civil_from_days(fixed_from_islamic(self.m_year, self.m_month, self.m_day) - UNIX_EPOCH_RATA_DIE),
+            Calendar::IslamicUmmAlQura => civil_from_days(days_from_umm_al_qura(self.m_year, self.m_month, self.m_day)),
+            Calendar::Hebrew => {
+                let month = hebrew_month_from_ordinal(self.m_month, self.m_year).unwrap();
+                civil_from_days(fixed_from_hebrew(self.m_year, month, self.m_day) - UNIX_EPOCH_RATA_DIE)
+            },
+        }
+    }
+
+    pub fn calendar(&self) -> Calendar {
+        self.m_calendar
+    }
+
+    /// Era index: for the Gregorian and ROC calendars, 0 before the epoch and 1 after it;
+    /// for the Japanese calendar, 0 before Meiji, then 1 for Meiji to 5 for Reiwa;
+    /// otherwise always 0.
+    pub fn era(&self) -> usize {
+        self.m_era
+    }
+
+    /// Year of the era.
+    pub fn year(&self) -> i64 {
+        self.m_year
+    }
+
+    /// Ordinal month in the year, from 1.
+    pub fn month(&self) -> u32 {
+        self.m_month
+    }
+
+    pub fn day(&self) -> u32 {
+        self.m_day
+    }
+
+    /// Index into the month names of the calendar, which for the Hebrew calendar
+    /// distinguishes Adar (6) from Adar I (5) and Adar II (13).
+    pub(crate) fn month_name_index(&self) -> usize {
+        self.m_month_name_index
+    }
+}
+
+// Persian calendar, after the jalaali algorithm by Kazimierz M. Borkowski.
+
+const PERSIAN_BREAKS: [i64; 20] = [-61, 9, 38, 199, 426, 686, 756, 818, 1111, 1181, 1210, 1635, 2060, 2097, 2192, 2262, 2324, 2394, 2456, 3178];
+
+/// Returns whether the Persian year is a leap year, its Gregorian year
+/// and the day of March in which the Persian year starts.
+fn persian_year_info(year: i64) -> Option<(bool, i64, i64)> {
+    if year < PERSIAN_BREAKS[0] || year >= PERSIAN_BREAKS[PERSIAN_BREAKS.len() - 1] {
+        return None;
+    }
+    let gregorian_year = year + 621;
+    let mut leap_persian = -14;
+    let mut previous_break = PERSIAN_BREAKS[0];
+    let mut jump = 0;
+    for b in PERSIAN_BREAKS.iter().skip(1) {
+        jump = b - previous_break;
+        if year < *b {
+            break;
+        }
+        leap_persian += jump / 33 * 8 + jump % 33 / 4;
+        previous_break = *b;
+    }
+    let mut n = year - previous_break;
+    leap_persian += n / 33 * 8 + (n % 33 + 3) / 4;
+    if jump % 33 == 4 && jump - n == 4 {
+        leap_persian += 1;
+    }
+    let leap_gregorian = gregorian_year / 4 - (gregorian_year / 100 + 1) * 3 / 4 - 150;
+    let march = 20 + leap_persian - leap_gregorian;
+    if jump - n < 6 {
+        n = n - jump + (jump + 4) / 33 * 33;
+    }
+    let mut leap = ((n + 1) % 33 - 1) % 4;
+    if leap == -1 {
+        leap = 4;
+    }
+    Some((leap == 0, gregorian_year, march))
+}
+
+fn days_from_persian(year: i64, month: u32, day: u32) -> Option<i64> {
+    let (is_leap, gregorian_year, march) = persian_year_info(year)?;
+    let days_in_month = if month <= 6 { 31 } else if month <= 11 || is_leap { 30 } else { 29 };
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month {
+        return None;
+    }
+    let month = month as i64;
+    Some(days_from_civil(gregorian_year, 3, march as u32) + (month - 1) * 31 - month / 7 * (month - 7) + day as i64 - 1)
+}
+
+fn persian_from_days(days: i64) -> Option<(i64, u32, u32)> {
+    let (gregorian_year, _, _) = civil_from_days(days);
+    let mut year = gregorian_year - 621;
+    let (_, _, march) = persian_year_info(year)?;
+    let mut k = days - days_from_civil(gregorian_year, 3, march as u32);
+    if k >= 0 {
+        if k <= 185 {
+            return Some((year, (1 + k / 31) as u32, (k % 31 + 1) as u32));
+        }
+        k -= 186;
+    } else {
+        year -= 1;
+        let (is_leap, _, _) = persian_year_info(year)?;
+        k += 179;
+        if is_leap {
+            k += 1;
+        }
+    }
+    Some((year, (7 + k / 30) as u32, (k % 30 + 1) as u32))
+}
+
+// Tabular Islamic and Hebrew calendars, after Calendrical Calculations by Reingold and Dershowitz.
+
+fn fixed_from_islamic(year: i64, month: u32, day: u32) -> i64 {
+    ISLAMIC_EPOCH - 1 + (year - 1) * 354 + (3 + 11 * year).div_euclid(30) + 29 * (month as i64 - 1) + (month as i64).div_euclid(2) + day as i64
+}
+
+fn islamic_from_fixed(date: i64) -> (i64, u32, u32) {
+    let year = (30 * (date - ISLAMIC_EPOCH) + 10646).div_euclid(10631);
+    let prior_days = date - fixed_from_islamic(year, 1, 1);
+    let month = (11 * prior_days + 330).div_euclid(325) as u32;
+    let day = (date - fixed_from_islamic(year, month, 1) + 1) as u32;
+    (year, month, day)
+}
+
+fn islamic_days_in_month(year: i64, month: u32) -> u32 {
+    if month % 2 == 1 || (month == 12 && (14 + 11 * year).rem_euclid(30) < 11) { 30 } else { 29 }
+}
+
+// Umm al-Qura calendar, from its month length table.
+
+fn umm_al_qura_year_months(year: i64) -> Option<u16> {
+    usize::try_from(year - UMM_AL_QURA_FIRST_YEAR).ok().and_then(|i| UMM_AL_QURA_MONTHS.get(i).copied())
+}
+
+fn umm_al_qura_month_length(months: u16, month: u32) -> u32 {
+    if months & (1 << (month - 1)) != 0 { 30 } else { 29 }
+}
+
+fn umm_al_qura_days_in_month(year: i64, month: u32) -> u32 {
+    match umm_al_qura_year_months(year) {
+        Some(months) => umm_al_qura_month_length(months, month),
+        None => islamic_days_in_month(year, month),
+    }
+}
+
+fn days_from_umm_al_qura(year: i64, month: u32, day: u32) -> i64 {
+    let Some(months) = umm_al_qura_year_months(year) else {
+        return fixed_from_islamic(year, month, day) - UNIX_EPOCH_RATA_DIE;
+    };
+    let years = &UMM_AL_QURA_MONTHS[..(year - UMM_AL_QURA_FIRST_YEAR) as usize];
+    let r = UMM_AL_QURA_EPOCH + years.iter().map(|months| 348 + months.count_ones() as i64).sum::<i64>();
+    r + (1..month).map(|m| umm_al_qura_month_length(months, m) as i64).sum::<i64>() + day as i64 - 1
+}
+
+fn umm_al_qura_from_days(days: i64) -> (i64, u32, u32) {
+    let mut start = UMM_AL_QURA_EPOCH;
+    if days < start {
+        return islamic_from_fixed(days + UNIX_EPOCH_RATA_DIE);
+    }
+    for (year, months) in (UMM_AL_QURA_FIRST_YEAR..).zip(UMM_AL_QURA_MONTHS) {
+        let length = 348 + months.count_ones() as i64;
+        if days >= start + length {
+            start += length;
+            continue;
+        }
+        for month in 1..=12 {
+            let length = umm_al_qura_month_length(months, month) as i64;
+            if days < start + length {
+                return (year, month, (days - start + 1) as u32);
+            }
+            start += length;
+        }
+    }
+    islamic_from_fixed(days + UNIX_EPOCH_RATA_DIE)
+}
+
+fn hebrew_is_leap_year(year: i64) -> bool {
+    (7 * year + 1).rem_euclid(19) < 7
+}
+
+fn hebrew_last_month(year: i64) -> u32 {
+    if hebrew_is_leap_year(year) { 13 } else { 12 }
+}
+
+fn hebrew_elapsed_days(year: i64) -> i64 {
+    let months_elapsed = (235 * year - 234).div_euclid(19);
+    let parts_elapsed = 12084 + 13753 * months_elapsed;
+    let days = 29 * months_elapsed + parts_elapsed.div_euclid(25920);
+    if (3 * (days + 1)).rem_euclid(7) < 3 { days + 1 } else { days }
+}
+
+fn hebrew_new_year(year: i64) -> i64 {
+    let (previous, current, next) = (hebrew_elapsed_days(year - 1), hebrew_elapsed_days(year), hebrew_elapsed_days(year + 1));
+    let correction = if next - current == 356 { 2 } else if current - previous == 382 { 1 } else { 0 };
+    HEBREW_EPOCH + current + correction
+}
+
+fn hebrew_days_in_year(year: i64) -> i64 {
+    hebrew_new_year(year + 1) - hebrew_new_year(year)
+}
+
+/// Last day of a month, numbered from Nisan (1) as in the religious year,
+/// so that Tishri is 7 and Adar II is 13.
+fn hebrew_last_day_of_month(month: u32, year: i64) -> u32 {
+    let days_in_year = hebrew_days_in_year(year);
+    let long_marheshvan = days_in_year == 355 || days_in_year == 385;
+    let short_kislev = days_in_year == 353 || days_in_year == 383;
+    if [2, 4, 6, 10, 13].contains(&month)
+    || (month == 12 && !hebrew_is_leap_year(year))
+    || (month == 8 && !long_marheshvan)
+    || (month == 9 && short_kislev) { 29 } else { 30 }
+}
+
+fn fixed_from_hebrew(year: i64, month: u32, day: u32) -> i64 {
+    let mut r = hebrew_new_year(year) + day as i64 - 1;
+    if month < 7 {
+        for m in 7..=hebrew_last_month(year) {
+            r += hebrew_last_day_of_month(m, year) as i64;
+        }
+        for m in 1..month {
+            r += hebrew_last_day_of_month(m, year) as i64;
+        }
+    } else {
+        for m in 7..month {
+            r += hebrew_last_day_of_month(m, year) as i64;
+        }
+    }
+    r
+}
+
+fn hebrew_from_fixed(date: i64) -> (i64, u32, u32) {
+    let approx = ((date - HEBREW_EPOCH) as f64 / (35975351.0 / 98496.0)).floor() as i64 + 1;
+    let mut year = approx - 1;
+    while hebrew_new_year(year + 1) <= date {
+        year += 1;
+    }
+    let start = if date < fixed_from_hebrew(year, 1, 1) { 7 } else { 1 };
+    let mut month = start;
+    while date > fixed_from_hebrew(year, month, hebrew_last_day_of_month(month, year)) {
+        month += 1;
+    }
+    (year, month, (date - fixed_from_hebrew(year, month, 1) + 1) as u32)
+}
+
+/// Converts a month numbered from Nisan to the ordinal month of the civil year, from Tishri.
+fn hebrew_ordinal_month(month: u32, year: i64) -> u32 {
+    if month >= 7 { month - 6 } else { month + hebrew_last_month(year) - 6 }
+}
+
+fn hebrew_month_from_ordinal(ordinal: u32, year: i64) -> Option<u32> {
+    let last_month = hebrew_last_month(year);
+    if ordinal == 0 || ordinal > last_month {
+        return None;
+    }
+    Some(if ordinal <= last_month - 6 { ordinal + 6 } else { ordinal + 6 - last_month })
+}
+
+/// Index into the Hebrew month names: Tishri to Shevat (0 to 4), Adar I (5), Adar (6),
+/// Nisan to Elul (7 to 12) and Adar II (13).
+fn hebrew_month_name_index(month: u32, year: i64) -> usize {
+    match month {
+        7..=11 => month as usize - 7,
+        12 => if hebrew_is_leap_year(year) { 5 } else { 6 },
+        13 => 13,
+        _ => month as usize + 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DateFormatter, Language, TimeZone, ZonedDateTime};
+
+    fn convert(calendar: Calendar, year: i64, month: u32, day: u32) -> (usize, i64, u32, u32) {
+        let date = calendar.from_gregorian(year, month, day).unwrap();
+        (date.era(), date.year(), date.month(), date.day())
+    }
+
+    #[test]
+    fn known_dates() {
+        assert_eq!(convert(Calendar::Gregorian, 0, 12, 31), (0, 1, 12, 31));
+        assert_eq!(convert(Calendar::Buddhist, 2024, 2, 29), (0, 2567, 2, 29));
+        assert_eq!(convert(Calendar::Roc, 2024, 5, 1), (1, 113, 5, 1));
+        assert_eq!(convert(Calendar::Roc, 1912, 1, 1), (1, 1, 1, 1));
+        assert_eq!(convert(Calendar::Roc, 1911, 12, 31), (0, 1, 12, 31));
+
+        // Nowruz, with 1403 AP a leap year
+        assert_eq!(convert(Calendar::Persian, 2024, 3, 19), (0, 1402, 12, 29));
+        assert_eq!(convert(Calendar::Persian, 2024, 3, 20), (0, 1403, 1, 1));
+        assert_eq!(convert(Calendar::Persian, 2025, 3, 20), (0, 1403, 12, 30));
+        assert_eq!(convert(Calendar::Persian, 2025, 3, 21), (0, 1404, 1, 1));
+        assert_eq!(convert(Calendar::Persian, 2024, 9, 22), (0, 1403, 7, 1));
+
+        assert_eq!(convert(Calendar::IslamicCivil, 622, 7, 19), (0, 1, 1, 1));
+        assert_eq!(convert(Calendar::IslamicCivil, 2023, 7, 19), (0, 1445, 1, 1));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2023, 7, 19), (0, 1445, 1, 1));
+
+        // Rosh Hashanah 5784, a leap year, and Purim in Adar II
+        assert_eq!(convert(Calendar::Hebrew, 2023, 9, 16), (0, 5784, 1, 1));
+        assert_eq!(convert(Calendar::Hebrew, 2024, 3, 24), (0, 5784, 7, 14));
+        assert_eq!(Calendar::Hebrew.from_gregorian(2024, 3, 24).unwrap().month_name_index(), 13);
+        assert_eq!(Calendar::Hebrew.from_gregorian(2024, 2, 23).unwrap().month_name_index(), 5);
+        assert_eq!(convert(Calendar::Hebrew, 2023, 3, 7), (0, 5783, 6, 14));
+        assert_eq!(Calendar::Hebrew.from_gregorian(2023, 3, 7).unwrap().month_name_index(), 6);
+        assert_eq!(convert(Calendar::Hebrew, 2024, 10, 3), (0, 5785, 1, 1));
+
+        assert!(Calendar::Gregorian.from_gregorian(2023, 2, 29).is_none());
+    }
+
+    #[test]
+    fn umm_al_qura() {
+        // Eid al-Adha 1445 and the year 1446 start a day earlier than in the civil calendar
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2024, 6, 16), (0, 1445, 12, 10));
+        assert_eq!(convert(Calendar::IslamicCivil, 2024, 6, 16), (0, 1445, 12, 9));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2024, 7, 7), (0, 1446, 1, 1));
+        assert_eq!(convert(Calendar::IslamicCivil, 2024, 7, 7), (0, 1445, 12, 30));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2024, 3, 11), (0, 1445, 9, 1));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2024, 4, 10), (0, 1445, 10, 1));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2025, 3, 1), (0, 1446, 9, 1));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2023, 3, 23), (0, 1444, 9, 1));
+
+        // ends of the table
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 1882, 11, 12), (0, 1300, 1, 1));
+        assert_eq!(convert(Calendar::IslamicUmmAlQura, 2174, 11, 24), (0, 1600, 12, 29));
+        assert_eq!(CalendarDate::new(Calendar::IslamicUmmAlQura, 0, 1600, 12, 30).unwrap().to_gregorian(), (2174, 11, 25));
+        assert!(CalendarDate::new(Calendar::IslamicUmmAlQura, 0, 1445, 10, 30).is_none());
+        assert!(CalendarDate::new(Calendar::IslamicUmmAlQura, 0, 1445, 9, 30).is_some());
+    }
+
+    #[test]
+    fn japanese_eras() {
+        assert_eq!(convert(Calendar::Japanese, 1868, 10, 22), (0, 1868, 10, 22));
+        assert_eq!(convert(Calendar::Japanese, 1868, 10, 23), (1, 1, 10, 23));
+        assert_eq!(convert(Calendar::Japanese, 1989, 1, 7), (3, 64, 1, 7));
+        assert_eq!(convert(Calendar::Japanese, 1989, 1, 8), (4, 1, 1, 8));
+        assert_eq!(convert(Calendar::Japanese, 2019, 4, 30), (4, 31, 4, 30));
+        assert_eq!(convert(Calendar::Japanese, 2019, 5, 1), (5, 1, 5, 1));
+
+        assert_eq!(CalendarDate::new(Calendar::Japanese, 5, 1, 5, 1).unwrap().to_gregorian(), (2019, 5, 1));
+        assert_eq!(CalendarDate::new(Calendar::Japanese, 4, 31, 4, 30).unwrap().to_gregorian(), (2019, 4, 30));
+        // Reiwa 1 starts on May 1 and Heisei 31 ends on April 30
+        assert!(CalendarDate::new(Calendar::Japanese, 5, 1, 1, 1).is_none());
+        assert!(CalendarDate::new(Calendar::Japanese, 4, 31, 5, 1).is_none());
+        assert!(CalendarDate::new(Calendar::Japanese, 6, 1, 1, 1).is_none());
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(CalendarDate::new(Calendar::Persian, 0, 1403, 12, 30).is_some());
+        assert!(CalendarDate::new(Calendar::Persian, 0, 1402, 12, 30).is_none());
+        assert!(CalendarDate::new(Calendar::Persian, 0, 3178, 1, 1).is_none());
+        assert!(CalendarDate::new(Calendar::IslamicCivil, 0, 1445, 12, 30).is_some());
+        assert!(CalendarDate::new(Calendar::IslamicCivil, 0, 1444, 12, 30).is_none());
+        assert!(CalendarDate::new(Calendar::Hebrew, 0, 5784, 13, 29).is_some());
+        assert!(CalendarDate::new(Calendar::Hebrew, 0, 5783, 13, 1).is_none());
+        assert!(CalendarDate::new(Calendar::Buddhist, 0, 2567, 2, 29).is_some());
+        assert!(CalendarDate::new(Calendar::Buddhist, 0, 2566, 2, 29).is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        let calendars = [
+            Calendar::Gregorian, Calendar::Japanese, Calendar::Buddhist, Calendar::Persian,
+            Calendar::IslamicCivil, Calendar::IslamicUmmAlQura, Calendar::Hebrew, Calendar::Roc,
+        ];
+        for calendar in calendars {
+            assert_eq!(Calendar::from_id(calendar.id()), Some(calendar));
+            let mut days = days_from_civil(1850, 1, 1);
+            while days < days_from_civil(2150, 1, 1) {
+                let (year, month, day) = civil_from_days(days);
+                let date = calendar.from_gregorian(year, month, day).unwrap();
+                assert_eq!(date.to_gregorian(), (year, month, day), "{:?} {:?}", calendar, date);
+                assert_eq!(CalendarDate::new(calendar, date.era(), date.year(), date.month(), date.day()).as_ref(), Some(&date));
+                days += 13;
+            }
+        }
+    }
+
+    #[test]
+    fn language_calendar() {
+        assert_eq!(Language::parse("th-TH").unwrap().calendar(), Calendar::Buddhist);
+        assert_eq!(Language::parse("en-US-u-ca-islamic-umalqura").unwrap().calendar(), Calendar::IslamicUmmAlQura);
+        assert_eq!(Language::parse("ja-JP-u-ca-japanese").unwrap().calendar(), Calendar::Japanese);
+        assert_eq!(Language::parse("en-US").unwrap().calendar(), Calendar::Gregorian);
+
+        let date = ZonedDateTime::from_timestamp(1556668800, 0, &TimeZone::utc());
+        let formatter = DateFormatter::with_pattern(&Language::parse("en-US-u-ca-japanese").unwrap(), "G y-MM-dd");
+        assert_eq!(formatter.format(&date), "Reiwa 1-05-01");
+    }
+}
@@ -0,0 +1,299 @@
+use std::cmp::Ordering;
+use unicode_normalization::{UnicodeNormalization, char::canonical_combining_class};
+use unicode_segmentation::UnicodeSegmentation;
+use rialight_util::AnyStringType;
+use super::Language;
+
+const COMBINING_DOT_ABOVE: char = '\u{307}';
+const COMBINING_DIAERESIS: char = '\u{308}';
+
+/// Language-specific case rules.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum CaseTailoring {
+    Root,
+    /// Turkish and Azerbaijani dotted and dotless I.
+    Turkic,
+    /// Lithuanian retains the dot of i under accents.
+    Lithuanian,
+    /// Greek drops accents when uppercasing.
+    Greek,
+    /// Dutch titlecases the ij digraph as a whole.
+    Dutch,
+}
+
+/// Maps text between cases following the rules of a language,
+/// and compares text ignoring case.
+///
+/// Plain `to_lowercase`/`to_uppercase` apply the language-independent Unicode mappings,
+/// which are wrong for Turkish and Azerbaijani (I, ı, İ, i), Lithuanian (i with accents)
+/// and Greek (accents in uppercase). `CaseMapper` applies the tailorings for these languages.
+#[derive(Copy, Clone, Debug)]
+pub struct CaseMapper {
+    m_tailoring: CaseTailoring,
+}
+
+impl CaseMapper {
+    /// Constructs a `CaseMapper` for the given language.
+    pub fn new(language: &Language) -> Self {
+        let tailoring = match language.language_code().as_ref() {
+            "tr" | "az" => CaseTailoring::Turkic,
+            "lt" => CaseTailoring::Lithuanian,
+            "el" => CaseTailoring::Greek,
+            "nl" => CaseTailoring::Dutch,
+            _ => CaseTailoring::Root,
+        };
+        Self { m_tailoring: tailoring }
+    }
+
+    /// Converts text to uppercase.
+    pub fn to_upper(&self, text: impl AnyStringType) -> String {
+        let text = text.convert();
+        match self.m_tailoring {
+            CaseTailoring::Turkic => text.replace('i', "İ").to_uppercase(),
+            CaseTailoring::Lithuanian => remove_dots_after_soft_dotted(text).to_uppercase(),
+            CaseTailoring::Greek => greek_upper(text),
+            _ => text.to_uppercase(),
+        }
+    }
+
+    /// Converts text to lowercase.
+    pub fn to_lower(&self, text: impl AnyStringType) -> String {
+        let text = text.convert();
+        match self.m_tailoring {
+            CaseTailoring::Turkic => text.replace("I\u{307}", "i").replace('I', "ı").replace('İ', "i").to_lowercase(),
+            CaseTailoring::Lithuanian => lithuanian_lower(text),
+            _ => text.to_lowercase(),
+        }
+    }
+
+    /// Converts text to title case, that is, uppercases the first letter of each word
+    /// and lowercases the remaining letters.
+    pub fn to_title(&self, text: impl AnyStringType) -> String {
+        let mut r = String::new();
+        for word in text.convert().split_word_bounds() {
+            let mut chars = word.chars();
+            let first = chars.next();
+            if first.is_none() || !first.unwrap().is_alphanumeric() {
+                r.push_str(&self.to_lower(word));
+                continue;
+            }
+            let first = first.unwrap();
+            let rest = chars.as_str();
+            if self.m_tailoring == CaseTailoring::Dutch && (first == 'i' || first == 'I') && rest.starts_with(['j', 'J']) {
+                r.push_str("IJ");
+                r.push_str(&self.to_lower(&rest[1..]));
+                continue;
+            }
+            r.push_str(&self.title_char(first));
+            r.push_str(&self.to_lower(rest));
+        }
+        r
+    }
+
+    fn title_char(&self, c: char) -> String {
+        match c {
+            'Ǆ' | 'ǅ' | 'ǆ' => return "ǅ".to_owned(),
+            'Ǉ' | 'ǈ' | 'ǉ' => return "ǈ".to_owned(),
+            'Ǌ' | 'ǋ' | 'ǌ' => return "ǋ".to_owned(),
+            'Ǳ' | 'ǲ' | 'ǳ' => return "ǲ".to_owned(),
+            'i' if self.m_tailoring == CaseTailoring::Turkic => return "İ".to_owned(),
+            _ => {},
+        }
+        // Greek keeps accents on a titlecased letter.
+        let upper: String = c.to_uppercase().collect();
+        let mut chars = upper.chars();
+        let first = chars.next().unwrap_or(c);
+        // expansions, such as "ß" to "SS", titlecase as "Ss"
+        first.to_string() + &chars.as_str().to_lowercase()
+    }
+
+    /// Applies full case folding for caseless matching, after compatibility normalization,
+    /// so that text differing only in case, or in compatibility forms, folds to the same string.
+    pub fn fold(&self, text: impl AnyStringType) -> String {
+        let text: String = text.convert().nfkc().collect();
+        let mut folded = String::new();
+        for c in text.chars() {
+            match c {
+                'I' if self.m_tailoring == CaseTailoring::Turkic => folded.push('ı'),
+                'İ' if self.m_tailoring == CaseTailoring::Turkic => folded.push('i'),
+                // dotless i has no folding, while its uppercase folds to i
+                'ı' => folded.push('ı'),
+                _ => folded.extend(c.to_uppercase().flat_map(|c| c.to_lowercase())),
+            }
+        }
+        folded.nfkc().collect()
+    }
+
+    /// Returns `true` if both texts are equal ignoring case, otherwise `false`.
+    pub fn caseless_eq(&self, a: impl AnyStringType, b: impl AnyStringType) -> bool {
+        self.fold(a) == self.fold(b)
+    }
+
+    /// Compares two texts ignoring case. This is a code point order of the folded texts,
+    /// not a collation order.
+    pub fn caseless_cmp(&self, a: impl AnyStringType, b: impl AnyStringType) -> Ordering {
+        self.fold(a).cmp(&self.fold(b))
+    }
+
+    /// Returns `true` if `text` contains `search` ignoring case, otherwise `false`,
+    /// as in search boxes.
+    pub fn caseless_contains(&self, text: impl AnyStringType, search: impl AnyStringType) -> bool {
+        self.fold(text).contains(&self.fold(search))
+    }
+}
+
+fn is_soft_dotted(c: char) -> bool {
+    matches!(c, 'i' | 'j' | 'į' | 'ɨ' | 'ʝ' | 'ɉ' | 'і' | 'ј' | 'ⁱ' | 'ᵢ' | 'ⱼ')
+}
+
+/// Removes U+0307 COMBINING DOT ABOVE after a soft-dotted letter, so that
+/// uppercasing "i̇́" gives "Í" rather than "İ́".
+fn remove_dots_after_soft_dotted(text: &str) -> String {
+    let mut r = String::new();
+    let mut after_soft_dotted = false;
+    for c in text.chars() {
+        let class = canonical_combining_class(c);
+        if c == COMBINING_DOT_ABOVE && after_soft_dotted {
+            continue;
+        }
+        if class == 0 {
+            after_soft_dotted = is_soft_dotted(c);
+        } else if class == 230 {
+            after_soft_dotted = false;
+        }
+        r.push(c);
+    }
+    r
+}
+
+/// Lowercases keeping the dot of I, J and Į when more accents follow,
+/// and of Ì, Í and Ĩ.
+fn lithuanian_lower(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut r = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let more_above = chars[i + 1..].iter()
+            .take_while(|c| canonical_combining_class(**c) != 0)
+            .any(|c| canonical_combining_class(*c) == 230);
+        match c {
+            'I' if more_above => r.push_str("i\u{307}"),
+            'J' if more_above => r.push_str("j\u{307}"),
+            'Į' if more_above => r.push_str("į\u{307}"),
+            'Ì' => r.push_str("i\u{307}\u{300}"),
+            'Í' => r.push_str("i\u{307}\u{301}"),
+            'Ĩ' => r.push_str("i\u{307}\u{303}"),
+            _ => r.push(*c),
+        }
+    }
+    r.to_lowercase()
+}
+
+/// Uppercases Greek text dropping accents and breathings. When the accent of the first
+/// vowel of a diphthong is dropped, the second vowel gets a diaeresis, as in "Μάιος" to "ΜΑΪΟΣ".
+fn greek_upper(text: &str) -> String {
+    let chars: Vec<char> = text.nfd().collect();
+    let mut r = String::new();
+    let mut previous_base: Option<char> = None;
+    let mut dropped_accent = false;
+    for (i, c) in chars.iter().enumerate() {
+        match c {
+            // varia, oxia/tonos, perispomeni, psili, dasia, koronis
+            '\u{300}' | '\u{301}' | '\u{342}' | '\u{313}' | '\u{314}' | '\u{343}' => {
+                dropped_accent = true;
+                continue;
+            },
+            // dialytika tonos
+            '\u{344}' => {
+                r.push(COMBINING_DIAERESIS);
+                continue;
+            },
+            _ => {},
+        }
+        if canonical_combining_class(*c) == 0 {
+            let lower = c.to_lowercase().next().unwrap_or(*c);
+            let has_marks = chars.get(i + 1).map(|n| canonical_combining_class(*n) != 0).unwrap_or(false);
+            let forms_diphthong = matches!(previous_base, Some('α' | 'ε' | 'ο' | 'υ' | 'η')) && matches!(lower, 'ι' | 'υ');
+            r.extend(c.to_uppercase());
+            if dropped_accent && forms_diphthong && !has_marks {
+                r.push(COMBINING_DIAERESIS);
+            }
+            previous_base = Some(lower);
+            dropped_accent = false;
+        } else {
+            r.extend(c.to_uppercase());
+        }
+    }
+    r.nfc().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapper(tag: &str) -> CaseMapper {
+        CaseMapper::new(&Language::parse(tag).unwrap())
+    }
+
+    #[test]
+    fn turkic() {
+        for tag in ["tr-TR", "az-AZ"] {
+            let m = mapper(tag);
+            assert_eq!(m.to_upper("istanbul ılık"), "İSTANBUL ILIK");
+            assert_eq!(m.to_lower("İSTANBUL ILIK"), "istanbul ılık");
+            assert_eq!(m.to_lower("I\u{307}"), "i");
+            assert_eq!(m.to_title("istanbul"), "İstanbul");
+            assert_eq!(m.fold("I"), "ı");
+            assert_eq!(m.fold("ı"), "ı");
+            assert_eq!(m.fold("İ"), "i");
+            assert!(m.caseless_eq("KIŞ", "kış"));
+            assert!(m.caseless_eq("İZMİR", "izmir"));
+            assert!(!m.caseless_eq("ı", "i"));
+        }
+        let m = mapper("en-US");
+        assert_eq!(m.to_upper("istanbul ılık"), "ISTANBUL ILIK");
+        assert_eq!(m.fold("ı"), "ı");
+        assert!(m.caseless_eq("KIŞ", "kiş"));
+    }
+
+    #[test]
+    fn lithuanian() {
+        let m = mapper("lt-LT");
+        assert_eq!(m.to_lower("Ì"), "i\u{307}\u{300}");
+        assert_eq!(m.to_lower("Í"), "i\u{307}\u{301}");
+        assert_eq!(m.to_lower("I\u{303}"), "i\u{307}\u{303}");
+        assert_eq!(m.to_lower("I"), "i");
+        assert_eq!(m.to_upper("i\u{307}\u{301}"), "I\u{301}");
+        assert_eq!(m.to_upper("j\u{307}\u{303}"), "J\u{303}");
+        assert_eq!(mapper("en-US").to_lower("Ì"), "ì");
+    }
+
+    #[test]
+    fn greek() {
+        let m = mapper("el-GR");
+        assert_eq!(m.to_upper("Μάιος"), "ΜΑΪΟΣ");
+        assert_eq!(m.to_upper("άνθρωπος"), "ΑΝΘΡΩΠΟΣ");
+        assert_eq!(m.to_upper("ἀρετή"), "ΑΡΕΤΗ");
+        assert_eq!(m.to_upper("ΐ"), "Ϊ");
+        assert_eq!(m.to_title("άνθρωπος"), "Άνθρωπος");
+        assert_eq!(mapper("en-US").to_upper("ά"), "Ά");
+    }
+
+    #[test]
+    fn final_sigma() {
+        let m = mapper("el-GR");
+        assert_eq!(m.to_lower("ΟΔΟΣ ΣΟΦΙΑΣ"), "οδος σοφιας");
+        assert_eq!(m.to_lower("Σ"), "σ");
+        assert!(m.caseless_eq("οδος", "ΟΔΟΣ"));
+    }
+
+    #[test]
+    fn normalization() {
+        let m = mapper("en-US");
+        assert!(m.caseless_eq("Caf\u{e9}", "CAFE\u{301}"));
+        assert!(m.caseless_eq("ﬁle", "FILE"));
+        assert!(m.caseless_eq("Straße", "STRASSE"));
+        assert!(m.caseless_contains("Na\u{ef}ve Caf\u{e9}", "CAFE\u{301}"));
+        assert_eq!(mapper("el-GR").to_upper("Μα\u{301}ιος"), "ΜΑΪΟΣ");
+        assert_eq!(m.caseless_cmp("a", "B"), Ordering::Less);
+    }
+}
@@ -0,0 +1,374 @@
+use std::{collections::HashMap, convert::TryInto, ops::Deref};
+use rialight_filesystem::{File, IoError};
+use super::message::{MessagePart, parse_message, parse_attributes, format_attributes};
+
+const MAGIC: &[u8; 8] = b"RLCAT\0\0\0";
+const VERSION: u32 = 3;
+const HEADER_SIZE: usize = 32;
+const STRING_RECORD_SIZE: usize = 8;
+const ENTRY_RECORD_SIZE: usize = 12;
+const PART_RECORD_SIZE: usize = 12;
+const NO_STRING: u32 = u32::MAX;
+
+const PART_TEXT: u8 = 0;
+const PART_VARIABLE: u8 = 1;
+const PART_REFERENCE: u8 = 2;
+const PART_START_TAG: u8 = 3;
+const PART_END_TAG: u8 = 4;
+const PART_EMPTY_TAG: u8 = 5;
+
+/// A compiled translation catalog.
+///
+/// A catalog is a flat table from message identifier to pre-parsed message,
+/// compiled from a JSON resource by `LocaleCatalog::compile`.
+/// Catalogs opened from the file system are memory-mapped and queried in place.
+///
+/// # Format
+///
+/// All integers are little-endian.
+///
+/// - Header: magic `RLCAT\0\0\0`, version (`u32`), CRC-32 of everything after the header (`u32`),
+///   then the string, entry and part counts (`u32` each) and the string data length (`u32`).
+/// - String table: offset and length (`u32` each) into the string data, for each interned string.
+/// - Entry table: message identifier string, first part and part count (`u32` each),
+///   sorted by identifier.
+/// - Part table: kind (`u8`, padded to 4 bytes) and two string indices (`u32` each):
+///   the text, the variable name and format, the reference identifier and variant,
+///   or the markup tag name and attributes.
+/// - String data: UTF-8 bytes.
+pub struct LocaleCatalog {
+    m_bytes: LocaleCatalogBytes,
+    m_string_count: usize,
+    m_entry_count: usize,
+    m_part_count: usize,
+}
+
+enum LocaleCatalogBytes {
+    Owned(Vec<u8>),
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for LocaleCatalogBytes {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            LocaleCatalogBytes::Owned(b) => b.as_ref(),
+            LocaleCatalogBytes::Mapped(b) => b.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LocaleCatalogError {
+    Io(IoError),
+    InvalidFormat,
+    UnsupportedVersion(u32),
+    ChecksumMismatch,
+}
+
+impl From<IoError> for LocaleCatalogError {
+    fn from(error: IoError) -> Self {
+        LocaleCatalogError::Io(error)
+    }
+}
+
+impl LocaleCatalog {
+    /// Compiles a JSON resource, as found in `res/lang`, into catalog bytes.
+    pub fn compile(root: &serde_json::Value) -> Vec<u8> {
+        let mut messages: Vec<(String, &str)> = vec![];
+        collect_messages(root, &mut vec![], &mut messages);
+        messages.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut strings = StringInterner::default();
+        let mut entries: Vec<(u32, u32, u32)> = vec![];
+        let mut parts: Vec<(u8, u32, u32)> = vec![];
+        for (id, message) in messages.iter() {
+            let id = strings.intern(id);
+            let first_part = parts.len() as u32;
+            for part in parse_message(message) {
+                parts.push(match part {
+                    MessagePart::Text(s) => (PART_TEXT, strings.intern(&s), NO_STRING),
+                    MessagePart::Variable { name, format } => (PART_VARIABLE, strings.intern(&name), format.map(|f| strings.intern(&f)).unwrap_or(NO_STRING)),
+                    MessagePart::Reference { id, variant } => (PART_REFERENCE, strings.intern(&id), variant.map(|v| strings.intern(&v)).unwrap_or(NO_STRING)),
+                    MessagePart::StartTag { name, attributes } => (PART_START_TAG, strings.intern(&name), strings.intern(&format_attributes(&attributes))),
+                    MessagePart::EndTag { name } => (PART_END_TAG, strings.intern(&name), NO_STRING),
+                    MessagePart::EmptyTag { name, attributes } => (PART_EMPTY_TAG, strings.intern(&name), strings.intern(&format_attributes(&attributes))),
+                });
+            }
+            entries.push((id, first_part, parts.len() as u32 - first_part));
+        }
+
+        let mut body: Vec<u8> = vec![];
+        for (offset, len) in strings.m_records.iter() {
+            body.extend(offset.to_le_bytes());
+            body.extend(len.to_le_bytes());
+        }
+        for (id, first_part, part_count) in entries.iter() {
+            body.extend(id.to_le_bytes());
+            body.extend(first_part.to_le_bytes());
+            body.extend(part_count.to_le_bytes());
+        }
+        for (kind, a, b) in parts.iter() {
+            body.extend([*kind, 0, 0, 0]);
+            body.extend(a.to_le_bytes());
+            body.extend(b.to_le_bytes());
+        }
+        body.extend(strings.m_data.iter());
+
+        let mut r: Vec<u8> = Vec::with_capacity(HEADER_SIZE + body.len());
+        r.extend(MAGIC);
+        r.extend(VERSION.to_le_bytes());
+        r.extend(crc32fast::hash(&body).to_le_bytes());
+        r.extend((strings.m_records.len() as u32).to_le_bytes());
+        r.extend((entries.len() as u32).to_le_bytes());
+        r.extend((parts.len() as u32).to_le_bytes());
+        r.extend((strings.m_data.len() as u32).to_le_bytes());
+        r.extend(body);
+        r
+    }
+
+    /// Loads a catalog from bytes, verifying its checksum.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<LocaleCatalog, LocaleCatalogError> {
+        LocaleCatalog::validate(LocaleCatalogBytes::Owned(bytes))
+    }
+
+    /// Memory-maps a catalog file, verifying its checksum.
+    /// A catalog mounted in the virtual file system is read instead.
+    pub fn open(file: &File) -> Result<LocaleCatalog, LocaleCatalogError> {
+        if file.is_mounted() {
+            return LocaleCatalog::from_bytes(file.read_bytes()?);
+        }
+        let f = std::fs::File::open(file.application_based_native_path())?;
+        // The mapping is only read, and catalog files are not expected to change while in use.
+        let map = unsafe { memmap2::Mmap::map(&f)? };
+        LocaleCatalog::validate(LocaleCatalogBytes::Mapped(map))
+    }
+
+    fn validate(bytes: LocaleCatalogBytes) -> Result<LocaleCatalog, LocaleCatalogError> {
+        if bytes.len() < HEADER_SIZE || &bytes[0..8] != MAGIC {
+            return Err(LocaleCatalogError::InvalidFormat);
+        }
+        let version = read_u32(&bytes, 8);
+        // Earlier versions only lack variable formats (version 1) and markup (versions 1 and 2).
+        if version == 0 || version > VERSION {
+            return Err(LocaleCatalogError::UnsupportedVersion(version));
+        }
+        let string_count = read_u32(&bytes, 16) as usize;
+        let entry_count = read_u32(&bytes, 20) as usize;
+        let part_count = read_u32(&bytes, 24) as usize;
+        let data_len = read_u32(&bytes, 28) as usize;
+        let expected_len = HEADER_SIZE
+            + string_count * STRING_RECORD_SIZE
+            + entry_count * ENTRY_RECORD_SIZE
+            + part_count * PART_RECORD_SIZE
+            + data_len;
+        if bytes.len() != expected_len {
+            return Err(LocaleCatalogError::InvalidFormat);
+        }
+        if crc32fast::hash(&bytes[HEADER_SIZE..]) != read_u32(&bytes, 12) {
+            return Err(LocaleCatalogError::ChecksumMismatch);
+        }
+        let r = LocaleCatalog {
+            m_bytes: bytes,
+            m_string_count: string_count,
+            m_entry_count: entry_count,
+            m_part_count: part_count,
+        };
+        for i in 0..string_count {
+            let (offset, len) = r.string_record(i as u32);
+            if offset + len > data_len || std::str::from_utf8(&r.m_bytes[r.data_start() + offset..r.data_start() + offset + len]).is_err() {
+                return Err(LocaleCatalogError::InvalidFormat);
+            }
+        }
+        Ok(r)
+    }
+
+    /// Number of messages in the catalog.
+    pub fn len(&self) -> usize {
+        self.m_entry_count
+    }
+
+    /// Returns `true` if the catalog has no messages, otherwise `false`.
+    pub fn is_empty(&self) -> bool {
+        self.m_entry_count == 0
+    }
+
+    /// Returns `true` if the catalog contains the message identifier, otherwise `false`.
+    pub fn contains(&self, id: &str) -> bool {
+        self.find_entry(id).is_some()
+    }
+
+    /// Message identifiers in the catalog, in sorted order.
+    pub fn ids(&self) -> Vec<String> {
+        (0..self.m_entry_count).map(|i| self.string(self.entry(i).0).to_owned()).collect()
+    }
+
+    /// Returns the pre-parsed message for the identifier.
+    pub(crate) fn get_parts(&self, id: &str) -> Option<Vec<MessagePart>> {
+        let (_, first_part, part_count) = self.entry(self.find_entry(id)?);
+        let mut r: Vec<MessagePart> = vec![];
+        for i in first_part..(first_part + part_count) {
+            if i as usize >= self.m_part_count {
+                return None;
+            }
+            let start = self.parts_start() + i as usize * PART_RECORD_SIZE;
+            let kind = self.m_bytes[start];
+            let a = self.string(read_u32(&self.m_bytes, start + 4)).to_owned();
+            let b = read_u32(&self.m_bytes, start + 8);
+            r.push(match kind {
+                PART_TEXT => MessagePart::Text(a),
+                PART_VARIABLE => MessagePart::Variable { name: a, format: if b == NO_STRING { None } else { Some(self.string(b).to_owned()) } },
+                PART_REFERENCE => MessagePart::Reference { id: a, variant: if b == NO_STRING { None } else { Some(self.string(b).to_owned()) } },
+                PART_START_TAG => MessagePart::StartTag { name: a, attributes: parse_attributes(self.string(b)) },
+                PART_END_TAG => MessagePart::EndTag { name: a },
+                PART_EMPTY_TAG => MessagePart::EmptyTag { name: a, attributes: parse_attributes(self.string(b)) },
+                _ => return None,
+            });
+        }
+        Some(r)
+    }
+
+    fn find_entry(&self, id: &str) -> Option<usize> {
+        let (mut low, mut high) = (0, self.m_entry_count);
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.string(self.entry(mid).0).cmp(id) {
+                std::cmp::Ordering::Equal => return Some(mid),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    fn entry(&self, i: usize) -> (u32, u32, u32) {
+        let start = self.entries_start() + i * ENTRY_RECORD_SIZE;
+        (read_u32(&self.m_bytes, start), read_u32(&self.m_bytes, start + 4), read_u32(&self.m_bytes, start + 8))
+    }
+
+    fn string(&self, i: u32) -> &str {
+        if i as usize >= self.m_string_count {
+            return "";
+        }
+        let (offset, len) = self.string_record(i);
+        let start = self.data_start() + offset;
+        // validated when the catalog was loaded
+        std::str::from_utf8(&self.m_bytes[start..start + len]).unwrap_or("")
+    }
+
+    fn string_record(&self, i: u32) -> (usize, usize) {
+        let start = HEADER_SIZE + i as usize * STRING_RECORD_SIZE;
+        (read_u32(&self.m_bytes, start) as usize, read_u32(&self.m_bytes, start + 4) as usize)
+    }
+
+    fn entries_start(&self) -> usize {
+        HEADER_SIZE + self.m_string_count * STRING_RECORD_SIZE
+    }
+
+    fn parts_start(&self) -> usize {
+        self.entries_start() + self.m_entry_count * ENTRY_RECORD_SIZE
+    }
+
+    fn data_start(&self) -> usize {
+        self.parts_start() + self.m_part_count * PART_RECORD_SIZE
+    }
+}
+
+#[derive(Default)]
+struct StringInterner {
+    m_indices: HashMap<String, u32>,
+    m_records: Vec<(u32, u32)>,
+    m_data: Vec<u8>,
+}
+
+impl StringInterner {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(i) = self.m_indices.get(s) {
+            return *i;
+        }
+        let i = self.m_records.len() as u32;
+        self.m_records.push((self.m_data.len() as u32, s.len() as u32));
+        self.m_data.extend(s.as_bytes());
+        self.m_indices.insert(s.to_owned(), i);
+        i
+    }
+}
+
+fn collect_messages<'a>(value: &'a serde_json::Value, path: &mut Vec<String>, output: &mut Vec<(String, &'a str)>) {
+    if let Some(map) = value.as_object() {
+        for (k, v) in map.iter() {
+            path.push(k.clone());
+            collect_messages(v, path, output);
+            path.pop();
+        }
+    } else if let Some(s) = value.as_str() {
+        output.push((path.join("."), s));
+    }
+}
+
+fn read_u32(bytes: &[u8], start: usize) -> u32 {
+    u32::from_le_bytes(bytes[start..start + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(json: &str) -> Vec<u8> {
+        LocaleCatalog::compile(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn round_trip() {
+        let catalog = LocaleCatalog::from_bytes(compile(r#"{
+            "title": "Crystal Forge",
+            "menu": {"play": "Play", "coins": "$count:number coins"},
+            "open": "Open the $ref(title, genitive) <link id=\"forge\">door</link><icon name=\"coin\"/>"
+        }"#)).unwrap();
+        assert_eq!(catalog.len(), 4);
+        assert_eq!(catalog.ids(), ["menu.coins", "menu.play", "open", "title"]);
+        assert!(catalog.contains("menu.play"));
+        assert!(!catalog.contains("menu"));
+        assert_eq!(catalog.get_parts("title"), Some(vec![MessagePart::Text("Crystal Forge".to_owned())]));
+        assert_eq!(catalog.get_parts("menu.coins"), Some(parse_message("$count:number coins")));
+        assert_eq!(catalog.get_parts("open"), Some(parse_message("Open the $ref(title, genitive) <link id=\"forge\">door</link><icon name=\"coin\"/>")));
+        assert_eq!(catalog.get_parts("missing"), None);
+
+        let empty = LocaleCatalog::from_bytes(compile("{}")).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let mut bytes = compile(r#"{"title": "Crystal Forge"}"#);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(LocaleCatalog::from_bytes(bytes), Err(LocaleCatalogError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn invalid_format() {
+        let bytes = compile(r#"{"title": "Crystal Forge"}"#);
+        for len in [0, 8, HEADER_SIZE, bytes.len() - 1] {
+            assert!(matches!(LocaleCatalog::from_bytes(bytes[..len].to_vec()), Err(LocaleCatalogError::InvalidFormat)));
+        }
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(matches!(LocaleCatalog::from_bytes(extended), Err(LocaleCatalogError::InvalidFormat)));
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(matches!(LocaleCatalog::from_bytes(magic), Err(LocaleCatalogError::InvalidFormat)));
+        let mut version = bytes;
+        version[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(LocaleCatalog::from_bytes(version), Err(LocaleCatalogError::UnsupportedVersion(v)) if v == VERSION + 1));
+    }
+
+    #[test]
+    fn open() {
+        let path = std::env::temp_dir().join(format!("locale-catalog-test-{}.rlcat", std::process::id()));
+        std::fs::write(&path, compile(r#"{"menu": {"play": "Play"}}"#)).unwrap();
+        let catalog = LocaleCatalog::open(&File::new(path.to_str().unwrap()));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(catalog.unwrap().get_parts("menu.play"), Some(vec![MessagePart::Text("Play".to_owned())]));
+    }
+}
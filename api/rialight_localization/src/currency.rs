@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref CURRENCY_DATA: HashMap<String, CurrencyData> = {
+        serde_json::from_str::<HashMap<String, CurrencyData>>(include_str!("../data/currency.json")).unwrap()
+    };
+}
+
+fn default_fraction_digits() -> usize {
+    2
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CurrencyData {
+    /// Symbols, starting with the one used outside the currency's regions
+    s: Vec<String>,
+    /// Fraction digits
+    #[serde(default = "default_fraction_digits")]
+    d: usize,
+    /// Regions using the currency
+    r: Vec<String>,
+}
+
+/// An amount of money in an ISO 4217 currency.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CurrencyAmount {
+    pub amount: f64,
+    /// ISO 4217 code, such as `"USD"`.
+    pub currency: String,
+}
+
+/// ISO 4217 code of the currency used in a region.
+pub(crate) fn currency_for_region(region: &str) -> Option<String> {
+    CURRENCY_DATA.iter().find(|(_, data)| data.r.iter().any(|r| r == region)).map(|(code, _)| code.clone())
+}
+
+/// Symbol of a currency as written in a region: the local symbol, such as `"$"`
+/// for USD in the United States, or the international one, such as `"US$"`.
+/// Unknown currencies are written as their code.
+pub(crate) fn currency_symbol(code: &str, region: &str) -> String {
+    match CURRENCY_DATA.get(code) {
+        Some(data) if data.r.iter().any(|r| r == region) => data.s.get(1).unwrap_or(&data.s[0]).clone(),
+        Some(data) => data.s[0].clone(),
+        None => code.to_owned(),
+    }
+}
+
+/// Number of fraction digits of a currency, such as 0 for JPY.
+pub(crate) fn currency_fraction_digits(code: &str) -> usize {
+    CURRENCY_DATA.get(code).map(|data| data.d).unwrap_or(2)
+}
+
+/// Finds the currencies denoted by an ISO 4217 code or a symbol.
+/// An ambiguous symbol, such as `"$"`, denotes the currency of the region if it
+/// is one of them.
+pub(crate) fn find_currencies(symbol: &str, region: &str) -> Vec<String> {
+    let code = symbol.to_ascii_uppercase();
+    if CURRENCY_DATA.contains_key(&code) {
+        return vec![code];
+    }
+    let mut r: Vec<String> = CURRENCY_DATA.iter()
+        .filter(|(_, data)| data.s.iter().any(|s| s.to_lowercase() == symbol.to_lowercase()))
+        .map(|(code, _)| code.clone())
+        .collect();
+    if let Some(local) = r.iter().find(|code| CURRENCY_DATA[*code].r.iter().any(|r| r == region)) {
+        return vec![local.clone()];
+    }
+    r.sort();
+    r
+}
+
+/// Whether a character may be part of a currency symbol or code.
+pub(crate) fn is_currency_symbol_char(c: char) -> bool {
+    !(c.is_ascii_digit() || c.is_whitespace() || c == '-' || c == '+' || c == '\u{2212}' || c == '%')
+}
@@ -0,0 +1,718 @@
+use std::{cell::Cell, collections::HashMap};
+use serde::{Deserialize, Serialize};
+use super::{Language, ZonedDateTime, Calendar, CalendarDate, TimeZone, Disambiguation, ParseError, ParseErrorKind, ParseMode};
+use super::parsing::{is_space, trimmed_range};
+
+lazy_static! {
+    static ref DATE_DATA: HashMap<String, DateData> = {
+        serde_json::from_str::<HashMap<String, DateData>>(include_str!("../data/date.json")).unwrap()
+    };
+}
+
+lazy_static! {
+    static ref CALENDAR_DATA: HashMap<String, CalendarData> = {
+        serde_json::from_str::<HashMap<String, CalendarData>>(include_str!("../data/calendar.json")).unwrap()
+    };
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct DateData {
+    /// Month names
+    m: Vec<String>,
+    /// Abbreviated month names
+    ma: Vec<String>,
+    /// Weekday names, starting on Sunday
+    w: Vec<String>,
+    /// Abbreviated weekday names, starting on Sunday
+    wa: Vec<String>,
+    /// AM and PM markers
+    ap: Vec<String>,
+    /// Full, long, medium and short date patterns
+    dp: Vec<String>,
+    /// Full, long, medium and short time patterns
+    tp: Vec<String>,
+    /// Date-time pattern, where `{1}` is the date and `{0}` is the time
+    dtp: String,
+    /// UTC offset format, such as `GMT{0}`
+    gmt: String,
+    /// UTC offset format for a zero offset
+    gmtz: String,
+    /// Location format for time zones, such as `{0} Time`
+    rg: String,
+}
+
+/// Calendar-specific names and date patterns, by language.
+#[derive(Serialize, Deserialize, Clone)]
+struct CalendarData {
+    eras: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    narrow_eras: HashMap<String, Vec<String>>,
+    /// Month names, if they differ from the Gregorian ones
+    #[serde(default)]
+    months: HashMap<String, Vec<String>>,
+    /// Full, long, medium and short date patterns
+    #[serde(default)]
+    dp: HashMap<String, Vec<String>>,
+}
+
+/// Names of a calendar in a language.
+#[derive(Clone)]
+struct CalendarNames {
+    m_eras: Vec<String>,
+    m_narrow_eras: Vec<String>,
+    m_months: Option<Vec<String>>,
+}
+
+/// Length of a formatted date or time.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum DateStyle {
+    /// Such as "Tuesday, April 12, 2022" or "3:30:00 PM Los Angeles Time".
+    Full,
+    /// Such as "April 12, 2022" or "3:30:00 PM PDT".
+    Long,
+    /// Such as "Apr 12, 2022" or "3:30:00 PM".
+    Medium,
+    /// Such as "4/12/22" or "3:30 PM".
+    Short,
+}
+
+impl DateStyle {
+    fn index(&self) -> usize {
+        match self {
+            DateStyle::Full => 0,
+            DateStyle::Long => 1,
+            DateStyle::Medium => 2,
+            DateStyle::Short => 3,
+        }
+    }
+}
+
+/// Clock convention for formatted times.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum HourCycle {
+    /// 1 to 12, with AM and PM.
+    H12,
+    /// 0 to 23.
+    H23,
+}
+
+/// How a time zone is named.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum TimeZoneNameStyle {
+    /// Such as "GMT-3" or "GMT+5:30".
+    ShortOffset,
+    /// Such as "GMT-03:00".
+    LongOffset,
+    /// Exemplar city of the time zone, such as "Sao Paulo Time".
+    Location,
+    /// Abbreviation from the time zone database, such as "PDT",
+    /// or the short offset if the database has none.
+    Abbreviation,
+}
+
+/// Formats zoned date-times for a language, either with predefined styles or with
+/// a CLDR date pattern, such as `"EEEE, d MMMM y HH:mm"`.
+///
+/// Dates are formatted in the calendar of the language, as given by `Language::calendar`,
+/// unless overriden by the options.
+///
+/// Month and weekday names are available for English, Portuguese, Spanish, French,
+/// German, Japanese, Chinese, Thai, Persian, Arabic and Hebrew; other languages use English.
+#[derive(Clone)]
+pub struct DateFormatter {
+    m_data: DateData,
+    m_calendar: Calendar,
+    m_calendar_names: CalendarNames,
+    m_pattern: String,
+}
+
+impl DateFormatter {
+    pub fn new(language: &Language, options: &DateFormatOptions) -> Self {
+        let data = date_data(language);
+        let calendar = options.m_calendar.get().unwrap_or_else(|| language.calendar());
+        let calendar_data = CALENDAR_DATA.get(calendar_data_key(calendar)).unwrap();
+        let date_patterns = if calendar == Calendar::Gregorian { data.dp.clone() } else {
+            calendar_data.dp.get(&language.language_code()).or_else(|| calendar_data.dp.get("en")).cloned().unwrap_or_else(|| data.dp.clone())
+        };
+        let date_pattern = options.m_date_style.get().map(|s| date_patterns[s.index()].clone());
+        let mut time_pattern = options.m_time_style.get().map(|s| data.tp[s.index()].clone());
+        if let (Some(pattern), Some(hour_cycle)) = (time_pattern.as_ref(), options.m_hour_cycle.get()) {
+            time_pattern = Some(apply_hour_cycle(pattern, hour_cycle));
+        }
+        let pattern = match (date_pattern, time_pattern) {
+            (Some(date), Some(time)) => data.dtp.replace("{1}", &date).replace("{0}", &time),
+            (None, Some(time)) => time,
+            (Some(date), None) => date,
+            (None, None) => date_patterns[DateStyle::Medium.index()].clone(),
+        };
+        Self { m_data: data, m_calendar: calendar, m_calendar_names: calendar_names(language, calendar), m_pattern: pattern }
+    }
+
+    /// Constructs a `DateFormatter` with a CLDR date pattern. Supported fields are
+    /// `G` (era), `GGGGG` (narrow era), `y`, `yy`, `M` to `MMMM`, `d`, `dd`, `E` to `EEEE`,
+    /// `H`, `HH`, `h`, `hh`, `m`, `mm`, `s`, `ss`, `a`, `z` (abbreviation) and `zzzz` (location),
+    /// with literals in single quotes.
+    pub fn with_pattern(language: &Language, pattern: &str) -> Self {
+        let calendar = language.calendar();
+        Self { m_data: date_data(language), m_calendar: calendar, m_calendar_names: calendar_names(language, calendar), m_pattern: pattern.to_owned() }
+    }
+
+    pub fn calendar(&self) -> Calendar {
+        self.m_calendar
+    }
+
+    /// The CLDR date pattern used by the formatter.
+    pub fn pattern(&self) -> String {
+        self.m_pattern.clone()
+    }
+
+    pub fn format(&self, date: &ZonedDateTime) -> String {
+        let calendar_date = self.m_calendar.from_gregorian(date.year(), date.month(), date.day())
+            .or_else(|| Calendar::Gregorian.from_gregorian(date.year(), date.month(), date.day()))
+            .unwrap();
+        let mut r = String::new();
+        for token in pattern_tokens(&self.m_pattern) {
+            match token {
+                PatternToken::Literal(text) => r.push_str(&text),
+                PatternToken::Field(field, count) => r.push_str(&self.format_field(date, &calendar_date, field, count)),
+            }
+        }
+        r
+    }
+
+    fn format_field(&self, date: &ZonedDateTime, calendar_date: &CalendarDate, field: char, count: usize) -> String {
+        let data = &self.m_data;
+        let names = &self.m_calendar_names;
+        match (field, count) {
+            ('G', 5) => names.m_narrow_eras.get(calendar_date.era()).cloned().unwrap_or_default(),
+            ('G', _) => names.m_eras.get(calendar_date.era()).cloned().unwrap_or_default(),
+            ('y', 2) => format!("{:02}", calendar_date.year().rem_euclid(100)),
+            ('y', _) => format!("{:01$}", calendar_date.year(), count),
+            ('M', 1) => calendar_date.month().to_string(),
+            ('M', 2) => format!("{:02}", calendar_date.month()),
+            ('M', _) if names.m_months.is_some() => names.m_months.as_ref().unwrap()[calendar_date.month_name_index()].clone(),
+            ('M', 3) => data.ma[calendar_date.month_name_index()].clone(),
+            ('M', _) => data.m[calendar_date.month_name_index()].clone(),
+            ('d', 1) => calendar_date.day().to_string(),
+            ('d', _) => format!("{:02}", calendar_date.day()),
+            ('E', 4) => data.w[date.weekday().index_from_sunday()].clone(),
+            ('E', _) => data.wa[date.weekday().index_from_sunday()].clone(),
+            ('H', 1) => date.hour().to_string(),
+            ('H', _) => format!("{:02}", date.hour()),
+            ('h', _) => {
+                let hour = if date.hour().is_multiple_of(12) { 12 } else { date.hour() % 12 };
+                if count == 1 { hour.to_string() } else { format!("{:02}", hour) }
+            },
+            ('m', 1) => date.minute().to_string(),
+            ('m', _) => format!("{:02}", date.minute()),
+            ('s', 1) => date.second().to_string(),
+            ('s', _) => format!("{:02}", date.second()),
+            ('a', _) => data.ap[if date.hour() < 12 { 0 } else { 1 }].clone(),
+            ('z', 4) => self.time_zone_name(date, TimeZoneNameStyle::Location),
+            ('z', _) => self.time_zone_name(date, TimeZoneNameStyle::Abbreviation),
+            _ => field.to_string().repeat(count),
+        }
+    }
+
+    /// Parses a date-time as formatted by the formatter, in a time zone.
+    /// Fields missing from the pattern are taken from the current date, and
+    /// from midnight for the time; in lenient mode, input may also end before
+    /// the time fields. Years with two digits are within 80 years before and
+    /// 20 years after the current year, and years are in the current era unless
+    /// the pattern has an era.
+    pub fn parse(&self, text: &str, time_zone: &TimeZone, mode: ParseMode) -> Result<ZonedDateTime, ParseError> {
+        let lenient = mode == ParseMode::Lenient;
+        let chars: Vec<char> = text.chars().collect();
+        let (start, end) = trimmed_range(&chars, mode)?;
+        let now = ZonedDateTime::now(time_zone);
+        let today = self.m_calendar.from_gregorian(now.year(), now.month(), now.day()).unwrap();
+        let data = &self.m_data;
+        let names = &self.m_calendar_names;
+        let months = names.m_months.clone().unwrap_or_else(|| data.m.clone());
+        let abbreviated_months = names.m_months.clone().unwrap_or_else(|| data.ma.clone());
+
+        let mut fields = ParsedFields::default();
+        let mut i = start;
+        for token in pattern_tokens(&self.m_pattern) {
+            if lenient {
+                i = skip_separators(&chars, i, end);
+                if i == end && fields.day.is_some() {
+                    break;
+                }
+            }
+            let field_start = i;
+            match token {
+                PatternToken::Literal(literal) if lenient => {
+                    let literal: Vec<char> = literal.chars().filter(|c| c.is_alphanumeric()).collect();
+                    if !literal.is_empty() && match_names(&chars, i, end, &[literal.iter().collect::<String>()]).is_some() {
+                        i += literal.len();
+                    }
+                },
+                PatternToken::Literal(literal) => {
+                    for c in literal.chars() {
+                        if i < end && (chars[i] == c || (is_space(c) && is_space(chars[i]))) {
+                            i += 1;
+                        } else {
+                            return Err(unexpected(&chars, i, end));
+                        }
+                    }
+                },
+                PatternToken::Field(field, count) => {
+                    let digits = |i: usize, min: usize, max: usize| read_digits(&chars, i, end, if lenient { 1 } else { min }, max);
+                    let name = |i: usize, candidates: &[Vec<String>]| -> Result<(usize, usize), ParseError> {
+                        candidates.iter()
+                            .filter_map(|names| match_names(&chars, i, end, names))
+                            .max_by_key(|(_, length)| *length)
+                            .map(|(index, length)| {
+                                // abbreviations may be typed with a period
+                                let period = lenient && i + length < end && chars[i + length] == '.';
+                                (index, i + length + if period { 1 } else { 0 })
+                            })
+                            .ok_or_else(|| {
+                                let word: String = chars[i..end].iter().take_while(|c| c.is_alphabetic()).collect();
+                                if word.is_empty() { unexpected(&chars, i, end) } else { ParseError::new(i, ParseErrorKind::UnknownName(word)) }
+                            })
+                    };
+                    match field {
+                        // the current era is assumed when lenient input omits it
+                        'G' if lenient && (i == end || chars[i].is_ascii_digit()) => continue,
+                        'G' => {
+                            let candidates = if lenient { vec![names.m_eras.clone(), names.m_narrow_eras.clone()] } else if count == 5 { vec![names.m_narrow_eras.clone()] } else { vec![names.m_eras.clone()] };
+                            let (era, next) = name(i, &candidates)?;
+                            fields.era = Some(era);
+                            i = next;
+                        },
+                        'y' => {
+                            let (year, next) = if count == 2 && !lenient { digits(i, 2, 2)? } else { digits(i, 1, 9)? };
+                            fields.year = Some((year as i64, next - i == 2 && (count == 2 || lenient)));
+                            i = next;
+                        },
+                        'M' if count <= 2 && (!lenient || i < end && chars[i].is_ascii_digit()) => {
+                            let (month, next) = digits(i, count, 2)?;
+                            fields.month = Some((ParsedMonth::Number(month), i));
+                            i = next;
+                        },
+                        'M' => {
+                            let candidates = if lenient { vec![months.clone(), abbreviated_months.clone()] } else if count == 3 { vec![abbreviated_months.clone()] } else { vec![months.clone()] };
+                            let (index, next) = name(i, &candidates)?;
+                            fields.month = Some((ParsedMonth::NameIndex(index), i));
+                            i = next;
+                        },
+                        'd' => {
+                            let (day, next) = digits(i, count, 2)?;
+                            fields.day = Some((day, i));
+                            i = next;
+                        },
+                        'E' => {
+                            let candidates = if lenient { vec![data.w.clone(), data.wa.clone()] } else if count == 4 { vec![data.w.clone()] } else { vec![data.wa.clone()] };
+                            let (weekday, next) = name(i, &candidates)?;
+                            fields.weekday = Some((weekday, i));
+                            i = next;
+                        },
+                        'H' | 'h' | 'm' | 's' => {
+                            let (value, next) = digits(i, if field == 'm' || field == 's' { 2 } else { count }, 2)?;
+                            let limit = match field { 'H' => 23, 'h' => 12, _ => 59 };
+                            if value > limit || (field == 'h' && value == 0) {
+                                return Err(ParseError::new(i, ParseErrorKind::InvalidDate));
+                            }
+                            match field {
+                                'H' => fields.hour = Some(value),
+                                'h' => fields.hour12 = Some(value),
+                                'm' => fields.minute = Some(value),
+                                _ => fields.second = Some(value),
+                            }
+                            i = next;
+                        },
+                        'a' => {
+                            let (period, next) = name(i, std::slice::from_ref(&data.ap))?;
+                            fields.pm = Some(period == 1);
+                            i = next;
+                        },
+                        'z' => {
+                            // the time zone is given, so its name is skipped
+                            while i < end && !is_space(chars[i]) {
+                                i += 1;
+                            }
+                        },
+                        _ => return Err(unexpected(&chars, i, end)),
+                    }
+                    if i == field_start && field != 'z' {
+                        return Err(unexpected(&chars, i, end));
+                    }
+                },
+            }
+        }
+        if lenient {
+            i = skip_separators(&chars, i, end);
+        }
+        if i < end {
+            return Err(unexpected(&chars, i, end));
+        }
+
+        let era = fields.era.unwrap_or(today.era());
+        let year = match fields.year {
+            Some((year, true)) => {
+                let current = today.year();
+                let year = current - current.rem_euclid(100) + year;
+                if year > current + 20 { year - 100 } else if year <= current - 80 { year + 100 } else { year }
+            },
+            Some((year, false)) => year,
+            None => today.year(),
+        };
+        let date_position = fields.day.map(|(_, position)| position).unwrap_or(start);
+        let month = match fields.month {
+            Some((ParsedMonth::Number(month), _)) => month,
+            Some((ParsedMonth::NameIndex(index), position)) => (1..=13)
+                .find(|m| CalendarDate::new(self.m_calendar, era, year, *m, 1).map(|d| d.month_name_index()) == Some(index))
+                .ok_or_else(|| ParseError::new(position, ParseErrorKind::InvalidDate))?,
+            None => today.month(),
+        };
+        let day = fields.day.map(|(day, _)| day).unwrap_or(today.day());
+        let date = CalendarDate::new(self.m_calendar, era, year, month, day)
+            .ok_or_else(|| ParseError::new(date_position, ParseErrorKind::InvalidDate))?;
+        let hour = match (fields.hour12, fields.pm) {
+            (Some(hour), Some(true)) => hour % 12 + 12,
+            (Some(hour), _) => hour % 12,
+            (None, _) => fields.hour.unwrap_or(0),
+        };
+        let (year, month, day) = date.to_gregorian();
+        let r = ZonedDateTime::from_local(year, month, day, hour, fields.minute.unwrap_or(0), fields.second.unwrap_or(0), time_zone, Disambiguation::Compatible)
+            .ok_or_else(|| ParseError::new(date_position, ParseErrorKind::InvalidDate))?;
+        if let (Some((weekday, position)), false) = (fields.weekday, lenient) {
+            if r.weekday().index_from_sunday() != weekday {
+                return Err(ParseError::new(position, ParseErrorKind::InvalidDate));
+            }
+        }
+        Ok(r)
+    }
+
+    /// Localized name of the time zone of a date-time.
+    pub fn time_zone_name(&self, date: &ZonedDateTime, style: TimeZoneNameStyle) -> String {
+        let offset = date.offset();
+        let id = date.time_zone().id();
+        let city = id.rsplit('/').next().unwrap_or("").replace('_', " ");
+        let has_location = id.contains('/') && !id.starts_with("Etc/");
+        match style {
+            TimeZoneNameStyle::Abbreviation if offset.abbreviation.chars().all(|c| c.is_ascii_alphabetic()) && !offset.abbreviation.is_empty() => offset.abbreviation,
+            TimeZoneNameStyle::Location if has_location => self.m_data.rg.replace("{0}", &city),
+            TimeZoneNameStyle::LongOffset => self.format_utc_offset(offset.utc_offset, true),
+            _ => self.format_utc_offset(offset.utc_offset, false),
+        }
+    }
+
+    fn format_utc_offset(&self, utc_offset: i32, long: bool) -> String {
+        if utc_offset == 0 {
+            return self.m_data.gmtz.clone();
+        }
+        let sign = if utc_offset < 0 { '-' } else { '+' };
+        let (hours, minutes) = (utc_offset.unsigned_abs() / 3600, utc_offset.unsigned_abs() / 60 % 60);
+        let offset = if long {
+            format!("{}{:02}:{:02}", sign, hours, minutes)
+        } else if minutes == 0 {
+            format!("{}{}", sign, hours)
+        } else {
+            format!("{}{}:{:02}", sign, hours, minutes)
+        };
+        self.m_data.gmt.replace("{0}", &offset)
+    }
+}
+
+enum PatternToken {
+    Literal(String),
+    /// Pattern letter and its repetition count.
+    Field(char, usize),
+}
+
+/// Splits a CLDR date pattern into fields and literals, where literals
+/// may be quoted and `''` is a single quote.
+fn pattern_tokens(pattern: &str) -> Vec<PatternToken> {
+    let mut r: Vec<PatternToken> = vec![];
+    let mut literal = String::new();
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' {
+            i += 1;
+            if chars.get(i) == Some(&'\'') {
+                literal.push('\'');
+                i += 1;
+                continue;
+            }
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if chars.get(i + 1) == Some(&'\'') {
+                        literal.push('\'');
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                literal.push(chars[i]);
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        if !c.is_ascii_alphabetic() {
+            literal.push(c);
+            i += 1;
+            continue;
+        }
+        let mut count = 1;
+        while chars.get(i + count) == Some(&c) {
+            count += 1;
+        }
+        if !literal.is_empty() {
+            r.push(PatternToken::Literal(std::mem::take(&mut literal)));
+        }
+        r.push(PatternToken::Field(c, count));
+        i += count;
+    }
+    if !literal.is_empty() {
+        r.push(PatternToken::Literal(literal));
+    }
+    r
+}
+
+enum ParsedMonth {
+    Number(u32),
+    /// Index into the month names.
+    NameIndex(usize),
+}
+
+/// Fields read by `DateFormatter::parse`, with the positions of the date fields.
+#[derive(Default)]
+struct ParsedFields {
+    era: Option<usize>,
+    /// Year and whether it has two digits.
+    year: Option<(i64, bool)>,
+    month: Option<(ParsedMonth, usize)>,
+    day: Option<(u32, usize)>,
+    weekday: Option<(usize, usize)>,
+    hour: Option<u32>,
+    hour12: Option<u32>,
+    minute: Option<u32>,
+    second: Option<u32>,
+    pm: Option<bool>,
+}
+
+fn read_digits(chars: &[char], start: usize, end: usize, min: usize, max: usize) -> Result<(u32, usize), ParseError> {
+    let mut i = start;
+    while i < end && i - start < max && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i - start < min {
+        return Err(unexpected(chars, i, end));
+    }
+    let digits: String = chars[start..i].iter().collect();
+    Ok((digits.parse().unwrap(), i))
+}
+
+/// Finds the longest name matching the input, ignoring case,
+/// returning its index and length.
+fn match_names(chars: &[char], start: usize, end: usize, names: &[String]) -> Option<(usize, usize)> {
+    names.iter().enumerate()
+        .filter(|(_, name)| {
+            let name: Vec<char> = name.chars().collect();
+            !name.is_empty() && start + name.len() <= end
+                && chars[start..start + name.len()].iter().zip(name.iter()).all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+        })
+        .map(|(index, name)| (index, name.chars().count()))
+        .max_by_key(|(_, length)| *length)
+}
+
+/// Skips spaces and punctuation, which lenient parsing does not distinguish.
+fn skip_separators(chars: &[char], mut i: usize, end: usize) -> usize {
+    while i < end && !chars[i].is_alphanumeric() {
+        i += 1;
+    }
+    i
+}
+
+fn unexpected(chars: &[char], i: usize, end: usize) -> ParseError {
+    if i < end { ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i])) } else { ParseError::new(i, ParseErrorKind::UnexpectedEnd) }
+}
+
+fn date_data(language: &Language) -> DateData {
+    DATE_DATA.get(&language.tag().to_ascii_lowercase())
+        .or_else(|| DATE_DATA.get(&language.language_code()))
+        .unwrap_or_else(|| DATE_DATA.get("en").unwrap())
+        .clone()
+}
+
+fn calendar_data_key(calendar: Calendar) -> &'static str {
+    match calendar {
+        Calendar::IslamicCivil | Calendar::IslamicUmmAlQura => "islamic",
+        _ => calendar.id(),
+    }
+}
+
+fn calendar_names(language: &Language, calendar: Calendar) -> CalendarNames {
+    let data = CALENDAR_DATA.get(calendar_data_key(calendar)).unwrap();
+    let localized = |map: &HashMap<String, Vec<String>>| map.get(&language.language_code()).or_else(|| map.get("en")).cloned();
+    let eras = localized(&data.eras).unwrap_or_default();
+    CalendarNames {
+        m_narrow_eras: localized(&data.narrow_eras).unwrap_or_else(|| eras.clone()),
+        m_eras: eras,
+        m_months: localized(&data.months),
+    }
+}
+
+/// Rewrites the hours of a time pattern for an hour cycle.
+fn apply_hour_cycle(pattern: &str, hour_cycle: HourCycle) -> String {
+    match hour_cycle {
+        HourCycle::H12 if pattern.contains('H') => {
+            let pattern = pattern.replace("HH", "h").replace('H', "h");
+            // place the AM/PM marker after the seconds or minutes
+            let end = pattern.find("ss").map(|i| i + 2).or_else(|| pattern.find("mm").map(|i| i + 2)).unwrap_or(pattern.len());
+            pattern[..end].to_owned() + " a" + &pattern[end..]
+        },
+        HourCycle::H23 if pattern.contains('h') => {
+            pattern.replace(" a", "").replace("hh", "HH").replace('h', "HH")
+        },
+        _ => pattern.to_owned(),
+    }
+}
+
+pub struct DateFormatOptions {
+    m_date_style: Cell<Option<DateStyle>>,
+    m_time_style: Cell<Option<DateStyle>>,
+    m_hour_cycle: Cell<Option<HourCycle>>,
+    m_calendar: Cell<Option<Calendar>>,
+}
+
+impl Clone for DateFormatOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_date_style: self.m_date_style.clone(),
+            m_time_style: self.m_time_style.clone(),
+            m_hour_cycle: self.m_hour_cycle.clone(),
+            m_calendar: self.m_calendar.clone(),
+        }
+    }
+}
+
+impl DateFormatOptions {
+    /// Options for a medium date without time.
+    pub fn new() -> Self {
+        DateFormatOptions {
+            m_date_style: Cell::new(Some(DateStyle::Medium)),
+            m_time_style: Cell::new(None),
+            m_hour_cycle: Cell::new(None),
+            m_calendar: Cell::new(None),
+        }
+    }
+
+    /// Date style, or `None` to format only the time.
+    pub fn date_style(&self, value: Option<DateStyle>) -> &Self {
+        self.m_date_style.set(value);
+        self
+    }
+
+    /// Time style, or `None` to format only the date.
+    pub fn time_style(&self, value: Option<DateStyle>) -> &Self {
+        self.m_time_style.set(value);
+        self
+    }
+
+    /// Overrides the clock convention of the language, as from a user setting.
+    pub fn hour_cycle(&self, value: HourCycle) -> &Self {
+        self.m_hour_cycle.set(Some(value));
+        self
+    }
+
+    /// Overrides the calendar of the language.
+    pub fn calendar(&self, value: Calendar) -> &Self {
+        self.m_calendar.set(Some(value));
+        self
+    }
+}
+
+impl Default for DateFormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ParseMode::{Lenient, Strict};
+
+    fn formatter(tag: &str, date_style: Option<DateStyle>, time_style: Option<DateStyle>) -> DateFormatter {
+        let options = DateFormatOptions::new();
+        options.date_style(date_style).time_style(time_style);
+        DateFormatter::new(&Language::parse(tag).unwrap(), &options)
+    }
+
+    fn parse(formatter: &DateFormatter, text: &str, mode: ParseMode) -> Result<(i64, u32, u32, u32, u32), ParseErrorKind> {
+        formatter.parse(text, &TimeZone::utc(), mode)
+            .map(|d| (d.year(), d.month(), d.day(), d.hour(), d.minute()))
+            .map_err(|e| e.kind())
+    }
+
+    #[test]
+    fn format() {
+        let date = ZonedDateTime::from_local(2024, 3, 5, 13, 30, 0, &TimeZone::utc(), Disambiguation::Reject).unwrap();
+        assert_eq!(formatter("en-US", Some(DateStyle::Full), None).format(&date), "Tuesday, March 5, 2024");
+        assert_eq!(formatter("en-US", Some(DateStyle::Short), Some(DateStyle::Short)).format(&date), "3/5/24, 1:30 PM");
+        assert_eq!(formatter("de-DE", Some(DateStyle::Medium), None).format(&date), "05.03.2024");
+        assert_eq!(DateFormatter::with_pattern(&Language::parse("en-US").unwrap(), "'week' E HH:mm").format(&date), "week Tue 13:30");
+    }
+
+    #[test]
+    fn parse_strict() {
+        let short = formatter("en-US", Some(DateStyle::Short), None);
+        assert_eq!(parse(&short, "3/5/24", Strict), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&short, "03/05/99", Strict), Ok((1999, 3, 5, 0, 0)));
+        assert_eq!(parse(&short, "2/30/24", Strict), Err(ParseErrorKind::InvalidDate));
+        assert_eq!(parse(&short, "3-5-24", Strict), Err(ParseErrorKind::UnexpectedCharacter('-')));
+        assert_eq!(parse(&short, "3/5", Strict), Err(ParseErrorKind::UnexpectedEnd));
+        assert_eq!(parse(&short, "3/5/2024", Strict), Err(ParseErrorKind::UnexpectedCharacter('2')));
+        assert_eq!(parse(&short, "", Strict), Err(ParseErrorKind::Empty));
+
+        let full = formatter("en-US", Some(DateStyle::Full), None);
+        assert_eq!(parse(&full, "Tuesday, March 5, 2024", Strict), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&full, "Monday, March 5, 2024", Strict), Err(ParseErrorKind::InvalidDate));
+        assert_eq!(parse(&full, "Tuesday, Mar 5, 2024", Strict), Err(ParseErrorKind::UnknownName("Mar".to_owned())));
+        assert_eq!(parse(&full, "Tuesday, Foo 5, 2024", Strict), Err(ParseErrorKind::UnknownName("Foo".to_owned())));
+
+        let date_time = formatter("en-US", Some(DateStyle::Short), Some(DateStyle::Short));
+        assert_eq!(parse(&date_time, "3/5/24, 1:30 PM", Strict), Ok((2024, 3, 5, 13, 30)));
+        assert_eq!(parse(&date_time, "3/5/24, 12:05 AM", Strict), Ok((2024, 3, 5, 0, 5)));
+        assert_eq!(parse(&date_time, "3/5/24, 13:30 PM", Strict), Err(ParseErrorKind::InvalidDate));
+        assert_eq!(parse(&formatter("de-DE", Some(DateStyle::Medium), None), "05.03.2024", Strict), Ok((2024, 3, 5, 0, 0)));
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let full = formatter("en-US", Some(DateStyle::Full), None);
+        // the weekday is not checked and month names may be abbreviated
+        assert_eq!(parse(&full, "monday march 5 2024", Lenient), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&full, " Tue., Mar. 5, 2024 ", Lenient), Ok((2024, 3, 5, 0, 0)));
+
+        let medium = formatter("en-US", Some(DateStyle::Medium), None);
+        assert_eq!(parse(&medium, "March 5 2024", Lenient), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&medium, "Mar 5 24", Lenient), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&formatter("en-US", Some(DateStyle::Short), None), "March 5 24", Lenient), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&medium, "Foo 5, 2024", Lenient), Err(ParseErrorKind::UnknownName("Foo".to_owned())));
+
+        // input may end before the time
+        let date_time = formatter("en-US", Some(DateStyle::Short), Some(DateStyle::Short));
+        assert_eq!(parse(&date_time, "3/5/24", Lenient), Ok((2024, 3, 5, 0, 0)));
+        assert_eq!(parse(&date_time, "3/5/24 1:30 pm", Lenient), Ok((2024, 3, 5, 13, 30)));
+        assert_eq!(parse(&formatter("de-DE", Some(DateStyle::Medium), None), "5.3.2024", Lenient), Ok((2024, 3, 5, 0, 0)));
+    }
+
+    #[test]
+    fn parse_calendar() {
+        let japanese = DateFormatter::with_pattern(&Language::parse("en-US-u-ca-japanese").unwrap(), "G y-MM-dd");
+        assert_eq!(parse(&japanese, "Heisei 31-04-30", Strict), Ok((2019, 4, 30, 0, 0)));
+        assert_eq!(parse(&japanese, "Reiwa 1-05-01", Strict), Ok((2019, 5, 1, 0, 0)));
+        assert_eq!(parse(&japanese, "Reiwa 1-04-30", Strict), Err(ParseErrorKind::InvalidDate));
+
+        let buddhist = DateFormatter::with_pattern(&Language::parse("en-US-u-ca-buddhist").unwrap(), "d/M/y");
+        assert_eq!(parse(&buddhist, "5/3/2567", Strict), Ok((2024, 3, 5, 0, 0)));
+    }
+}
@@ -0,0 +1,261 @@
+use std::{fmt::{Debug, Display}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use super::{TimeZone, TimeZoneOffset};
+use super::time_zone::format_offset;
+
+/// Day of the week.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Weekday {
+    Sunday,
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+}
+
+impl Weekday {
+    /// Zero-based index of the day, starting on Sunday.
+    pub fn index_from_sunday(&self) -> usize {
+        *self as usize
+    }
+
+    fn from_index(i: i64) -> Weekday {
+        match i.rem_euclid(7) {
+            0 => Weekday::Sunday,
+            1 => Weekday::Monday,
+            2 => Weekday::Tuesday,
+            3 => Weekday::Wednesday,
+            4 => Weekday::Thursday,
+            5 => Weekday::Friday,
+            _ => Weekday::Saturday,
+        }
+    }
+}
+
+/// How to resolve a local time that is skipped or repeated by a time zone transition.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Disambiguation {
+    /// Takes the earlier instant of a repeated time and moves a skipped time
+    /// forward by the length of the gap, as most operating systems do.
+    Compatible,
+    /// Takes the earlier instant.
+    Earlier,
+    /// Takes the later instant.
+    Later,
+    /// Fails for skipped and repeated times.
+    Reject,
+}
+
+/// An instant with its Gregorian date and wall-clock time in a time zone.
+#[derive(Clone)]
+pub struct ZonedDateTime {
+    m_timestamp: i64,
+    m_nanosecond: u32,
+    m_time_zone: TimeZone,
+    m_offset: TimeZoneOffset,
+    m_year: i64,
+    m_month: u32,
+    m_day: u32,
+    m_hour: u32,
+    m_minute: u32,
+    m_second: u32,
+}
+
+impl ZonedDateTime {
+    /// Current time in a time zone.
+    pub fn now(time_zone: &TimeZone) -> Self {
+        ZonedDateTime::from_system_time(SystemTime::now(), time_zone)
+    }
+
+    pub fn from_system_time(time: SystemTime, time_zone: &TimeZone) -> Self {
+        let (timestamp, nanosecond) = timestamp_from_system_time(time);
+        ZonedDateTime::from_timestamp(timestamp, nanosecond, time_zone)
+    }
+
+    /// Constructs a `ZonedDateTime` from seconds and nanoseconds since the Unix epoch.
+    pub fn from_timestamp(timestamp: i64, nanosecond: u32, time_zone: &TimeZone) -> Self {
+        let offset = time_zone.offset_at_timestamp(timestamp);
+        let local = timestamp + offset.utc_offset as i64;
+        let (year, month, day) = civil_from_days(local.div_euclid(86400));
+        let seconds = local.rem_euclid(86400) as u32;
+        Self {
+            m_timestamp: timestamp,
+            m_nanosecond: nanosecond,
+            m_time_zone: time_zone.clone(),
+            m_offset: offset,
+            m_year: year,
+            m_month: month,
+            m_day: day,
+            m_hour: seconds / 3600,
+            m_minute: seconds / 60 % 60,
+            m_second: seconds % 60,
+        }
+    }
+
+    /// Constructs a `ZonedDateTime` from a Gregorian date and wall-clock time in a time zone.
+    /// Returns `None` if the date or time is invalid, or if the local time is skipped or
+    /// repeated by a transition and `disambiguation` is `Disambiguation::Reject`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_local(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32, time_zone: &TimeZone, disambiguation: Disambiguation) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let local = days_from_civil(year, month, day) * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+        let timestamps = time_zone.timestamps_for_local(local);
+        let timestamp = match (timestamps.len(), disambiguation) {
+            (1, _) => timestamps[0],
+            (0, Disambiguation::Reject) | (2, Disambiguation::Reject) => return None,
+            // skipped: interpret with the offset before or after the transition
+            (0, Disambiguation::Earlier) => local - time_zone.offset_at_timestamp(local + 86400).utc_offset as i64,
+            (0, _) => local - time_zone.offset_at_timestamp(local - 86400).utc_offset as i64,
+            (_, Disambiguation::Later) => *timestamps.last().unwrap(),
+            (_, _) => timestamps[0],
+        };
+        Some(ZonedDateTime::from_timestamp(timestamp, 0, time_zone))
+    }
+
+    pub fn to_system_time(&self) -> SystemTime {
+        if self.m_timestamp >= 0 {
+            UNIX_EPOCH + Duration::new(self.m_timestamp as u64, self.m_nanosecond)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(self.m_timestamp.unsigned_abs()) + Duration::from_nanos(self.m_nanosecond as u64)
+        }
+    }
+
+    /// Same instant in another time zone.
+    pub fn with_time_zone(&self, time_zone: &TimeZone) -> Self {
+        ZonedDateTime::from_timestamp(self.m_timestamp, self.m_nanosecond, time_zone)
+    }
+
+    /// Adds calendar days keeping the wall-clock time, which may be adjusted if it
+    /// is skipped on the resulting day.
+    pub fn add_days(&self, days: i64) -> Self {
+        let (year, month, day) = civil_from_days(days_from_civil(self.m_year, self.m_month, self.m_day) + days);
+        let mut r = ZonedDateTime::from_local(year, month, day, self.m_hour, self.m_minute, self.m_second, &self.m_time_zone, Disambiguation::Compatible).unwrap();
+        r.m_nanosecond = self.m_nanosecond;
+        r
+    }
+
+    /// First instant of the local day, which is not midnight in time zones
+    /// that skip midnight.
+    pub fn start_of_day(&self) -> Self {
+        ZonedDateTime::from_local(self.m_year, self.m_month, self.m_day, 0, 0, 0, &self.m_time_zone, Disambiguation::Compatible).unwrap()
+    }
+
+    /// Seconds since the Unix epoch.
+    pub fn timestamp(&self) -> i64 {
+        self.m_timestamp
+    }
+
+    pub fn time_zone(&self) -> TimeZone {
+        self.m_time_zone.clone()
+    }
+
+    /// Local time type in effect, with the UTC offset.
+    pub fn offset(&self) -> TimeZoneOffset {
+        self.m_offset.clone()
+    }
+
+    pub fn year(&self) -> i64 {
+        self.m_year
+    }
+
+    /// Month from 1 to 12.
+    pub fn month(&self) -> u32 {
+        self.m_month
+    }
+
+    /// Day of the month from 1.
+    pub fn day(&self) -> u32 {
+        self.m_day
+    }
+
+    /// Day of the year from 1.
+    pub fn day_of_year(&self) -> u32 {
+        (days_from_civil(self.m_year, self.m_month, self.m_day) - days_from_civil(self.m_year, 1, 1) + 1) as u32
+    }
+
+    pub fn weekday(&self) -> Weekday {
+        Weekday::from_index(days_from_civil(self.m_year, self.m_month, self.m_day) + 4)
+    }
+
+    pub fn hour(&self) -> u32 {
+        self.m_hour
+    }
+
+    pub fn minute(&self) -> u32 {
+        self.m_minute
+    }
+
+    pub fn second(&self) -> u32 {
+        self.m_second
+    }
+
+    pub fn nanosecond(&self) -> u32 {
+        self.m_nanosecond
+    }
+}
+
+impl PartialEq for ZonedDateTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.m_timestamp == other.m_timestamp && self.m_nanosecond == other.m_nanosecond && self.m_time_zone == other.m_time_zone
+    }
+}
+
+impl Display for ZonedDateTime {
+    /// Formats as RFC 9557, such as `2024-03-10T03:30:00-07:00[America/Los_Angeles]`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}[{}]", self.m_year, self.m_month, self.m_day, self.m_hour, self.m_minute, self.m_second, format_offset(self.m_offset.utc_offset), self.m_time_zone.id())
+    }
+}
+
+impl Debug for ZonedDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// Seconds since the Unix epoch, rounded down, and nanoseconds.
+pub(crate) fn timestamp_from_system_time(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            if d.subsec_nanos() == 0 { (-(d.as_secs() as i64), 0) } else { (-(d.as_secs() as i64) - 1, 1_000_000_000 - d.subsec_nanos()) }
+        },
+    }
+}
+
+/// Days since the Unix epoch of a proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Proleptic Gregorian date of a day since the Unix epoch.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = if days >= 0 { days } else { days - 146096 } / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 } as u32;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 => if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
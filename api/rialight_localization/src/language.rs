@@ -3,7 +3,7 @@ use std::{sync::Arc, fmt::Debug};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use rialight_util::AnyStringType;
-use super::Region;
+use super::{Region, Calendar};
 
 lazy_static! {
     static ref LANGUAGE_DATA: HashMap<String, LanguageData> = {
@@ -11,6 +11,14 @@ lazy_static! {
     };
 }
 
+lazy_static! {
+    /// Parent locales that differ from the language alone, with `"root"`
+    /// for locales that do not inherit from their language.
+    static ref PARENT_DATA: HashMap<String, String> = {
+        serde_json::from_str::<HashMap<String, String>>(include_str!("../data/parent.json")).unwrap()
+    };
+}
+
 lazy_static! {
     static ref INTERNED_LANGUAGES: HashMap<String, Language> = {
         let mut r = HashMap::<String, Language>::new();
@@ -19,6 +27,7 @@ lazy_static! {
                 m_abbrev: k.clone(),
                 m_data: Arc::new(v.clone()),
                 m_region: None,
+                m_calendar: None,
             });
         }
         r
@@ -36,20 +45,28 @@ struct LanguageData {
 }
 
 /// Represents a language code.
+///
+/// A language tag may carry a calendar through the Unicode extension,
+/// as in `ja-JP-u-ca-japanese`. The calendar does not take part in comparisons.
+///
+/// A script subtag is implied by the region and dropped, so that `zh-Hant-HK`
+/// is `zh-HK`, while `zh-Hant` alone is `zh-TW`.
 #[derive(Clone)]
 pub struct Language {
     m_abbrev: String,
     m_data: Arc<LanguageData>,
     m_region: Option<Region>,
+    m_calendar: Option<Calendar>,
 }
 
 impl Language {
     fn parse_tag(tag: impl AnyStringType) -> Option<(String, String)> {
-        let tag = tag.convert().to_lowercase().replace("_", "-");
-        let tag_split: Vec<&str> = tag.split("-").collect();
+        let tag = tag.convert().to_ascii_lowercase().replace("_", "-");
+        let mut tag_split: Vec<&str> = tag.split("-").collect();
         if tag_split.len() == 0 {
             return None;
         }
+        let script = if tag_split.len() > 1 && tag_split[1].len() == 4 { Some(tag_split.remove(1)) } else { None };
         let mut language_abbrev = tag_split[0];
         let mut region_abbrev = if tag_split.len() == 2 { Some(String::from(tag_split[1])) } else { None };
         if region_abbrev.is_none() {
@@ -60,6 +77,7 @@ impl Language {
             if language_abbrev == "jp" { language_abbrev = "es"; region_abbrev = Some(String::from("es")); }
             if language_abbrev == "uk" { language_abbrev = "en"; region_abbrev = Some(String::from("gb")); }
             if language_abbrev == "fi" { language_abbrev = "fi"; region_abbrev = Some(String::from("fi")); }
+            if language_abbrev == "zh" { region_abbrev = Some(String::from(if script == Some("hant") { "tw" } else { "cn" })); }
         }
         if region_abbrev.is_none() {
             let r = Region::parse(language_abbrev);
@@ -73,7 +91,27 @@ impl Language {
         Some((String::from(language_abbrev), region_abbrev.unwrap()))
     }
 
+    /// Splits the Unicode extension (`-u-`) from a tag and returns its calendar keyword (`ca`).
+    fn parse_unicode_extension(tag: &str) -> (String, Option<Calendar>) {
+        let tag = tag.to_ascii_lowercase().replace("_", "-");
+        let Some(i) = tag.find("-u-") else { return (tag, None) };
+        let subtags: Vec<&str> = tag[i + 3..].split("-").collect();
+        let mut calendar: Option<Calendar> = None;
+        let mut k = 0;
+        while k < subtags.len() && subtags[k].len() != 1 {
+            if subtags[k] == "ca" {
+                // calendar identifiers may span several subtags, as in islamic-umalqura
+                let value: Vec<&str> = subtags[k + 1..].iter().take_while(|s| s.len() > 2).cloned().collect();
+                calendar = Calendar::from_id(&value.join("-"));
+                k += value.len();
+            }
+            k += 1;
+        }
+        (tag[..i].to_owned(), calendar)
+    }
+
     pub fn parse(tag: impl AnyStringType) -> Option<Language> {
+        let (tag, calendar) = Language::parse_unicode_extension(tag.convert());
         let tag = Language::parse_tag(tag);
         if tag.is_none() {
             return None;
@@ -88,6 +126,7 @@ impl Language {
             m_abbrev: tag.0.clone(),
             m_data: lng.unwrap().m_data.clone(),
             m_region: region.clone(),
+            m_calendar: calendar,
         })
     }
 
@@ -106,6 +145,45 @@ impl Language {
     pub fn region(&self) -> Region {
         self.m_region.as_ref().unwrap().clone()
     }
+    /// Calendar given by the `-u-ca-` extension of the tag, or the calendar customarily
+    /// used in the region: Buddhist in Thailand, Persian in Iran and Afghanistan,
+    /// Umm al-Qura in Saudi Arabia and Gregorian elsewhere.
+    pub fn calendar(&self) -> Calendar {
+        if let Some(c) = self.m_calendar {
+            return c;
+        }
+        match self.m_region.as_ref().unwrap().id().as_ref() {
+            "th" => Calendar::Buddhist,
+            "ir" | "af" => Calendar::Persian,
+            "sa" => Calendar::IslamicUmmAlQura,
+            _ => Calendar::Gregorian,
+        }
+    }
+
+    /// Returns the same language with a different calendar.
+    pub fn with_calendar(&self, calendar: Calendar) -> Language {
+        let mut r = self.clone();
+        r.m_calendar = Some(calendar);
+        r
+    }
+
+    /// Parent locale in the CLDR inheritance chain, such as `pt-PT` for `pt-AO`
+    /// and `pt` for `pt-PT`, or `None` for a locale that is its language's default.
+    /// Region groups are represented by one of their regions: `en-001` by `en-GB`,
+    /// `es-419` by `es-MX` and `zh-Hant` by `zh-TW`.
+    pub fn parent(&self) -> Option<Language> {
+        let parent = match PARENT_DATA.get(&self.tag()) {
+            Some(tag) if tag == "root" => return None,
+            Some(tag) => Language::parse(tag.clone()),
+            None => Language::parse(self.m_abbrev.clone()),
+        };
+        parent.filter(|p| p != self)
+    }
+
+    /// Language code without the region, such as `"pt"`.
+    pub(crate) fn language_code(&self) -> String {
+        self.m_abbrev.clone()
+    }
 }
 
 impl PartialEq for Language {
@@ -139,4 +217,28 @@ impl Debug for Language {
 pub enum Direction {
     Ltr,
     Rtl,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(tag: &str) -> Vec<String> {
+        let mut r = vec![];
+        let mut locale = Language::parse(tag);
+        while let Some(l) = locale {
+            r.push(l.tag());
+            locale = l.parent();
+        }
+        r
+    }
+
+    #[test]
+    fn parent() {
+        assert_eq!(chain("pt-AO"), ["pt-AO", "pt-PT", "pt-BR"]);
+        assert_eq!(chain("en-AU"), ["en-AU", "en-GB", "en-US"]);
+        assert_eq!(chain("es-AR"), ["es-AR", "es-MX", "es-ES"]);
+        assert_eq!(chain("zh-MO"), ["zh-MO", "zh-HK", "zh-TW"]);
+        assert_eq!(chain("fr-CA"), ["fr-CA", "fr-FR"]);
+    }
 }
\ No newline at end of file
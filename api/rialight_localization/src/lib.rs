@@ -5,7 +5,69 @@ mod language;
 pub use language::{Language, Direction};
 
 mod region;
-pub use region::Region;
+pub use region::{Region, MeasurementSystem};
+
+mod segmentation;
+pub use segmentation::{
+    Segmenter, Segment, LineBreakOpportunity,
+    WordBreakTailoring, DictionaryWordBreaker,
+    register_word_break_tailoring,
+};
+
+mod normalization;
+pub use normalization::{NormalizationForm, normalize, is_normalized};
+
+mod case_mapping;
+pub use case_mapping::CaseMapper;
+
+mod plural;
+pub use plural::{PluralCategory, PluralRules};
+
+mod parsing;
+pub use parsing::{ParseMode, ParseError, ParseErrorKind};
+
+mod currency;
+pub use currency::CurrencyAmount;
+
+mod number_format;
+pub use number_format::{NumberFormatter, NumberFormatOptions};
+
+mod spellout;
+pub use spellout::{
+    SpelloutFormatter, SpelloutFormatOptions,
+    SpelloutStyle, GrammaticalGender,
+};
+
+mod list_format;
+pub use list_format::{ListFormatter, ListFormatOptions, ListStyle};
+
+mod measure;
+pub use measure::{
+    MeasureFormatter, MeasureFormatOptions,
+    MeasureUnit, MeasureQuantity, UnitWidth,
+};
+
+mod time_zone;
+pub use time_zone::{TimeZone, TimeZoneOffset};
+
+mod date_time;
+pub use date_time::{ZonedDateTime, Weekday, Disambiguation};
+
+mod calendar;
+pub use calendar::{Calendar, CalendarDate};
+
+mod date_format;
+pub use date_format::{
+    DateFormatter, DateFormatOptions,
+    DateStyle, HourCycle, TimeZoneNameStyle,
+};
+
+mod message;
+mod rich_text;
+pub use rich_text::RichSpan;
+
+mod catalog;
+pub use catalog::{LocaleCatalog, LocaleCatalogError};
 
 mod locale_bundle;
 pub use locale_bundle::*;
\ No newline at end of file
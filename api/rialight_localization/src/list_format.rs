@@ -0,0 +1,118 @@
+use std::{cell::Cell, collections::HashMap};
+use serde::{Deserialize, Serialize};
+use super::Language;
+
+lazy_static! {
+    static ref LIST_DATA: HashMap<String, ListData> = {
+        serde_json::from_str::<HashMap<String, ListData>>(include_str!("../data/list.json")).unwrap()
+    };
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ListData {
+    /// Conjunction patterns
+    a: ListPatterns,
+    /// Disjunction patterns
+    o: ListPatterns,
+    /// Unit patterns
+    u: ListPatterns,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ListPatterns {
+    /// Pattern joining all items but the last two, such as `"{0}, {1}"`
+    m: String,
+    /// Pattern joining the last two items, such as `"{0}, and {1}"`
+    e: String,
+    /// Pattern joining a list of two items, if different from the end pattern
+    t: Option<String>,
+}
+
+/// Kind of a formatted list.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ListStyle {
+    /// Such as "a, b, and c".
+    And,
+    /// Such as "a, b, or c".
+    Or,
+    /// A list of measures, such as "1 hr, 5 min".
+    Unit,
+}
+
+/// Joins items into a list as written in a language.
+///
+/// List patterns are available for English, Portuguese, Spanish, French,
+/// German, Italian, Dutch, Russian, Japanese and Chinese; other languages use English.
+#[derive(Clone)]
+pub struct ListFormatter {
+    m_patterns: ListPatterns,
+}
+
+impl ListFormatter {
+    pub fn new(language: &Language, options: &ListFormatOptions) -> Self {
+        let data = LIST_DATA.get(&language.tag().to_ascii_lowercase())
+            .or_else(|| LIST_DATA.get(&language.language_code()))
+            .unwrap_or_else(|| LIST_DATA.get("en").unwrap());
+        Self {
+            m_patterns: match options.m_style.get() {
+                ListStyle::And => data.a.clone(),
+                ListStyle::Or => data.o.clone(),
+                ListStyle::Unit => data.u.clone(),
+            },
+        }
+    }
+
+    /// Joins items, such as `["a", "b", "c"]` into "a, b, and c".
+    pub fn format(&self, items: &[String]) -> String {
+        match items.len() {
+            0 => String::new(),
+            1 => items[0].clone(),
+            2 => apply_pattern(self.m_patterns.t.as_ref().unwrap_or(&self.m_patterns.e), &items[0], &items[1]),
+            n => {
+                let mut r = apply_pattern(&self.m_patterns.e, &items[n - 2], &items[n - 1]);
+                for item in items[..n - 2].iter().rev() {
+                    r = apply_pattern(&self.m_patterns.m, item, &r);
+                }
+                r
+            },
+        }
+    }
+}
+
+/// Substitutes `{0}` and `{1}` in a list pattern.
+fn apply_pattern(pattern: &str, first: &str, second: &str) -> String {
+    let (before, rest) = pattern.split_once("{0}").unwrap();
+    let (middle, after) = rest.split_once("{1}").unwrap();
+    format!("{}{}{}{}{}", before, first, middle, second, after)
+}
+
+pub struct ListFormatOptions {
+    m_style: Cell<ListStyle>,
+}
+
+impl Clone for ListFormatOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_style: self.m_style.clone(),
+        }
+    }
+}
+
+impl ListFormatOptions {
+    pub fn new() -> Self {
+        ListFormatOptions {
+            m_style: Cell::new(ListStyle::And),
+        }
+    }
+
+    pub fn style(&self, value: ListStyle) -> &Self {
+        self.m_style.set(value);
+        self
+    }
+}
+
+impl Default for ListFormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
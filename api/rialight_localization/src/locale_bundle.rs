@@ -1,8 +1,11 @@
-use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet}, sync::Arc};
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet}, sync::{Arc, RwLock}, time::Duration};
 use maplit::{hashmap, hashset};
-use super::{Language};
-use lazy_static::lazy_static;
-use lazy_regex::regex;
+use super::{Language, LocaleCatalog, RichSpan, SpelloutFormatter, SpelloutFormatOptions, SpelloutStyle, GrammaticalGender};
+use super::{NumberFormatter, NumberFormatOptions, CurrencyAmount, DateFormatter, DateFormatOptions, DateStyle, ZonedDateTime};
+use super::{MeasureFormatter, MeasureFormatOptions, UnitWidth, ListFormatter, ListFormatOptions, ListStyle};
+use super::currency::currency_for_region;
+use super::message::{MessagePart, parse_message, reference_candidates, check_markup, unknown_tags_as_text};
+use super::rich_text::RichTextBuilder;
 use rialight_util::AnyStringType;
 
 /// Creates a `HashMap<String, String>` from a list of key-value pairs.
@@ -39,6 +42,36 @@ macro_rules! bundle_vars {
     };
 }
 
+/// Creates a `HashMap<String, MessageArgument>` from a list of key-value pairs,
+/// converting each value with `MessageArgument::from`.
+///
+/// ## Example
+///
+/// ```
+/// use rialight_localization::{bundle_args, CurrencyAmount, MessageArgument};
+/// fn main() {
+///     let map = bundle_args!{
+///         "name" => "Jane",
+///         "count" => 3,
+///         "price" => CurrencyAmount { amount: 9.5, currency: "EUR".to_owned() },
+///     };
+///     assert!(matches!(map[&"count".to_owned()], MessageArgument::Number(n) if n == 3.0));
+/// }
+/// ```
+#[macro_export]
+macro_rules! bundle_args {
+    ($($key:expr => $value:expr,)+) => { bundle_args!($($key => $value),+) };
+    ($($key:expr => $value:expr),*) => {
+        {
+            let mut r_map = ::std::collections::HashMap::<String, $crate::MessageArgument>::new();
+            $(
+                let _ = r_map.insert($key.to_string(), $crate::MessageArgument::from($value));
+            )*
+            r_map
+        }
+    };
+}
+
 /// Loads and stores locale-based messages.
 pub struct LocaleBundle {
     m_current_locale: Option<Language>,
@@ -46,15 +79,105 @@ pub struct LocaleBundle {
     m_supported_locales: Arc<HashSet<Language>>,
     m_default_locale: Language,
     m_fallbacks: Arc<HashMap<Language, Vec<Language>>>,
-    m_assets: Arc<HashMap<Language, serde_json::Value>>,
+    m_assets: Arc<HashMap<Language, LocaleResources>>,
     m_assets_src: String,
     m_assets_base_file_names: Vec<String>,
+    m_assets_lazy_file_names: Vec<String>,
     m_assets_clean_unused: bool,
     m_assets_load_method: LocaleBundleLoadMethod,
+    m_assets_format: LocaleBundleAssetFormat,
+    m_namespaces: RwLock<LocaleBundleNamespaces>,
+    m_markup_tags: Vec<String>,
+}
+
+/// Messages of lazily loaded namespaces.
+#[derive(Default, Clone)]
+struct LocaleBundleNamespaces {
+    m_roots: HashMap<Language, LocaleResources>,
+    m_loaded: HashSet<(Language, String)>,
+    // namespaces that failed to load on first access,
+    // which are only loaded again through `ensure_namespace`
+    m_failed: HashSet<(Language, String)>,
+}
+
+/// A loaded resource file.
+#[derive(Clone)]
+enum LocaleResource {
+    Json(serde_json::Value),
+    Catalog(Arc<LocaleCatalog>),
+}
+
+/// Messages loaded for a locale. JSON resources are merged into a single tree,
+/// while catalogs are kept apart together with their identifier prefix.
+#[derive(Clone)]
+struct LocaleResources {
+    m_root: serde_json::Value,
+    m_catalogs: Vec<(Vec<String>, Arc<LocaleCatalog>)>,
+}
+
+impl LocaleResources {
+    fn new() -> Self {
+        Self {
+            m_root: serde_json::Value::Object(serde_json::Map::new()),
+            m_catalogs: vec![],
+        }
+    }
+
+    fn insert(&mut self, name: &String, resource: LocaleResource) {
+        match resource {
+            LocaleResource::Json(root) => LocaleBundle::apply_deep(name, root, &mut self.m_root),
+            LocaleResource::Catalog(catalog) => {
+                self.m_catalogs.retain(|(prefix, _)| prefix.join("/") != *name);
+                self.m_catalogs.push((name.split("/").map(|s| s.to_string()).collect(), catalog));
+            },
+        }
+    }
+
+    fn remove(&mut self, name: &String) {
+        self.m_catalogs.retain(|(prefix, _)| prefix.join("/") != *name);
+        let mut path: Vec<&str> = name.split("/").collect();
+        let last_name = path.pop().unwrap();
+        let mut output = Some(&mut self.m_root);
+        for frag in path.iter() {
+            output = output.and_then(|o| o.get_mut(*frag));
+        }
+        if let Some(output) = output.and_then(|o| o.as_object_mut()) {
+            output.remove(last_name);
+        }
+    }
+
+    fn lookup(&self, id: &[String]) -> Option<Vec<MessagePart>> {
+        if let Some(message) = LocaleBundle::resolve_id(Some(&self.m_root), id) {
+            return Some(parse_message(&message));
+        }
+        for (prefix, catalog) in self.m_catalogs.iter() {
+            if prefix.len() < id.len() && prefix.iter().zip(id.iter()).all(|(a, b)| a == b) {
+                if let Some(parts) = catalog.get_parts(&id[prefix.len()..].join(".")) {
+                    return Some(parts);
+                }
+            }
+        }
+        None
+    }
+
+    fn message_ids(&self) -> Vec<String> {
+        let mut r: Vec<String> = vec![];
+        LocaleBundle::enumerate_message_ids(&self.m_root, &mut vec![], &mut |id| r.push(id));
+        for (prefix, catalog) in self.m_catalogs.iter() {
+            for id in catalog.ids() {
+                r.push(prefix.join(".") + "." + &id);
+            }
+        }
+        r
+    }
 }
 
 impl LocaleBundle {
     /// Constructs a `LocaleBundle` object.
+    ///
+    /// A supported locale without explicit fallbacks falls back to its nearest
+    /// supported ancestor (`Language::parent`), or else to the default locale.
+    /// Panics if the fallbacks form a cycle.
     pub fn new(options: &LocaleBundleOptions) -> Self {
         let mut locale_path_components = HashMap::<Language, String>::new();
         let mut supported_locales = HashSet::<Language>::new();
@@ -67,18 +190,41 @@ impl LocaleBundle {
         for (k, v) in options.m_fallbacks.borrow().iter() {
             fallbacks.insert(Language::parse(k.clone()).unwrap(), v.iter().map(|s| Language::parse(s.clone()).unwrap()).collect());
         }
-        let default_locale = options.m_default_locale.borrow().clone();
+        let default_locale = Language::parse(options.m_default_locale.borrow().clone()).unwrap();
+        for locale in supported_locales.iter() {
+            if fallbacks.contains_key(locale) {
+                continue;
+            }
+            // the nearest supported ancestor, which in turn falls back to its own
+            let mut parent = locale.parent();
+            while let Some(p) = parent.clone() {
+                if supported_locales.contains(&p) {
+                    break;
+                }
+                parent = p.parent();
+            }
+            if let Some(p) = parent.or(if *locale != default_locale { Some(default_locale.clone()) } else { None }) {
+                fallbacks.insert(locale.clone(), vec![p]);
+            }
+        }
+        if let Some(cycle) = LocaleBundle::find_fallback_cycle(&fallbacks) {
+            panic!("Cyclic locale fallbacks: {}.", cycle.iter().map(|l| l.tag()).collect::<Vec<String>>().join(" -> "));
+        }
         Self {
             m_current_locale: None,
             m_locale_path_components: Arc::new(locale_path_components),
             m_supported_locales: Arc::new(supported_locales),
-            m_default_locale: Language::parse(default_locale).unwrap(),
+            m_default_locale: default_locale,
             m_fallbacks: Arc::new(fallbacks),
             m_assets: Arc::new(HashMap::new()),
             m_assets_src: options.m_assets.borrow().m_src.borrow().clone(),
-            m_assets_base_file_names: options.m_assets.borrow().m_base_file_names.borrow().iter().map(|s| s.clone()).collect(),
+            m_assets_base_file_names: options.m_assets.borrow().m_base_file_names.borrow().iter().cloned().collect(),
+            m_assets_lazy_file_names: options.m_assets.borrow().m_lazy_file_names.borrow().clone(),
             m_assets_clean_unused: options.m_assets.borrow().m_clean_unused.get(),
             m_assets_load_method: options.m_assets.borrow().m_load_method.get(),
+            m_assets_format: options.m_assets.borrow().m_format.get(),
+            m_namespaces: RwLock::new(LocaleBundleNamespaces::default()),
+            m_markup_tags: options.m_markup_tags.borrow().clone(),
         }
     }
 
@@ -131,7 +277,7 @@ impl LocaleBundle {
         let mut to_load: HashSet<Language> = hashset![new_locale.clone()];
         self.enumerate_fallbacks(new_locale.clone(), &mut to_load);
 
-        let mut new_assets: HashMap<Language, serde_json::Value> = hashmap![];
+        let mut new_assets: HashMap<Language, LocaleResources> = hashmap![];
         for locale in to_load {
             let res = self.load_single_locale(&locale).await;
             if res.is_none() {
@@ -139,58 +285,239 @@ impl LocaleBundle {
             }
             new_assets.insert(locale.clone(), res.unwrap());
         }
-        if self.m_assets_clean_unused {
-            Arc::get_mut(&mut self.m_assets).unwrap().clear();
+        let mut combined_assets: HashMap<Language, LocaleResources> = if self.m_assets_clean_unused { hashmap![] } else { self.m_assets.as_ref().clone() };
+        combined_assets.extend(new_assets);
+        if let Some(cycle) = self.find_reference_cycle(&combined_assets) {
+            println!("Cyclic message reference: {}.", cycle.join(" -> "));
+            return false;
+        }
+        for resources in combined_assets.values() {
+            if let Some((id, problem)) = self.find_markup_error(resources) {
+                println!("Invalid markup in message {}: {}.", id, problem);
+                return false;
+            }
         }
 
-        for (locale, root) in new_assets {
-            Arc::get_mut(&mut self.m_assets).unwrap().insert(locale, root);
+        let mut active_namespaces: HashSet<String> = hashset![];
+        if let Some(previous_locale) = self.m_current_locale.clone() {
+            let namespaces = self.m_namespaces.read().unwrap();
+            for (locale, name) in namespaces.m_loaded.iter() {
+                if *locale == previous_locale {
+                    active_namespaces.insert(name.clone());
+                }
+            }
         }
+
+        self.m_assets = Arc::new(combined_assets);
         self.m_current_locale = Some(new_locale.clone());
+
+        if self.m_assets_clean_unused {
+            let locales = self.current_locale_seq();
+            let mut namespaces = self.m_namespaces.write().unwrap();
+            namespaces.m_roots.retain(|locale, _| locales.contains(locale));
+            namespaces.m_loaded.retain(|(locale, _)| locales.contains(locale));
+            namespaces.m_failed.retain(|(locale, _)| locales.contains(locale));
+        }
+        for name in active_namespaces {
+            if !self.ensure_namespace(name).await {
+                return false;
+            }
+        }
         // let new_locale_code = unic_langid::LanguageIdentifier::from_bytes(new_locale.clone().standard_tag().to_string().as_ref()).unwrap();
 
         true
     }
 
-    async fn load_single_locale(&self, locale: &Language) -> Option<serde_json::Value> {
-        let mut r = serde_json::Value::Object(serde_json::Map::new());
+    async fn load_single_locale(&self, locale: &Language) -> Option<LocaleResources> {
+        let mut r = LocaleResources::new();
+        for base_name in self.m_assets_base_file_names.iter() {
+            r.insert(base_name, self.load_resource(locale, base_name).await?);
+        }
+        Some(r)
+    }
+
+    fn resource_path(&self, locale: &Language, base_name: &String) -> String {
+        let locale_path_comp = self.m_locale_path_components.get(locale);
+        if locale_path_comp.is_none() {
+            panic!("Fallback locale is not supported a locale: {}", locale.tag());
+        }
+        let extension = match self.m_assets_format {
+            LocaleBundleAssetFormat::Json => "json",
+            LocaleBundleAssetFormat::Catalog => "rlcat",
+        };
+        format!("{}/{}/{}.{}", self.m_assets_src, locale_path_comp.unwrap(), base_name, extension)
+    }
+
+    async fn load_resource(&self, locale: &Language, base_name: &String) -> Option<LocaleResource> {
+        let res_path = self.resource_path(locale, base_name);
         match self.m_assets_load_method {
-            LocaleBundleLoadMethod::FileSystem => {
-                for base_name in self.m_assets_base_file_names.iter() {
-                    let locale_path_comp = self.m_locale_path_components.get(locale);
-                    if locale_path_comp.is_none() {
-                        panic!("Fallback locale is not supported a locale: {}", locale.tag());
-                    }
-                    let res_path = format!("{}/{}/{}.json", self.m_assets_src, locale_path_comp.unwrap(), base_name);
-                    let content = rialight_filesystem::File::new(res_path.clone()).read_bytes();
-                    if content.is_err() {
-                        println!("Failed to load resource at {}.", res_path);
-                        return None;
-                    }
-                    LocaleBundle::apply_deep(base_name, serde_json::from_str(String::from_utf8(content.unwrap()).unwrap().as_ref()).unwrap(), &mut r);
+            LocaleBundleLoadMethod::FileSystem => self.load_resource_sync(locale, base_name),
+            LocaleBundleLoadMethod::Http => {
+                let content = reqwest::get(reqwest::Url::parse(res_path.clone().as_ref()).unwrap()).await;
+                if content.is_err() {
+                    println!("Failed to load resource at {}.", res_path);
+                    return None;
+                }
+                match self.m_assets_format {
+                    LocaleBundleAssetFormat::Json => {
+                        let content = content.unwrap().text().await;
+                        Some(LocaleResource::Json(serde_json::from_str(content.unwrap().as_ref()).unwrap()))
+                    },
+                    LocaleBundleAssetFormat::Catalog => {
+                        let content = content.unwrap().bytes().await;
+                        let catalog = content.ok().and_then(|b| LocaleCatalog::from_bytes(b.to_vec()).ok());
+                        if catalog.is_none() {
+                            println!("Failed to load catalog at {}.", res_path);
+                            return None;
+                        }
+                        Some(LocaleResource::Catalog(Arc::new(catalog.unwrap())))
+                    },
                 }
             },
-            LocaleBundleLoadMethod::Http => {
-                for base_name in self.m_assets_base_file_names.iter() {
-                    let locale_path_comp = self.m_locale_path_components.get(locale);
-                    if locale_path_comp.is_none() {
-                        panic!("Fallback locale is not supported a locale: {}", locale.tag());
-                    }
-                    let res_path = format!("{}/{}/{}.json", self.m_assets_src, locale_path_comp.unwrap(), base_name);
-                    let content = reqwest::get(reqwest::Url::parse(res_path.clone().as_ref()).unwrap()).await;
-                    if content.is_err() {
-                        println!("Failed to load resource at {}.", res_path);
-                        return None;
-                    }
-                    let content = if content.is_ok() { Some(content.unwrap().text().await) } else { None };
-                    LocaleBundle::apply_deep(base_name, serde_json::from_str(content.unwrap().unwrap().as_ref()).unwrap(), &mut r);
+        }
+    }
+
+    /// Loads a resource without blocking on the network.
+    /// Only the `FileSystem` load method is supported.
+    fn load_resource_sync(&self, locale: &Language, base_name: &String) -> Option<LocaleResource> {
+        let LocaleBundleLoadMethod::FileSystem = self.m_assets_load_method else {
+            return None;
+        };
+        let res_path = self.resource_path(locale, base_name);
+        match self.m_assets_format {
+            LocaleBundleAssetFormat::Json => {
+                let content = rialight_filesystem::File::new(res_path.clone()).read_bytes();
+                if content.is_err() {
+                    println!("Failed to load resource at {}.", res_path);
+                    return None;
+                }
+                Some(LocaleResource::Json(serde_json::from_str(String::from_utf8(content.unwrap()).unwrap().as_ref()).unwrap()))
+            },
+            LocaleBundleAssetFormat::Catalog => {
+                let catalog = LocaleCatalog::open(&rialight_filesystem::File::new(res_path.clone()));
+                if catalog.is_err() {
+                    println!("Failed to load catalog at {}.", res_path);
+                    return None;
                 }
+                Some(LocaleResource::Catalog(Arc::new(catalog.unwrap())))
             },
         }
-        Some(r)
     }
 
-    fn apply_deep(name: &String, assign: serde_json::Value, mut output: &mut serde_json::Value) {
+    /// Loads a namespace, that is, a resource file that is not one of the
+    /// `base_file_names`, for the current locale and its fallbacks.
+    /// Namespaces that are already loaded are not loaded again.
+    /// For example, `ensure_namespace("quests/act2")` loads `quests/act2.json`, whose
+    /// messages are then referred to with the `quests.act2.` prefix.
+    ///
+    /// If any resource fails to load or no locale is loaded, the method returns `false`, otherwise `true`.
+    pub async fn ensure_namespace(&self, name: impl AnyStringType) -> bool {
+        let name = name.convert().to_owned();
+        if self.m_current_locale.is_none() {
+            return false;
+        }
+        for locale in self.current_locale_seq() {
+            if self.is_namespace_loaded_for(&locale, &name) {
+                continue;
+            }
+            let res = self.load_resource(&locale, &name).await;
+            if res.is_none() || !self.check_namespace_markup(&name, res.as_ref().unwrap()) {
+                return false;
+            }
+            self.insert_namespace(&locale, &name, res.unwrap());
+        }
+        true
+    }
+
+    /// Unloads a namespace previously loaded by `ensure_namespace` or on first access,
+    /// for every locale.
+    pub fn unload_namespace(&self, name: impl AnyStringType) {
+        let name = name.convert().to_owned();
+        let mut namespaces = self.m_namespaces.write().unwrap();
+        namespaces.m_loaded.retain(|(_, n)| *n != name);
+        namespaces.m_failed.retain(|(_, n)| *n != name);
+        for resources in namespaces.m_roots.values_mut() {
+            resources.remove(&name);
+        }
+    }
+
+    /// Returns `true` if the namespace is loaded for the current locale, otherwise `false`.
+    pub fn is_namespace_loaded(&self, name: impl AnyStringType) -> bool {
+        if let Some(locale) = self.current_locale() {
+            return self.is_namespace_loaded_for(&locale, name.convert());
+        }
+        false
+    }
+
+    fn is_namespace_loaded_for(&self, locale: &Language, name: &str) -> bool {
+        self.m_namespaces.read().unwrap().m_loaded.contains(&(locale.clone(), name.to_owned()))
+    }
+
+    /// Checks the markup of a namespace's messages, printing the first problem.
+    fn check_namespace_markup(&self, name: &String, resource: &LocaleResource) -> bool {
+        let mut resources = LocaleResources::new();
+        resources.insert(name, resource.clone());
+        if let Some((id, problem)) = self.find_markup_error(&resources) {
+            println!("Invalid markup in message {}: {}.", id, problem);
+            return false;
+        }
+        true
+    }
+
+    /// Finds a message with unbalanced markup tags,
+    /// returning its identifier and a description of the problem.
+    fn find_markup_error(&self, resources: &LocaleResources) -> Option<(String, String)> {
+        for id in resources.message_ids() {
+            let split: Vec<String> = id.split(".").map(|s| s.to_string()).collect();
+            if let Some(problem) = resources.lookup(&split).and_then(|parts| check_markup(&parts, &self.m_markup_tags)) {
+                return Some((id, problem));
+            }
+        }
+        None
+    }
+
+    fn insert_namespace(&self, locale: &Language, name: &String, resource: LocaleResource) {
+        let mut namespaces = self.m_namespaces.write().unwrap();
+        namespaces.m_roots.entry(locale.clone()).or_insert(LocaleResources::new()).insert(name, resource);
+        namespaces.m_failed.remove(&(locale.clone(), name.clone()));
+        namespaces.m_loaded.insert((locale.clone(), name.clone()));
+    }
+
+    /// Loads, if necessary, the lazy namespace that the message identifier belongs to.
+    /// Namespaces are loaded on first access only with the `FileSystem` load method;
+    /// otherwise they must be loaded through `ensure_namespace`.
+    /// A namespace that fails to load is not loaded again on access.
+    fn load_namespace_on_demand(&self, id: &[String]) {
+        let namespace = self.m_assets_lazy_file_names.iter()
+            .filter(|name| {
+                let split: Vec<&str> = name.split("/").collect();
+                split.len() < id.len() && split.iter().zip(id.iter()).all(|(a, b)| *a == b)
+            })
+            .max_by_key(|name| name.len());
+        if namespace.is_none() {
+            return;
+        }
+        let namespace = namespace.unwrap();
+        for locale in self.current_locale_seq() {
+            let key = (locale.clone(), namespace.clone());
+            {
+                let namespaces = self.m_namespaces.read().unwrap();
+                if namespaces.m_loaded.contains(&key) || namespaces.m_failed.contains(&key) {
+                    continue;
+                }
+            }
+            match self.load_resource_sync(&locale, namespace) {
+                Some(resource) if self.check_namespace_markup(namespace, &resource) => {
+                    self.insert_namespace(&locale, namespace, resource);
+                },
+                _ => {
+                    self.m_namespaces.write().unwrap().m_failed.insert(key);
+                },
+            }
+        }
+    }
+
+    fn apply_deep(name: &str, assign: serde_json::Value, mut output: &mut serde_json::Value) {
         let mut names: Vec<&str> = name.split("/").collect();
         let last_name = names.pop();
         for name in names {
@@ -207,12 +534,38 @@ impl LocaleBundle {
     fn enumerate_fallbacks(&self, locale: Language, output: &mut HashSet<Language>) {
         for list in self.m_fallbacks.get(&locale).iter() {
             for item in list.iter() {
-                output.insert(item.clone());
-                self.enumerate_fallbacks(item.clone(), output);
+                if output.insert(item.clone()) {
+                    self.enumerate_fallbacks(item.clone(), output);
+                }
             }
         }
     }
 
+    /// Finds a locale that falls back to itself, returning the path from it.
+    fn find_fallback_cycle(fallbacks: &HashMap<Language, Vec<Language>>) -> Option<Vec<Language>> {
+        fn visit(fallbacks: &HashMap<Language, Vec<Language>>, locale: &Language, path: &mut Vec<Language>, done: &mut HashSet<Language>) -> Option<Vec<Language>> {
+            if let Some(i) = path.iter().position(|l| l == locale) {
+                let mut cycle = path[i..].to_vec();
+                cycle.push(locale.clone());
+                return Some(cycle);
+            }
+            if done.contains(locale) {
+                return None;
+            }
+            path.push(locale.clone());
+            for item in fallbacks.get(locale).into_iter().flatten() {
+                if let Some(cycle) = visit(fallbacks, item, path, done) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            done.insert(locale.clone());
+            None
+        }
+        let mut done = HashSet::<Language>::new();
+        fallbacks.keys().find_map(|locale| visit(fallbacks, locale, &mut vec![], &mut done))
+    }
+
     /// Retrieves message by identifier.
     pub fn get(&self, id: impl AnyStringType) -> String {
         self.get_formatted(id, vec![])
@@ -220,8 +573,36 @@ impl LocaleBundle {
 
     /// Retrieves message by identifier with formatting arguments.
     pub fn get_formatted(&self, id: impl AnyStringType, options: Vec<&dyn LocaleBundleFormatArgument>) -> String {
-        let mut variables: Option<HashMap<String, String>> = None;
-        let mut id = id.convert().to_owned();
+        let (id, variables) = LocaleBundle::resolve_arguments(id.convert(), options);
+        if self.m_current_locale.is_none() {
+            return id.join(".");
+        }
+        self.load_namespace_on_demand(&id);
+        let r = self.get_formatted_with_locale(self.m_current_locale.clone().unwrap(), &id, &variables);
+        if let Some(r) = r { r } else { id.join(".") }
+    }
+
+    /// Retrieves a message with its markup as a tree of spans, formatting it
+    /// as `get_formatted` does. Variables are plain text, except for nested
+    /// messages, which keep their markup.
+    pub fn get_rich(&self, id: impl AnyStringType, options: Vec<&dyn LocaleBundleFormatArgument>) -> Vec<RichSpan> {
+        let (id, variables) = LocaleBundle::resolve_arguments(id.convert(), options);
+        if self.m_current_locale.is_none() {
+            return vec![RichSpan::Text(id.join("."))];
+        }
+        self.load_namespace_on_demand(&id);
+        let Some((locale, message)) = self.find_message(&self.m_assets, &self.m_current_locale.clone().unwrap(), &id) else {
+            return vec![RichSpan::Text(id.join("."))];
+        };
+        let mut stack = vec![id.join(".")];
+        self.apply_rich_message(&locale, message, &variables, &mut stack)
+    }
+
+    /// Appends the variant arguments to a message identifier and collects
+    /// the variables, returning the identifier split into components.
+    fn resolve_arguments(id: &str, options: Vec<&dyn LocaleBundleFormatArgument>) -> (Vec<String>, HashMap<String, MessageArgument>) {
+        let mut variables: Option<HashMap<String, MessageArgument>> = None;
+        let mut id = id.to_owned();
 
         for option in options.iter() {
             if let Some(r) = option.as_str() {
@@ -233,31 +614,55 @@ impl LocaleBundle {
                 id.push_str(r.as_str());
             }
             else if let Some(r) = option.as_string_map() {
-                variables = Some(r.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+                variables.get_or_insert_with(HashMap::new).extend(r.into_iter().map(|(k, v)| (k, MessageArgument::Text(v))));
+            }
+            else if let Some(r) = option.as_argument_map() {
+                variables.get_or_insert_with(HashMap::new).extend(r);
             }
         }
 
         if variables.is_none() { variables = Some(HashMap::new()); }
         let variables = variables.unwrap();
 
-        let id: Vec<String> = id.split(".").map(|s| s.to_string()).collect();
-        if self.m_current_locale.is_none() {
-            return id.join(".");
-        }
-        let r = self.get_formatted_with_locale(self.m_current_locale.clone().unwrap(), &id, &variables);
-        if let Some(r) = r { r } else { id.join(".") }
+        (id.split(".").map(|s| s.to_string()).collect(), variables)
+    }
+
+    fn get_formatted_with_locale(&self, locale: Language, id: &Vec<String>, vars: &HashMap<String, MessageArgument>) -> Option<String> {
+        let (locale, message) = self.find_message(&self.m_assets, &locale, id)?;
+        let mut stack = vec![id.join(".")];
+        Some(self.apply_message(&locale, message, vars, &mut stack))
     }
 
-    fn get_formatted_with_locale(&self, locale: Language, id: &Vec<String>, vars: &HashMap<String, String>) -> Option<String> {
-        let message = self.resolve_id(self.m_assets.get(&locale), id);
-        if message.is_some() {
-            return Some(self.apply_message(message.unwrap(), vars));
+    /// Looks up a message in the given locale, then in its fallbacks,
+    /// returning the locale in which it was found.
+    fn find_message(&self, assets: &HashMap<Language, LocaleResources>, locale: &Language, id: &Vec<String>) -> Option<(Language, Vec<MessagePart>)> {
+        if let Some(message) = self.lookup(assets, locale, id) {
+            return Some((locale.clone(), message));
         }
+        if let Some(fallbacks) = self.m_fallbacks.get(locale) {
+            for fl in fallbacks.iter() {
+                let r = self.find_message(assets, fl, id);
+                if r.is_some() {
+                    return r;
+                }
+            }
+        }
+        None
+    }
 
-        let fallbacks = self.m_fallbacks.get(&locale);
-        if fallbacks.is_some() {
-            for fl in fallbacks.unwrap().iter() {
-                let r = self.get_formatted_with_locale(fl.clone(), id, vars);
+    /// Looks up the message targeted by a `$ref(...)` in the given locale,
+    /// then in its fallbacks. Within each locale, the variant-suffixed
+    /// identifier is tried before the plain one.
+    fn find_reference(&self, assets: &HashMap<Language, LocaleResources>, locale: &Language, id: &str, variant: &Option<String>) -> Option<(Language, String, Vec<MessagePart>)> {
+        for candidate in reference_candidates(id, variant) {
+            let split: Vec<String> = candidate.split(".").map(|s| s.to_string()).collect();
+            if let Some(message) = self.lookup(assets, locale, &split) {
+                return Some((locale.clone(), candidate, message));
+            }
+        }
+        if let Some(fallbacks) = self.m_fallbacks.get(locale) {
+            for fl in fallbacks.iter() {
+                let r = self.find_reference(assets, fl, id, variant);
                 if r.is_some() {
                     return r;
                 }
@@ -266,37 +671,229 @@ impl LocaleBundle {
         None
     }
 
-    fn apply_message(&self, message: String, vars: &HashMap<String, String>) -> String {
-        regex!(r"\$(\$|[A-Za-z0-9]+)").replace_all(&message, |s: &regex::Captures<'_>| {
-            let s = s.get(0).unwrap().as_str();
-            if s == "$$" {
-                "$"
-            } else {
-                let v = vars.get(&s.to_string().replace("$", ""));
-                if let Some(v) = v { v } else { "undefined" }
+    fn apply_message(&self, locale: &Language, message: Vec<MessagePart>, vars: &HashMap<String, MessageArgument>, stack: &mut Vec<String>) -> String {
+        let mut r = String::new();
+        for part in message {
+            match part {
+                MessagePart::Text(s) => r.push_str(&s),
+                MessagePart::Variable { name, format } => match vars.get(&name) {
+                    Some(value) => r.push_str(&self.format_argument(locale, value, format.as_deref(), vars, stack)),
+                    None => r.push_str("undefined"),
+                },
+                MessagePart::Reference { id, variant } => {
+                    self.load_namespace_on_demand(&id.split(".").map(|s| s.to_string()).collect::<Vec<String>>());
+                    let found = self.find_reference(&self.m_assets, locale, &id, &variant);
+                    match found {
+                        Some((ref_locale, ref_id, ref_message)) if !stack.contains(&ref_id) => {
+                            stack.push(ref_id);
+                            r.push_str(&self.apply_message(&ref_locale, ref_message, vars, stack));
+                            stack.pop();
+                        },
+                        _ => r.push_str(&id),
+                    }
+                },
+                MessagePart::StartTag { .. } | MessagePart::EndTag { .. } | MessagePart::EmptyTag { .. } => {},
             }
-        }).as_ref().to_string()
+        }
+        r
     }
 
-    fn resolve_id(&self, root: Option<&serde_json::Value>, id: &Vec<String>) -> Option<String> {
-        let mut r = root;
-        for frag in id.iter() {
-            if r.is_none() {
-                return None;
+    fn apply_rich_message(&self, locale: &Language, message: Vec<MessagePart>, vars: &HashMap<String, MessageArgument>, stack: &mut Vec<String>) -> Vec<RichSpan> {
+        let mut r = RichTextBuilder::new();
+        for part in message {
+            match part {
+                MessagePart::Text(s) => r.push_text(&s),
+                MessagePart::Variable { name, format } => match vars.get(&name) {
+                    Some(MessageArgument::Message(id)) => {
+                        r.push_spans(self.apply_rich_message(locale, vec![MessagePart::Reference { id: id.clone(), variant: None }], vars, stack));
+                    },
+                    _ => r.push_text(&self.apply_message(locale, vec![MessagePart::Variable { name, format }], vars, stack)),
+                },
+                MessagePart::Reference { id, variant } => {
+                    self.load_namespace_on_demand(&id.split(".").map(|s| s.to_string()).collect::<Vec<String>>());
+                    let found = self.find_reference(&self.m_assets, locale, &id, &variant);
+                    match found {
+                        Some((ref_locale, ref_id, ref_message)) if !stack.contains(&ref_id) => {
+                            stack.push(ref_id);
+                            r.push_spans(self.apply_rich_message(&ref_locale, ref_message, vars, stack));
+                            stack.pop();
+                        },
+                        _ => r.push_text(&id),
+                    }
+                },
+                MessagePart::StartTag { name, attributes } => r.start(&name, attributes),
+                MessagePart::EndTag { name } => r.end(&name),
+                MessagePart::EmptyTag { name, attributes } => {
+                    r.start(&name, attributes);
+                    r.end(&name);
+                },
+            }
+        }
+        r.finish()
+    }
+
+    /// Formats the value of a variable in a locale given an optional format
+    /// from the message, such as `currency` or `date:short`. Formats that do
+    /// not apply to the value are ignored.
+    fn format_argument(&self, locale: &Language, value: &MessageArgument, format: Option<&str>, vars: &HashMap<String, MessageArgument>, stack: &mut Vec<String>) -> String {
+        let (style, option) = match format {
+            Some(format) => format.split_once(':').unwrap_or((format, "")),
+            None => ("", ""),
+        };
+        match value {
+            MessageArgument::Text(s) => {
+                // textual numbers are formatted as numbers only if requested,
+                // and spelled out only if they are integers
+                let spellout = matches!(style, "spellout" | "spellout-ordinal" | "ordinal" | "roman");
+                match s.trim().parse::<f64>() {
+                    Ok(n) if n.is_finite() && (spellout || matches!(style, "number" | "integer" | "percent" | "currency")) && (!spellout || n.fract() == 0.0) => {
+                        LocaleBundle::format_number(locale, n, style, option)
+                    },
+                    _ => s.clone(),
+                }
+            },
+            MessageArgument::Number(n) => LocaleBundle::format_number(locale, *n, style, option),
+            MessageArgument::Currency(amount) => NumberFormatter::new(locale, &NumberFormatOptions::new()).format_currency(amount),
+            MessageArgument::Date(date) => {
+                let date_style = match option {
+                    "full" => DateStyle::Full,
+                    "long" => DateStyle::Long,
+                    "short" => DateStyle::Short,
+                    _ => DateStyle::Medium,
+                };
+                let options = DateFormatOptions::new();
+                options.date_style(if style == "time" { None } else { Some(date_style) });
+                options.time_style(if style == "date" { None } else { Some(date_style) });
+                DateFormatter::new(locale, &options).format(date)
+            },
+            MessageArgument::Duration(duration) => {
+                let options = MeasureFormatOptions::new();
+                options.width(match option {
+                    "short" => UnitWidth::Short,
+                    "narrow" => UnitWidth::Narrow,
+                    _ => UnitWidth::Long,
+                });
+                MeasureFormatter::new(locale, &options).format_duration(*duration)
+            },
+            MessageArgument::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| self.format_argument(locale, item, None, vars, stack)).collect();
+                let options = ListFormatOptions::new();
+                options.style(match option {
+                    "or" => ListStyle::Or,
+                    "unit" => ListStyle::Unit,
+                    _ => ListStyle::And,
+                });
+                ListFormatter::new(locale, &options).format(&items)
+            },
+            MessageArgument::Message(id) => self.apply_message(locale, vec![MessagePart::Reference { id: id.clone(), variant: None }], vars, stack),
+        }
+    }
+
+    /// Formats a number given a format such as `percent`, `spellout` or `ordinal:feminine`.
+    /// Numbers are formatted with the currency of the locale's region if the format is
+    /// `currency`, and spelled out only if they are integers.
+    fn format_number(locale: &Language, n: f64, style: &str, option: &str) -> String {
+        let integer = if n.fract() == 0.0 && n.abs() < 1e18 { Some(n as i64) } else { None };
+        match (style, integer) {
+            ("spellout" | "spellout-ordinal" | "ordinal" | "roman", Some(integer)) => {
+                let options = SpelloutFormatOptions::new();
+                options.style(match style {
+                    "spellout-ordinal" => SpelloutStyle::Ordinal,
+                    "ordinal" => SpelloutStyle::DigitsOrdinal,
+                    "roman" => SpelloutStyle::Roman,
+                    _ => SpelloutStyle::Cardinal,
+                });
+                options.gender(if option == "feminine" { GrammaticalGender::Feminine } else { GrammaticalGender::Masculine });
+                SpelloutFormatter::new(locale, &options).format(integer)
+            },
+            ("integer", _) => NumberFormatter::new(locale, NumberFormatOptions::new().maximum_fraction_digits(0)).format(n),
+            ("percent", _) => NumberFormatter::new(locale, &NumberFormatOptions::new()).format_percent(n),
+            ("currency", _) => {
+                let currency = currency_for_region(&locale.region().id().to_ascii_uppercase()).unwrap_or_else(|| "USD".to_owned());
+                NumberFormatter::new(locale, &NumberFormatOptions::new()).format_currency(&CurrencyAmount { amount: n, currency })
+            },
+            _ => NumberFormatter::new(locale, &NumberFormatOptions::new()).format(n),
+        }
+    }
+
+    /// Looks for cyclic `$ref(...)` chains among the messages of the given assets,
+    /// returning the identifiers forming the first cycle found.
+    fn find_reference_cycle(&self, assets: &HashMap<Language, LocaleResources>) -> Option<Vec<String>> {
+        let mut messages: Vec<(Language, String)> = vec![];
+        for (locale, resources) in assets.iter() {
+            messages.extend(resources.message_ids().into_iter().map(|id| (locale.clone(), id)));
+        }
+        let mut done: HashSet<(Language, String)> = hashset![];
+        for (locale, id) in messages {
+            let mut stack: Vec<(Language, String)> = vec![];
+            if let Some(cycle) = self.visit_references(assets, &locale, &id, &mut stack, &mut done) {
+                return Some(cycle);
             }
-            r = r.unwrap().get(frag);
         }
-        if r.is_none() {
+        None
+    }
+
+    fn visit_references(&self, assets: &HashMap<Language, LocaleResources>, locale: &Language, id: &str, stack: &mut Vec<(Language, String)>, done: &mut HashSet<(Language, String)>) -> Option<Vec<String>> {
+        let node = (locale.clone(), id.to_owned());
+        if let Some(i) = stack.iter().position(|n| *n == node) {
+            let mut cycle: Vec<String> = stack[i..].iter().map(|(_, id)| id.clone()).collect();
+            cycle.push(id.to_owned());
+            return Some(cycle);
+        }
+        if done.contains(&node) {
             return None;
         }
-        let r = r.unwrap().as_str();
-        if let Some(r) = r { Some(r.to_string()) } else { None }
+        let split: Vec<String> = id.split(".").map(|s| s.to_string()).collect();
+        if let Some(message) = self.lookup(assets, locale, &split) {
+            stack.push(node.clone());
+            for part in message {
+                if let MessagePart::Reference { id, variant } = part {
+                    if let Some((ref_locale, ref_id, _)) = self.find_reference(assets, locale, &id, &variant) {
+                        let r = self.visit_references(assets, &ref_locale, &ref_id, stack, done);
+                        if r.is_some() {
+                            return r;
+                        }
+                    }
+                }
+            }
+            stack.pop();
+        }
+        done.insert(node);
+        None
+    }
+
+    fn enumerate_message_ids(value: &serde_json::Value, path: &mut Vec<String>, output: &mut impl FnMut(String)) {
+        if let Some(map) = value.as_object() {
+            for (k, v) in map.iter() {
+                path.push(k.clone());
+                LocaleBundle::enumerate_message_ids(v, path, output);
+                path.pop();
+            }
+        } else if value.is_string() {
+            output(path.join("."));
+        }
+    }
+
+    /// Looks up a message in a single locale, including its loaded namespaces.
+    fn lookup(&self, assets: &HashMap<Language, LocaleResources>, locale: &Language, id: &[String]) -> Option<Vec<MessagePart>> {
+        let message = assets.get(locale).and_then(|r| r.lookup(id))
+            .or_else(|| self.m_namespaces.read().unwrap().m_roots.get(locale).and_then(|r| r.lookup(id)))?;
+        Some(unknown_tags_as_text(message, &self.m_markup_tags))
+    }
+
+    fn resolve_id(root: Option<&serde_json::Value>, id: &[String]) -> Option<String> {
+        let mut r = root;
+        for frag in id.iter() {
+            r = r?.get(frag);
+        }
+        r?.as_str().map(|r| r.to_string())
     }
 }
 
 impl Clone for LocaleBundle {
     /// Clones the locator, sharing the same
-    /// resources.
+    /// resources. Lazily loaded namespaces are copied,
+    /// so that each clone loads and unloads its own.
     fn clone(&self) -> Self {
         Self {
             m_current_locale: self.m_current_locale.clone(),
@@ -307,8 +904,12 @@ impl Clone for LocaleBundle {
             m_assets: self.m_assets.clone(),
             m_assets_src: self.m_assets_src.clone(),
             m_assets_base_file_names: self.m_assets_base_file_names.clone(),
+            m_assets_lazy_file_names: self.m_assets_lazy_file_names.clone(),
             m_assets_clean_unused: self.m_assets_clean_unused,
             m_assets_load_method: self.m_assets_load_method,
+            m_assets_format: self.m_assets_format,
+            m_namespaces: RwLock::new(self.m_namespaces.read().unwrap().clone()),
+            m_markup_tags: self.m_markup_tags.clone(),
         }
     }
 }
@@ -317,6 +918,7 @@ pub trait LocaleBundleFormatArgument {
     fn as_str(&self) -> Option<&'static str> { None }
     fn as_string(&self) -> Option<String> { None }
     fn as_string_map(&self) -> Option<HashMap<String, String>> { None }
+    fn as_argument_map(&self) -> Option<HashMap<String, MessageArgument>> { None }
 }
 
 impl LocaleBundleFormatArgument for &'static str {
@@ -331,6 +933,10 @@ impl LocaleBundleFormatArgument for HashMap<String, String> {
     fn as_string_map(&self) -> Option<HashMap<String, String>> { Some(self.clone()) }
 }
 
+impl LocaleBundleFormatArgument for HashMap<String, MessageArgument> {
+    fn as_argument_map(&self) -> Option<HashMap<String, MessageArgument>> { Some(self.clone()) }
+}
+
 impl LocaleBundleFormatArgument for i8 { fn as_string(&self) -> Option<String> { Some(self.to_string()) } }
 impl LocaleBundleFormatArgument for i16 { fn as_string(&self) -> Option<String> { Some(self.to_string()) } }
 impl LocaleBundleFormatArgument for i32 { fn as_string(&self) -> Option<String> { Some(self.to_string()) } }
@@ -346,11 +952,62 @@ impl LocaleBundleFormatArgument for usize { fn as_string(&self) -> Option<String
 impl LocaleBundleFormatArgument for f32 { fn as_string(&self) -> Option<String> { Some(self.to_string()) } }
 impl LocaleBundleFormatArgument for f64 { fn as_string(&self) -> Option<String> { Some(self.to_string()) } }
 
+/// A typed value of a message variable, formatted in the current locale
+/// as given by the variable's format in the message, such as `$price:currency`
+/// or `$when:date:short`.
+#[derive(Clone)]
+pub enum MessageArgument {
+    /// Text, inserted as is. Textual numbers are formatted as numbers
+    /// if the variable has a number format, such as `spellout`.
+    Text(String),
+    /// A number, formatted with the formats `number` (the default), `integer`,
+    /// `percent`, `currency`, `spellout`, `spellout-ordinal`, `ordinal` or `roman`.
+    Number(f64),
+    /// A date-time, formatted with the formats `datetime` (the default), `date`
+    /// or `time`, optionally followed by a style: `full`, `long`, `medium` (the default)
+    /// or `short`.
+    Date(ZonedDateTime),
+    /// A duration in days, hours, minutes and seconds, formatted with the formats
+    /// `duration:long` (the default), `duration:short` or `duration:narrow`.
+    Duration(Duration),
+    /// An amount of money, formatted with the fraction digits of its currency.
+    Currency(CurrencyAmount),
+    /// A list of values, each formatted with its default format and joined with the
+    /// formats `list:and` (the default), `list:or` or `list:unit`.
+    List(Vec<MessageArgument>),
+    /// Identifier of a message, formatted with the same arguments as if
+    /// it were referenced with `$ref(...)`.
+    Message(String),
+}
+
+impl From<&str> for MessageArgument { fn from(value: &str) -> Self { MessageArgument::Text(value.to_owned()) } }
+impl From<String> for MessageArgument { fn from(value: String) -> Self { MessageArgument::Text(value) } }
+impl From<i8> for MessageArgument { fn from(value: i8) -> Self { MessageArgument::Number(value.into()) } }
+impl From<i16> for MessageArgument { fn from(value: i16) -> Self { MessageArgument::Number(value.into()) } }
+impl From<i32> for MessageArgument { fn from(value: i32) -> Self { MessageArgument::Number(value.into()) } }
+impl From<i64> for MessageArgument { fn from(value: i64) -> Self { MessageArgument::Number(value as f64) } }
+impl From<isize> for MessageArgument { fn from(value: isize) -> Self { MessageArgument::Number(value as f64) } }
+impl From<u8> for MessageArgument { fn from(value: u8) -> Self { MessageArgument::Number(value.into()) } }
+impl From<u16> for MessageArgument { fn from(value: u16) -> Self { MessageArgument::Number(value.into()) } }
+impl From<u32> for MessageArgument { fn from(value: u32) -> Self { MessageArgument::Number(value.into()) } }
+impl From<u64> for MessageArgument { fn from(value: u64) -> Self { MessageArgument::Number(value as f64) } }
+impl From<usize> for MessageArgument { fn from(value: usize) -> Self { MessageArgument::Number(value as f64) } }
+impl From<f32> for MessageArgument { fn from(value: f32) -> Self { MessageArgument::Number(value.into()) } }
+impl From<f64> for MessageArgument { fn from(value: f64) -> Self { MessageArgument::Number(value) } }
+impl From<ZonedDateTime> for MessageArgument { fn from(value: ZonedDateTime) -> Self { MessageArgument::Date(value) } }
+impl From<Duration> for MessageArgument { fn from(value: Duration) -> Self { MessageArgument::Duration(value) } }
+impl From<CurrencyAmount> for MessageArgument { fn from(value: CurrencyAmount) -> Self { MessageArgument::Currency(value) } }
+
+impl<T: Into<MessageArgument>> From<Vec<T>> for MessageArgument {
+    fn from(value: Vec<T>) -> Self { MessageArgument::List(value.into_iter().map(|v| v.into()).collect()) }
+}
+
 pub struct LocaleBundleOptions {
     m_default_locale: RefCell<String>,
     m_supported_locales: RefCell<Vec<String>>,
     m_fallbacks: RefCell<HashMap<String, Vec<String>>>,
     m_assets: RefCell<LocaleBundleOptionsForAssets>,
+    m_markup_tags: RefCell<Vec<String>>,
 }
 
 impl LocaleBundleOptions {
@@ -360,6 +1017,7 @@ impl LocaleBundleOptions {
             m_supported_locales: RefCell::new(vec!["en".to_string()]),
             m_fallbacks: RefCell::new(hashmap! {}),
             m_assets: RefCell::new(LocaleBundleOptionsForAssets::new()),
+            m_markup_tags: RefCell::new(["b", "i", "u", "s", "link", "icon"].iter().map(|s| s.to_string()).collect()),
         }
     }
 
@@ -381,17 +1039,32 @@ impl LocaleBundleOptions {
         self
     }
 
+    /// Markup tags allowed in messages, which defaults to `b`, `i`, `u`, `s`, `link` and `icon`.
+    /// Other tags, such as `<Enter>`, are text.
+    pub fn markup_tags(&self, list: Vec<impl AnyStringType>) -> &Self {
+        self.m_markup_tags.replace(list.iter().map(|name| name.convert().to_owned()).collect());
+        self
+    }
+
     pub fn assets(&self, options: &LocaleBundleOptionsForAssets) -> &Self {
         self.m_assets.replace(options.clone());
         self
     }
 }
 
+impl Default for LocaleBundleOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct LocaleBundleOptionsForAssets {
     m_src: RefCell<String>,
     m_base_file_names: RefCell<Vec<String>>,
+    m_lazy_file_names: RefCell<Vec<String>>,
     m_clean_unused: Cell<bool>,
     m_load_method: Cell<LocaleBundleLoadMethod>,
+    m_format: Cell<LocaleBundleAssetFormat>,
 }
 
 impl Clone for LocaleBundleOptionsForAssets {
@@ -399,8 +1072,10 @@ impl Clone for LocaleBundleOptionsForAssets {
         Self {
             m_src: self.m_src.clone(),
             m_base_file_names: self.m_base_file_names.clone(),
+            m_lazy_file_names: self.m_lazy_file_names.clone(),
             m_clean_unused: self.m_clean_unused.clone(),
             m_load_method: self.m_load_method.clone(),
+            m_format: self.m_format.clone(),
         }
     }
 }
@@ -410,11 +1085,16 @@ impl LocaleBundleOptionsForAssets {
         LocaleBundleOptionsForAssets {
             m_src: RefCell::new("res/lang".to_string()),
             m_base_file_names: RefCell::new(vec![]),
+            m_lazy_file_names: RefCell::new(vec![]),
             m_clean_unused: Cell::new(true),
             m_load_method: Cell::new(LocaleBundleLoadMethod::Http),
+            m_format: Cell::new(LocaleBundleAssetFormat::Json),
         }
     }
     
+    /// Location of the resource files. With the `FileSystem` load method,
+    /// this is a path or a URL accepted by `rialight_filesystem::File`,
+    /// including custom schemes registered with `FileSchemes`, such as `mods://core/lang`.
     pub fn src(&self, src: impl AnyStringType) -> &Self {
         self.m_src.replace(src.convert().to_owned());
         self
@@ -425,6 +1105,13 @@ impl LocaleBundleOptionsForAssets {
         self
     }
 
+    /// Resource files that are loaded on first access to one of their messages
+    /// instead of when the locale is loaded, such as `"quests/act2"`.
+    pub fn lazy_file_names(&self, list: Vec<impl AnyStringType>) -> &Self {
+        self.m_lazy_file_names.replace(list.iter().map(|name| name.convert().to_owned()).collect());
+        self
+    }
+
     pub fn clean_unused(&self, value: bool) -> &Self {
         self.m_clean_unused.set(value);
         self
@@ -434,10 +1121,313 @@ impl LocaleBundleOptionsForAssets {
         self.m_load_method.set(value);
         self
     }
+
+    /// Format of the resource files. With `LocaleBundleAssetFormat::Catalog`,
+    /// each resource file is a catalog compiled by `rialight_pi::compile_locale_catalogs`,
+    /// with the `.rlcat` extension instead of `.json`.
+    pub fn format(&self, value: LocaleBundleAssetFormat) -> &Self {
+        self.m_format.set(value);
+        self
+    }
+}
+
+impl Default for LocaleBundleOptionsForAssets {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Copy, Clone)]
 pub enum LocaleBundleLoadMethod {
     FileSystem,
     Http,
+}
+
+#[derive(Copy, Clone)]
+pub enum LocaleBundleAssetFormat {
+    Json,
+    Catalog,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Disambiguation, TimeZone};
+
+    /// A directory in the system temporary directory,
+    /// removed with its contents when dropped.
+    struct TestDirectory(std::path::PathBuf);
+
+    impl std::ops::Deref for TestDirectory {
+        type Target = std::path::Path;
+        fn deref(&self) -> &std::path::Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDirectory {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Writes English assets to a new temporary directory.
+    fn write_assets(name: &str, files: Vec<(&str, &str)>) -> TestDirectory {
+        let directory = TestDirectory(std::env::temp_dir().join(format!("locale-bundle-test-{}-{}", std::process::id(), name)));
+        write_files(&directory, "en", files);
+        directory
+    }
+
+    fn write_files(directory: &std::path::Path, locale: &str, files: Vec<(&str, &str)>) {
+        for (file_name, contents) in files {
+            let path = directory.join(locale).join(file_name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+    }
+
+    fn lazy_bundle(src: &std::path::Path, supported_locales: Vec<&str>, lazy_file_names: Vec<&str>) -> LocaleBundle {
+        LocaleBundle::new(
+            LocaleBundleOptions::new()
+                .supported_locales(supported_locales)
+                .default_locale("en")
+                .assets(LocaleBundleOptionsForAssets::new()
+                    .src(src.to_str().unwrap())
+                    .base_file_names(vec!["_"])
+                    .lazy_file_names(lazy_file_names)
+                    .load_method(LocaleBundleLoadMethod::FileSystem))
+        )
+    }
+
+    fn bundle(src: &std::path::Path, base_file_names: Vec<&str>) -> LocaleBundle {
+        LocaleBundle::new(
+            LocaleBundleOptions::new()
+                .supported_locales(vec!["en"])
+                .default_locale("en")
+                .assets(LocaleBundleOptionsForAssets::new()
+                    .src(src.to_str().unwrap())
+                    .base_file_names(base_file_names)
+                    .load_method(LocaleBundleLoadMethod::FileSystem))
+        )
+    }
+
+    #[tokio::test]
+    async fn references() {
+        let directory = write_assets("references", vec![("_.json", r#"{
+            "items": {"forge": "Crystal Forge", "forge_genitive": "Crystal Forge's"},
+            "open_forge": "Open the $ref(_.items.forge)",
+            "forge_door": "The $ref(_.items.forge, genitive) door",
+            "plain_door": "The $ref(_.items.forge, dative) door",
+            "greeting": "Hello, $name",
+            "welcome": "$ref(_.greeting)!"
+        }"#)]);
+        let mut bundle = bundle(&directory, vec!["_"]);
+        assert!(bundle.load(None).await);
+        assert_eq!(bundle.get("_.open_forge"), "Open the Crystal Forge");
+        assert_eq!(bundle.get("_.forge_door"), "The Crystal Forge's door");
+        assert_eq!(bundle.get("_.plain_door"), "The Crystal Forge door");
+        let vars: HashMap<String, String> = hashmap! { "name".to_owned() => "Ana".to_owned() };
+        assert_eq!(bundle.get_formatted("_.welcome", vec![&vars]), "Hello, Ana!");
+    }
+
+    #[tokio::test]
+    async fn rich_text() {
+        let directory = write_assets("rich-text", vec![("_.json", r#"{
+            "item": "<b>Crystal</b> Sword",
+            "buy": "Buy <b>$item</b> for <icon name=\"coin\"/> $price",
+            "keys": "Press <Enter> or <br/> to <b>continue</b>",
+            "escaped": "$<b> is $$1",
+            "nested": "Take the $item"
+        }"#), ("invalid.json", r#"{"unclosed": "<b>bold"}"#)]);
+        let mut bundle = bundle(&directory, vec!["_"]);
+        assert!(bundle.load(None).await);
+        let vars: HashMap<String, String> = hashmap! { "item".to_owned() => "<i>sword</i>".to_owned(), "price".to_owned() => "5".to_owned() };
+        assert_eq!(bundle.get_formatted("_.buy", vec![&vars]), "Buy <i>sword</i> for  5");
+        let spans = bundle.get_rich("_.buy", vec![&vars]);
+        assert_eq!(spans, [
+            RichSpan::Text("Buy ".to_owned()),
+            RichSpan::Element { tag: "b".to_owned(), attributes: vec![], children: vec![RichSpan::Text("<i>sword</i>".to_owned())] },
+            RichSpan::Text(" for ".to_owned()),
+            RichSpan::Element { tag: "icon".to_owned(), attributes: vec![("name".to_owned(), "coin".to_owned())], children: vec![] },
+            RichSpan::Text(" 5".to_owned()),
+        ]);
+        // nested messages keep their markup
+        let args = bundle_args! { "item" => MessageArgument::Message("_.item".to_owned()) };
+        assert_eq!(bundle.get_rich("_.nested", vec![&args]), [
+            RichSpan::Text("Take the ".to_owned()),
+            RichSpan::Element { tag: "b".to_owned(), attributes: vec![], children: vec![RichSpan::Text("Crystal".to_owned())] },
+            RichSpan::Text(" Sword".to_owned()),
+        ]);
+        // other tags are text
+        assert_eq!(bundle.get("_.keys"), "Press <Enter> or <br/> to continue");
+        assert_eq!(RichSpan::plain_text(&bundle.get_rich("_.keys", vec![])), "Press <Enter> or <br/> to continue");
+        assert_eq!(bundle.get("_.escaped"), "<b> is $1");
+        assert_eq!(bundle.get_rich("_.escaped", vec![]), [RichSpan::Text("<b> is $1".to_owned())]);
+        assert_eq!(bundle.get_rich("_.missing", vec![]), [RichSpan::Text("_.missing".to_owned())]);
+
+        let mut invalid = self::bundle(&directory, vec!["_", "invalid"]);
+        assert!(!invalid.load(None).await);
+    }
+
+    #[tokio::test]
+    async fn catalog_assets() {
+        let directory = write_assets("catalog-assets", vec![]);
+        let catalog = LocaleCatalog::compile(&serde_json::from_str(r#"{"menu": {"play": "Play", "coins": "$count coins"}}"#).unwrap());
+        std::fs::create_dir_all(directory.join("en")).unwrap();
+        std::fs::write(directory.join("en").join("_.rlcat"), catalog).unwrap();
+        let mut bundle = LocaleBundle::new(
+            LocaleBundleOptions::new()
+                .supported_locales(vec!["en"])
+                .default_locale("en")
+                .assets(LocaleBundleOptionsForAssets::new()
+                    .src(directory.to_str().unwrap())
+                    .base_file_names(vec!["_"])
+                    .format(LocaleBundleAssetFormat::Catalog)
+                    .load_method(LocaleBundleLoadMethod::FileSystem))
+        );
+        assert!(bundle.load(None).await);
+        assert_eq!(bundle.get("_.menu.play"), "Play");
+        let vars: HashMap<String, String> = hashmap! { "count".to_owned() => "3".to_owned() };
+        assert_eq!(bundle.get_formatted("_.menu.coins", vec![&vars]), "3 coins");
+        assert_eq!(bundle.get("_.menu.missing"), "_.menu.missing");
+    }
+
+    #[tokio::test]
+    async fn lazy_namespaces() {
+        let directory = write_assets("lazy-namespaces", vec![
+            ("_.json", r#"{"title": "Game"}"#),
+            ("quests/act2.json", r#"{"start": "The journey begins"}"#),
+        ]);
+        let mut bundle = lazy_bundle(&directory, vec!["en"], vec!["quests/act2"]);
+        assert!(bundle.load(None).await);
+        assert!(!bundle.is_namespace_loaded("quests/act2"));
+        assert_eq!(bundle.get("quests.act2.start"), "The journey begins");
+        assert!(bundle.is_namespace_loaded("quests/act2"));
+
+        bundle.unload_namespace("quests/act2");
+        assert!(!bundle.is_namespace_loaded("quests/act2"));
+        assert!(bundle.ensure_namespace("quests/act2").await);
+        assert!(bundle.is_namespace_loaded("quests/act2"));
+        assert_eq!(bundle.get("quests.act2.start"), "The journey begins");
+        assert!(!bundle.ensure_namespace("quests/missing").await);
+    }
+
+    #[tokio::test]
+    async fn lazy_namespace_failure_is_remembered() {
+        let directory = write_assets("lazy-namespace-failure", vec![("_.json", r#"{"title": "Game"}"#)]);
+        let mut bundle = lazy_bundle(&directory, vec!["en"], vec!["quests/act3"]);
+        assert!(bundle.load(None).await);
+        assert_eq!(bundle.get("quests.act3.start"), "quests.act3.start");
+        write_files(&directory, "en", vec![("quests/act3.json", r#"{"start": "Act 3"}"#)]);
+        // not attempted again on access
+        assert_eq!(bundle.get("quests.act3.start"), "quests.act3.start");
+        assert!(bundle.ensure_namespace("quests/act3").await);
+        assert_eq!(bundle.get("quests.act3.start"), "Act 3");
+    }
+
+    #[tokio::test]
+    async fn lazy_namespaces_reload_on_locale_change() {
+        let directory = write_assets("lazy-namespaces-locale", vec![
+            ("_.json", r#"{"title": "Game"}"#),
+            ("quests/act2.json", r#"{"start": "The journey begins", "end": "The end"}"#),
+        ]);
+        write_files(&directory, "pt", vec![
+            ("_.json", r#"{"title": "Jogo"}"#),
+            ("quests/act2.json", r#"{"start": "A jornada começa"}"#),
+        ]);
+        let mut bundle = lazy_bundle(&directory, vec!["en", "pt"], vec!["quests/act2"]);
+        assert!(bundle.load(None).await);
+        assert!(bundle.ensure_namespace("quests/act2").await);
+        assert!(bundle.load(Some(Language::parse("pt").unwrap())).await);
+        assert!(bundle.is_namespace_loaded("quests/act2"));
+        assert_eq!(bundle.get("quests.act2.start"), "A jornada começa");
+        // falls back to English
+        assert_eq!(bundle.get("quests.act2.end"), "The end");
+    }
+
+    #[tokio::test]
+    async fn lazy_namespaces_are_not_shared_by_clones() {
+        let directory = write_assets("lazy-namespaces-clone", vec![
+            ("_.json", r#"{"title": "Game"}"#),
+            ("quests/act2.json", r#"{"start": "The journey begins"}"#),
+        ]);
+        write_files(&directory, "pt", vec![("_.json", r#"{"title": "Jogo"}"#)]);
+        let mut bundle = lazy_bundle(&directory, vec!["en", "pt"], vec!["quests/act2"]);
+        assert!(bundle.load(None).await);
+        assert!(bundle.ensure_namespace("quests/act2").await);
+        let other = bundle.clone();
+        bundle.unload_namespace("quests/act2");
+        assert!(other.is_namespace_loaded("quests/act2"));
+        assert_eq!(other.get("quests.act2.start"), "The journey begins");
+    }
+
+    #[tokio::test]
+    async fn reference_cycle() {
+        let directory = write_assets("reference-cycle", vec![("_.json", r#"{"a": "$ref(_.b)", "b": "x $ref(_.c)", "c": "$ref(_.a)"}"#)]);
+        let mut bundle = bundle(&directory, vec!["_"]);
+        assert!(!bundle.load(None).await);
+        std::fs::write(directory.join("en/_.json"), r#"{"a": "$ref(_.b)", "b": "x $ref(_.c)", "c": "c"}"#).unwrap();
+        assert!(bundle.load(None).await);
+        assert_eq!(bundle.get("_.a"), "x c");
+    }
+
+    #[tokio::test]
+    async fn typed_arguments() {
+        let directory = write_assets("typed-arguments", vec![("_.json", r#"{
+            "price": "$x:currency",
+            "total": "$n:currency",
+            "rank": "$n:ordinal, $t:spellout, $p:percent",
+            "short_date": "$when:date:short",
+            "date_time": "$when",
+            "methods": "$m:list:or",
+            "items": "$m",
+            "greeting": "Hi $msg!",
+            "name": "$who"
+        }"#)]);
+        let mut bundle = bundle(&directory, vec!["_"]);
+        assert!(bundle.load(None).await);
+        let when = ZonedDateTime::from_local(2024, 3, 5, 13, 30, 0, &TimeZone::utc(), Disambiguation::Reject).unwrap();
+        let args = bundle_args! {
+            "x" => CurrencyAmount { amount: 9.5, currency: "EUR".to_owned() },
+            "n" => 1234.5,
+            "t" => "21",
+            "p" => 0.5,
+            "when" => when,
+            "m" => vec!["a", "b", "c"],
+            "msg" => MessageArgument::Message("_.name".to_owned()),
+            "who" => "Ana",
+        };
+        assert_eq!(bundle.get_formatted("_.price", vec![&args]), "€9.50");
+        // numbers are formatted in the currency of the locale's region
+        assert_eq!(bundle.get_formatted("_.total", vec![&args]), "$1,234.50");
+        assert_eq!(bundle.get_formatted("_.rank", vec![&bundle_args! { "n" => 2, "t" => "21", "p" => 0.5 }]), "2nd, twenty-one, 50%");
+        assert_eq!(bundle.get_formatted("_.short_date", vec![&args]), "3/5/24");
+        assert_eq!(bundle.get_formatted("_.date_time", vec![&args]), "Mar 5, 2024, 1:30:00 PM");
+        assert_eq!(bundle.get_formatted("_.methods", vec![&args]), "a, b, or c");
+        assert_eq!(bundle.get_formatted("_.items", vec![&args]), "a, b, and c");
+        assert_eq!(bundle.get_formatted("_.greeting", vec![&args]), "Hi Ana!");
+        // a text number is not spelled out unless it is an integer
+        assert_eq!(bundle.get_formatted("_.rank", vec![&bundle_args! { "n" => 2, "t" => "2.5", "p" => 1 }]), "2nd, 2.5, 100%");
+    }
+
+    #[test]
+    fn derived_fallbacks() {
+        let bundle = LocaleBundle::new(
+            LocaleBundleOptions::new()
+                .supported_locales(vec!["pt", "pt-PT", "pt-AO", "pt-MZ", "en-AU"])
+                .default_locale("pt")
+                .fallbacks(hashmap! { "pt-MZ" => vec!["pt"] })
+        );
+        let fallback = |tag: &str| bundle.m_fallbacks.get(&Language::parse(tag).unwrap()).map(|list| list.iter().map(|l| l.tag()).collect::<Vec<String>>());
+        assert_eq!(fallback("pt-AO"), Some(vec!["pt-PT".to_owned()]));
+        assert_eq!(fallback("pt-PT"), Some(vec!["pt-BR".to_owned()]));
+        assert_eq!(fallback("pt"), None);
+        assert_eq!(fallback("pt-MZ"), Some(vec!["pt-BR".to_owned()]));
+        // no supported ancestor
+        assert_eq!(fallback("en-AU"), Some(vec!["pt-BR".to_owned()]));
+
+        let mut locales = hashset![];
+        bundle.enumerate_fallbacks(Language::parse("pt-AO").unwrap(), &mut locales);
+        assert_eq!(locales, hashset![Language::parse("pt-PT").unwrap(), Language::parse("pt").unwrap()]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,363 @@
+use std::{cell::Cell, collections::HashMap, time::Duration};
+use super::{Language, MeasurementSystem, NumberFormatter, NumberFormatOptions, PluralRules, ListFormatter, ListFormatOptions, ListStyle};
+
+/// Unit patterns by language, unit, width and plural category.
+type UnitData = HashMap<String, HashMap<String, HashMap<String, HashMap<String, String>>>>;
+
+lazy_static! {
+    static ref UNIT_DATA: UnitData = {
+        serde_json::from_str(include_str!("../data/unit.json")).unwrap()
+    };
+}
+
+/// Physical quantity measured by a unit.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum MeasureQuantity {
+    Length,
+    Speed,
+    Temperature,
+    Mass,
+    Duration,
+}
+
+/// Unit of measurement.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum MeasureUnit {
+    Kilometer,
+    Meter,
+    Centimeter,
+    Mile,
+    Yard,
+    Foot,
+    Inch,
+    KilometerPerHour,
+    MilePerHour,
+    MeterPerSecond,
+    Celsius,
+    Fahrenheit,
+    Kilogram,
+    Gram,
+    Pound,
+    Ounce,
+    Stone,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl MeasureUnit {
+    /// Unit identifier, as in CLDR, such as `"kilometer-per-hour"`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            MeasureUnit::Kilometer => "kilometer",
+            MeasureUnit::Meter => "meter",
+            MeasureUnit::Centimeter => "centimeter",
+            MeasureUnit::Mile => "mile",
+            MeasureUnit::Yard => "yard",
+            MeasureUnit::Foot => "foot",
+            MeasureUnit::Inch => "inch",
+            MeasureUnit::KilometerPerHour => "kilometer-per-hour",
+            MeasureUnit::MilePerHour => "mile-per-hour",
+            MeasureUnit::MeterPerSecond => "meter-per-second",
+            MeasureUnit::Celsius => "celsius",
+            MeasureUnit::Fahrenheit => "fahrenheit",
+            MeasureUnit::Kilogram => "kilogram",
+            MeasureUnit::Gram => "gram",
+            MeasureUnit::Pound => "pound",
+            MeasureUnit::Ounce => "ounce",
+            MeasureUnit::Stone => "stone",
+            MeasureUnit::Day => "day",
+            MeasureUnit::Hour => "hour",
+            MeasureUnit::Minute => "minute",
+            MeasureUnit::Second => "second",
+        }
+    }
+
+    pub fn quantity(&self) -> MeasureQuantity {
+        match self {
+            MeasureUnit::Kilometer | MeasureUnit::Meter | MeasureUnit::Centimeter
+            | MeasureUnit::Mile | MeasureUnit::Yard | MeasureUnit::Foot | MeasureUnit::Inch => MeasureQuantity::Length,
+            MeasureUnit::KilometerPerHour | MeasureUnit::MilePerHour | MeasureUnit::MeterPerSecond => MeasureQuantity::Speed,
+            MeasureUnit::Celsius | MeasureUnit::Fahrenheit => MeasureQuantity::Temperature,
+            MeasureUnit::Kilogram | MeasureUnit::Gram | MeasureUnit::Pound | MeasureUnit::Ounce | MeasureUnit::Stone => MeasureQuantity::Mass,
+            MeasureUnit::Day | MeasureUnit::Hour | MeasureUnit::Minute | MeasureUnit::Second => MeasureQuantity::Duration,
+        }
+    }
+
+    /// Factor to the base unit of the quantity: meter, meter per second, kilogram, second.
+    /// Temperatures are not proportional and are converted separately.
+    fn factor(&self) -> f64 {
+        match self {
+            MeasureUnit::Kilometer => 1000.0,
+            MeasureUnit::Meter => 1.0,
+            MeasureUnit::Centimeter => 0.01,
+            MeasureUnit::Mile => 1609.344,
+            MeasureUnit::Yard => 0.9144,
+            MeasureUnit::Foot => 0.3048,
+            MeasureUnit::Inch => 0.0254,
+            MeasureUnit::KilometerPerHour => 1.0 / 3.6,
+            MeasureUnit::MilePerHour => 0.44704,
+            MeasureUnit::MeterPerSecond => 1.0,
+            MeasureUnit::Celsius | MeasureUnit::Fahrenheit => 1.0,
+            MeasureUnit::Kilogram => 1.0,
+            MeasureUnit::Gram => 0.001,
+            MeasureUnit::Pound => 0.45359237,
+            MeasureUnit::Ounce => 0.028349523125,
+            MeasureUnit::Stone => 6.35029318,
+            MeasureUnit::Day => 86400.0,
+            MeasureUnit::Hour => 3600.0,
+            MeasureUnit::Minute => 60.0,
+            MeasureUnit::Second => 1.0,
+        }
+    }
+
+    /// Converts a value from this unit to another unit of the same quantity,
+    /// or returns `None` if the quantities differ.
+    pub fn convert(&self, value: f64, to: MeasureUnit) -> Option<f64> {
+        if self.quantity() != to.quantity() {
+            return None;
+        }
+        if *self == to {
+            return Some(value);
+        }
+        Some(match (self, to) {
+            (MeasureUnit::Celsius, MeasureUnit::Fahrenheit) => value * 9.0 / 5.0 + 32.0,
+            (MeasureUnit::Fahrenheit, MeasureUnit::Celsius) => (value - 32.0) * 5.0 / 9.0,
+            _ => value * self.factor() / to.factor(),
+        })
+    }
+
+    /// Returns the unit of similar scale customarily used in a measurement system,
+    /// such as miles for kilometers in the United States.
+    pub fn preferred_for(&self, system: MeasurementSystem) -> MeasureUnit {
+        match system {
+            MeasurementSystem::Metric => match self {
+                MeasureUnit::Mile => MeasureUnit::Kilometer,
+                MeasureUnit::Yard | MeasureUnit::Foot => MeasureUnit::Meter,
+                MeasureUnit::Inch => MeasureUnit::Centimeter,
+                MeasureUnit::MilePerHour => MeasureUnit::KilometerPerHour,
+                MeasureUnit::Fahrenheit => MeasureUnit::Celsius,
+                MeasureUnit::Pound | MeasureUnit::Stone => MeasureUnit::Kilogram,
+                MeasureUnit::Ounce => MeasureUnit::Gram,
+                unit => *unit,
+            },
+            MeasurementSystem::Uk => match self {
+                MeasureUnit::Kilometer | MeasureUnit::Mile => MeasureUnit::Mile,
+                MeasureUnit::KilometerPerHour | MeasureUnit::MilePerHour => MeasureUnit::MilePerHour,
+                unit => unit.preferred_for(MeasurementSystem::Metric),
+            },
+            MeasurementSystem::Us => match self {
+                MeasureUnit::Kilometer => MeasureUnit::Mile,
+                MeasureUnit::Meter => MeasureUnit::Foot,
+                MeasureUnit::Centimeter => MeasureUnit::Inch,
+                MeasureUnit::KilometerPerHour => MeasureUnit::MilePerHour,
+                MeasureUnit::Celsius => MeasureUnit::Fahrenheit,
+                MeasureUnit::Kilogram | MeasureUnit::Stone => MeasureUnit::Pound,
+                MeasureUnit::Gram => MeasureUnit::Ounce,
+                unit => *unit,
+            },
+        }
+    }
+}
+
+/// Width of a formatted unit.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum UnitWidth {
+    /// Such as "12 kilometers per hour".
+    Long,
+    /// Such as "12 km/h".
+    Short,
+    /// Such as "12km/h".
+    Narrow,
+}
+
+impl UnitWidth {
+    fn id(&self) -> &'static str {
+        match self {
+            UnitWidth::Long => "long",
+            UnitWidth::Short => "short",
+            UnitWidth::Narrow => "narrow",
+        }
+    }
+}
+
+/// Formats measures, such as "12 km/h", "7.5 mi" or "21 °C", converting them
+/// to the measurement system of the language's region.
+///
+/// Unit names are available for English, Portuguese, Spanish, French and German;
+/// other languages use the English names with their own number format.
+#[derive(Clone)]
+pub struct MeasureFormatter {
+    m_language_code: String,
+    m_list_formatter: ListFormatter,
+    m_number_formatter: NumberFormatter,
+    /// Plural rules of the language of the unit names.
+    m_plural_rules: PluralRules,
+    m_width: UnitWidth,
+    m_measurement_system: MeasurementSystem,
+}
+
+impl MeasureFormatter {
+    pub fn new(language: &Language, options: &MeasureFormatOptions) -> Self {
+        let number_options = NumberFormatOptions::new();
+        number_options.maximum_fraction_digits(options.m_maximum_fraction_digits.get());
+        let has_unit_names = UNIT_DATA.contains_key(&language.language_code());
+        let list_options = ListFormatOptions::new();
+        list_options.style(ListStyle::Unit);
+        Self {
+            m_language_code: if has_unit_names { language.language_code() } else { "en".to_owned() },
+            m_list_formatter: ListFormatter::new(language, &list_options),
+            m_number_formatter: NumberFormatter::new(language, &number_options),
+            m_plural_rules: if has_unit_names { PluralRules::new(language) } else { PluralRules::new(&Language::parse("en").unwrap()) },
+            m_width: options.m_width.get(),
+            m_measurement_system: options.m_measurement_system.get().unwrap_or_else(|| language.region().measurement_system()),
+        }
+    }
+
+    /// Formats a value in the unit preferred by the measurement system,
+    /// converting it if necessary. For example, `(20.0, MeasureUnit::Kilometer)`
+    /// is formatted as "12.4 mi" in the United States.
+    pub fn format(&self, value: f64, unit: MeasureUnit) -> String {
+        let preferred = unit.preferred_for(self.m_measurement_system);
+        self.format_without_conversion(unit.convert(value, preferred).unwrap_or(value), preferred)
+    }
+
+    /// Formats a value in the given unit, regardless of the measurement system.
+    pub fn format_without_conversion(&self, value: f64, unit: MeasureUnit) -> String {
+        let number = self.m_number_formatter.format(value);
+        let category = self.m_plural_rules.select_decimal(&self.m_number_formatter.format_digits(value));
+        let patterns = UNIT_DATA.get(&self.m_language_code)
+            .and_then(|units| units.get(unit.id()))
+            .and_then(|widths| widths.get(self.m_width.id()));
+        let pattern = patterns.and_then(|p| p.get(category.as_str()).or_else(|| p.get("other")));
+        match pattern {
+            Some(pattern) => pattern.replace("{0}", &number),
+            None => number + " " + unit.id(),
+        }
+    }
+
+    /// Formats a duration in days, hours, minutes and seconds, such as
+    /// "1 hr, 5 min" or "1 hour, 5 minutes", omitting the units that are zero.
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let mut seconds = duration.as_secs();
+        let mut items: Vec<String> = vec![];
+        for unit in [MeasureUnit::Day, MeasureUnit::Hour, MeasureUnit::Minute] {
+            let factor = unit.factor() as u64;
+            if seconds >= factor {
+                items.push(self.format_without_conversion((seconds / factor) as f64, unit));
+                seconds %= factor;
+            }
+        }
+        if seconds > 0 || items.is_empty() {
+            items.push(self.format_without_conversion(seconds as f64, MeasureUnit::Second));
+        }
+        self.m_list_formatter.format(&items)
+    }
+}
+
+pub struct MeasureFormatOptions {
+    m_width: Cell<UnitWidth>,
+    m_maximum_fraction_digits: Cell<usize>,
+    m_measurement_system: Cell<Option<MeasurementSystem>>,
+}
+
+impl Clone for MeasureFormatOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_width: self.m_width.clone(),
+            m_maximum_fraction_digits: self.m_maximum_fraction_digits.clone(),
+            m_measurement_system: self.m_measurement_system.clone(),
+        }
+    }
+}
+
+impl MeasureFormatOptions {
+    pub fn new() -> Self {
+        MeasureFormatOptions {
+            m_width: Cell::new(UnitWidth::Short),
+            m_maximum_fraction_digits: Cell::new(1),
+            m_measurement_system: Cell::new(None),
+        }
+    }
+
+    pub fn width(&self, value: UnitWidth) -> &Self {
+        self.m_width.set(value);
+        self
+    }
+
+    pub fn maximum_fraction_digits(&self, value: usize) -> &Self {
+        self.m_maximum_fraction_digits.set(value);
+        self
+    }
+
+    /// Overrides the measurement system of the language's region,
+    /// as from a user setting.
+    pub fn measurement_system(&self, value: MeasurementSystem) -> &Self {
+        self.m_measurement_system.set(Some(value));
+        self
+    }
+}
+
+impl Default for MeasureFormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Region;
+
+    fn formatter(tag: &str, options: &MeasureFormatOptions) -> MeasureFormatter {
+        MeasureFormatter::new(&Language::parse(tag).unwrap(), options)
+    }
+
+    #[test]
+    fn measurement_system() {
+        assert_eq!(Region::parse("US").unwrap().measurement_system(), MeasurementSystem::Us);
+        assert_eq!(Region::parse("GB").unwrap().measurement_system(), MeasurementSystem::Uk);
+        assert_eq!(Region::parse("BR").unwrap().measurement_system(), MeasurementSystem::Metric);
+
+        assert_eq!(MeasureUnit::Kilometer.preferred_for(MeasurementSystem::Us), MeasureUnit::Mile);
+        assert_eq!(MeasureUnit::Kilometer.preferred_for(MeasurementSystem::Uk), MeasureUnit::Mile);
+        assert_eq!(MeasureUnit::Meter.preferred_for(MeasurementSystem::Uk), MeasureUnit::Meter);
+        assert_eq!(MeasureUnit::Stone.preferred_for(MeasurementSystem::Uk), MeasureUnit::Kilogram);
+        assert_eq!(MeasureUnit::Inch.preferred_for(MeasurementSystem::Metric), MeasureUnit::Centimeter);
+    }
+
+    #[test]
+    fn convert() {
+        assert!((MeasureUnit::Mile.convert(1.0, MeasureUnit::Kilometer).unwrap() - 1.609344).abs() < 1e-9);
+        assert!((MeasureUnit::Fahrenheit.convert(212.0, MeasureUnit::Celsius).unwrap() - 100.0).abs() < 1e-9);
+        assert!((MeasureUnit::Celsius.convert(-40.0, MeasureUnit::Fahrenheit).unwrap() + 40.0).abs() < 1e-9);
+        assert_eq!(MeasureUnit::Celsius.convert(1.0, MeasureUnit::Meter), None);
+    }
+
+    #[test]
+    fn format() {
+        let options = MeasureFormatOptions::new();
+        assert_eq!(formatter("en-US", &options).format(20.0, MeasureUnit::Kilometer), "12.4 mi");
+        assert_eq!(formatter("en-US", &options).format(100.0, MeasureUnit::Celsius), "212°F");
+        assert_eq!(formatter("en-GB", &options).format(100.0, MeasureUnit::KilometerPerHour), "62.1 mph");
+        assert_eq!(formatter("en-GB", &options).format(5.0, MeasureUnit::Kilogram), "5 kg");
+        assert_eq!(formatter("pt-BR", &options).format(10.0, MeasureUnit::Mile), "16,1 km");
+        // languages without unit names use the English names
+        assert_eq!(formatter("ja-JP", &options).format(1234.5, MeasureUnit::Meter), "1,234.5 m");
+
+        options.measurement_system(MeasurementSystem::Metric);
+        assert_eq!(formatter("en-US", &options).format(20.0, MeasureUnit::Kilometer), "20 km");
+    }
+
+    #[test]
+    fn format_long() {
+        let options = MeasureFormatOptions::new();
+        options.width(UnitWidth::Long);
+        let en = formatter("en-US", &options);
+        assert_eq!(en.format_without_conversion(1.0, MeasureUnit::Kilometer), "1 kilometer");
+        assert_eq!(en.format_without_conversion(1.5, MeasureUnit::Kilometer), "1.5 kilometers");
+        assert_eq!(formatter("pt-BR", &options).format(0.0, MeasureUnit::Kilometer), "0 quilômetro");
+        assert_eq!(formatter("fr-FR", &options).format(1.5, MeasureUnit::Kilometer), "1,5 kilomètre");
+    }
+}
@@ -0,0 +1,174 @@
+//! Parsing of message strings stored in locale resources.
+
+use lazy_regex::regex;
+
+/// A piece of a message string.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum MessagePart {
+    /// Literal text.
+    Text(String),
+    /// A `$name` variable, with an optional format such as `spellout`
+    /// in `$name:spellout`, `ordinal:feminine` in `$name:ordinal:feminine`
+    /// or `date:short` in `$name:date:short`.
+    Variable { name: String, format: Option<String> },
+    /// A `$ref(id)` or `$ref(id, variant)` reference to another message.
+    Reference { id: String, variant: Option<String> },
+    /// A markup start tag, such as `<link id="x">`.
+    StartTag { name: String, attributes: Vec<(String, String)> },
+    /// A markup end tag, such as `</link>`.
+    EndTag { name: String },
+    /// A markup element without content, such as `<icon name="coin"/>`.
+    EmptyTag { name: String, attributes: Vec<(String, String)> },
+}
+
+/// Splits a message string into its literal and substituted parts and its markup.
+/// A `<` that does not start a tag is literal text, as is `$<`.
+pub(crate) fn parse_message(message: &str) -> Vec<MessagePart> {
+    let mut r: Vec<MessagePart> = vec![];
+    let mut last = 0;
+    for m in regex!(r#"\$(\$|<|ref\(\s*([^,)\s]+)\s*(?:,\s*([^)\s]+)\s*)?\)|([A-Za-z0-9]+)(?::((?:spellout-ordinal|spellout|ordinal|roman)(?::(?:masculine|feminine))?|number|integer|percent|currency|(?:datetime|date|time)(?::(?:full|long|medium|short))?|duration(?::(?:long|short|narrow))?|list(?::(?:and|or|unit))?))?)|<(/)?([A-Za-z][A-Za-z0-9\-]*)((?:\s+[A-Za-z][A-Za-z0-9\-]*="[^"]*")*)\s*(/)?>"#).captures_iter(message) {
+        let whole = m.get(0).unwrap();
+        push_text(&mut r, &message[last..whole.start()]);
+        last = whole.end();
+        if whole.as_str() == "$$" || whole.as_str() == "$<" {
+            push_text(&mut r, &whole.as_str()[1..]);
+        } else if let Some(name) = m.get(7) {
+            let name = name.as_str().to_owned();
+            let attributes = parse_attributes(m.get(8).map(|a| a.as_str()).unwrap_or(""));
+            r.push(if m.get(6).is_some() {
+                MessagePart::EndTag { name }
+            } else if m.get(9).is_some() {
+                MessagePart::EmptyTag { name, attributes }
+            } else {
+                MessagePart::StartTag { name, attributes }
+            });
+        } else if let Some(id) = m.get(2) {
+            r.push(MessagePart::Reference {
+                id: id.as_str().to_owned(),
+                variant: m.get(3).map(|v| v.as_str().to_owned()),
+            });
+        } else {
+            r.push(MessagePart::Variable {
+                name: m.get(4).unwrap().as_str().to_owned(),
+                format: m.get(5).map(|f| f.as_str().to_owned()),
+            });
+        }
+    }
+    push_text(&mut r, &message[last..]);
+    r
+}
+
+/// Parses markup attributes, such as `id="x" name="coin"`.
+pub(crate) fn parse_attributes(text: &str) -> Vec<(String, String)> {
+    regex!(r#"([A-Za-z][A-Za-z0-9\-]*)="([^"]*)""#).captures_iter(text)
+        .map(|m| (m.get(1).unwrap().as_str().to_owned(), m.get(2).unwrap().as_str().to_owned()))
+        .collect()
+}
+
+/// Formats markup attributes as parsed by `parse_attributes`.
+pub(crate) fn format_attributes(attributes: &[(String, String)]) -> String {
+    attributes.iter().map(|(k, v)| format!("{}=\"{}\"", k, v)).collect::<Vec<String>>().join(" ")
+}
+
+/// Checks that the elements of a message with the given markup tags are closed
+/// in order, returning a description of the first problem. Other tags are text.
+pub(crate) fn check_markup(parts: &[MessagePart], tags: &[String]) -> Option<String> {
+    let mut open: Vec<&String> = vec![];
+    for part in parts {
+        match part {
+            MessagePart::StartTag { name, .. } | MessagePart::EndTag { name } | MessagePart::EmptyTag { name, .. } if !tags.contains(name) => {},
+            MessagePart::StartTag { name, .. } => open.push(name),
+            MessagePart::EndTag { name } if open.last() == Some(&name) => { open.pop(); },
+            MessagePart::EndTag { name } => return Some(format!("unexpected </{}>", name)),
+            _ => {},
+        }
+    }
+    open.last().map(|name| format!("unclosed <{}>", name))
+}
+
+/// Turns the tags that are not among the given markup tags into text,
+/// so that text such as `<Enter>` or `<br>` is kept.
+pub(crate) fn unknown_tags_as_text(parts: Vec<MessagePart>, tags: &[String]) -> Vec<MessagePart> {
+    let mut r: Vec<MessagePart> = vec![];
+    for part in parts {
+        match part {
+            MessagePart::Text(s) => push_text(&mut r, &s),
+            MessagePart::StartTag { name, attributes } if !tags.contains(&name) => push_text(&mut r, &format_tag(&name, &attributes, "")),
+            MessagePart::EndTag { name } if !tags.contains(&name) => push_text(&mut r, &format!("</{}>", name)),
+            MessagePart::EmptyTag { name, attributes } if !tags.contains(&name) => push_text(&mut r, &format_tag(&name, &attributes, "/")),
+            part => r.push(part),
+        }
+    }
+    r
+}
+
+fn format_tag(name: &str, attributes: &[(String, String)], end: &str) -> String {
+    if attributes.is_empty() {
+        return format!("<{}{}>", name, end);
+    }
+    format!("<{} {}{}>", name, format_attributes(attributes), end)
+}
+
+fn push_text(parts: &mut Vec<MessagePart>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(MessagePart::Text(last)) = parts.last_mut() {
+        last.push_str(text);
+        return;
+    }
+    parts.push(MessagePart::Text(text.to_owned()));
+}
+
+/// Returns the identifiers tried, in order, when resolving a reference
+/// to `id` with an optional grammatical variant, such as a case.
+pub(crate) fn reference_candidates(id: &str, variant: &Option<String>) -> Vec<String> {
+    match variant {
+        Some(v) => vec![format!("{}_{}", id, v), id.to_owned()],
+        None => vec![id.to_owned()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> MessagePart {
+        MessagePart::Text(s.to_owned())
+    }
+
+    fn tags() -> Vec<String> {
+        vec!["b".to_owned(), "icon".to_owned()]
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_message("Costs $$5, a < b, $<b> and $<"), [text("Costs $5, a < b, <b> and <")]);
+        assert_eq!(parse_message("<b>$name</b><icon name=\"coin\"/>"), [
+            MessagePart::StartTag { name: "b".to_owned(), attributes: vec![] },
+            MessagePart::Variable { name: "name".to_owned(), format: None },
+            MessagePart::EndTag { name: "b".to_owned() },
+            MessagePart::EmptyTag { name: "icon".to_owned(), attributes: vec![("name".to_owned(), "coin".to_owned())] },
+        ]);
+    }
+
+    #[test]
+    fn markup() {
+        assert_eq!(check_markup(&parse_message("<b>bold <icon/></b>"), &tags()), None);
+        assert_eq!(check_markup(&parse_message("<b>bold"), &tags()), Some("unclosed <b>".to_owned()));
+        assert_eq!(check_markup(&parse_message("bold</b>"), &tags()), Some("unexpected </b>".to_owned()));
+        // other tags are text
+        assert_eq!(check_markup(&parse_message("Press <Enter> or <br> to <b>continue</b></i>"), &tags()), None);
+    }
+
+    #[test]
+    fn unknown_tags() {
+        let parts = unknown_tags_as_text(parse_message("Press <Enter>, <key id=\"x\"/> or </br> to <b>continue</b>"), &tags());
+        assert_eq!(parts, [
+            text("Press <Enter>, <key id=\"x\"/> or </br> to "),
+            MessagePart::StartTag { name: "b".to_owned(), attributes: vec![] },
+            text("continue"),
+            MessagePart::EndTag { name: "b".to_owned() },
+        ]);
+    }
+}
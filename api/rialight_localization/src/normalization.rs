@@ -0,0 +1,37 @@
+use unicode_normalization::UnicodeNormalization;
+use rialight_util::AnyStringType;
+
+/// Unicode normalization form.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum NormalizationForm {
+    /// Canonical decomposition followed by canonical composition.
+    Nfc,
+    /// Canonical decomposition.
+    Nfd,
+    /// Compatibility decomposition followed by canonical composition.
+    Nfkc,
+    /// Compatibility decomposition.
+    Nfkd,
+}
+
+/// Normalizes text to the given Unicode normalization form.
+pub fn normalize(text: impl AnyStringType, form: NormalizationForm) -> String {
+    let text = text.convert();
+    match form {
+        NormalizationForm::Nfc => text.nfc().collect(),
+        NormalizationForm::Nfd => text.nfd().collect(),
+        NormalizationForm::Nfkc => text.nfkc().collect(),
+        NormalizationForm::Nfkd => text.nfkd().collect(),
+    }
+}
+
+/// Returns `true` if the text is already in the given Unicode normalization form, otherwise `false`.
+pub fn is_normalized(text: impl AnyStringType, form: NormalizationForm) -> bool {
+    let text = text.convert();
+    match form {
+        NormalizationForm::Nfc => unicode_normalization::is_nfc(text),
+        NormalizationForm::Nfd => unicode_normalization::is_nfd(text),
+        NormalizationForm::Nfkc => unicode_normalization::is_nfkc(text),
+        NormalizationForm::Nfkd => unicode_normalization::is_nfkd(text),
+    }
+}
@@ -0,0 +1,537 @@
+use std::{cell::Cell, collections::HashMap};
+use serde::{Deserialize, Serialize};
+use super::{Language, PluralCategory, PluralRules, CurrencyAmount, ParseError, ParseErrorKind, ParseMode};
+use super::currency::{find_currencies, currency_for_region, currency_symbol, currency_fraction_digits, is_currency_symbol_char};
+use super::parsing::{is_space, is_minus, trimmed_range, skip_spaces};
+
+lazy_static! {
+    static ref NUMBER_DATA: HashMap<String, NumberData> = {
+        serde_json::from_str::<HashMap<String, NumberData>>(include_str!("../data/number.json")).unwrap()
+    };
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct NumberData {
+    /// Decimal separator
+    d: String,
+    /// Group separator
+    g: String,
+    /// Minimum integer digits before grouping is used
+    m: usize,
+    /// Percent pattern, where `#` is the number
+    #[serde(default = "default_percent_pattern")]
+    p: String,
+    /// Currency pattern, where `#` is the number and `¤` is the currency symbol
+    #[serde(default = "default_currency_pattern")]
+    c: String,
+}
+
+fn default_percent_pattern() -> String {
+    "#%".to_owned()
+}
+
+fn default_currency_pattern() -> String {
+    "¤#".to_owned()
+}
+
+/// Formats numbers with the decimal and group separators of a language.
+#[derive(Clone)]
+pub struct NumberFormatter {
+    m_data: NumberData,
+    m_region: String,
+    m_plural_rules: PluralRules,
+    m_minimum_fraction_digits: usize,
+    m_maximum_fraction_digits: usize,
+    m_grouping: bool,
+}
+
+impl NumberFormatter {
+    pub fn new(language: &Language, options: &NumberFormatOptions) -> Self {
+        let data = NUMBER_DATA.get(&language.tag().to_ascii_lowercase())
+            .or_else(|| NUMBER_DATA.get(&language.language_code()))
+            .unwrap_or_else(|| NUMBER_DATA.get("en").unwrap());
+        let maximum_fraction_digits = options.m_maximum_fraction_digits.get();
+        Self {
+            m_data: data.clone(),
+            m_region: language.region().id().to_ascii_uppercase(),
+            m_plural_rules: PluralRules::new(language),
+            m_minimum_fraction_digits: options.m_minimum_fraction_digits.get().min(maximum_fraction_digits),
+            m_maximum_fraction_digits: maximum_fraction_digits,
+            m_grouping: options.m_grouping.get(),
+        }
+    }
+
+    /// Formats a number.
+    pub fn format(&self, n: f64) -> String {
+        self.localize(&self.format_digits(n))
+    }
+
+    /// Formats an integer exactly, without the precision loss of `f64`.
+    pub(crate) fn format_integer(&self, n: i64) -> String {
+        self.localize(&n.to_string())
+    }
+
+    /// Formats a fraction as a percentage, such as `0.5` as "50 %" in French.
+    pub fn format_percent(&self, n: f64) -> String {
+        let digits = self.format_digits(n * 100.0);
+        let (sign, digits) = if let Some(d) = digits.strip_prefix('-') { ("-", d) } else { ("", digits.as_str()) };
+        sign.to_owned() + &self.m_data.p.replace('#', &self.localize(digits))
+    }
+
+    /// Formats an amount of money with the fraction digits of its currency,
+    /// such as "R$ 1.234,50" in Brazilian Portuguese. The currency is written
+    /// with its local symbol in the language's region and its international
+    /// symbol elsewhere, such as "US$" outside the United States.
+    pub fn format_currency(&self, amount: &CurrencyAmount) -> String {
+        let mut formatter = self.clone();
+        formatter.m_minimum_fraction_digits = currency_fraction_digits(&amount.currency);
+        formatter.m_maximum_fraction_digits = formatter.m_minimum_fraction_digits;
+        let digits = formatter.format_digits(amount.amount);
+        let (sign, digits) = if let Some(d) = digits.strip_prefix('-') { ("-", d) } else { ("", digits.as_str()) };
+        let symbol = currency_symbol(&amount.currency, &self.m_region);
+        let mut pattern = self.m_data.c.clone();
+        // ISO codes are separated from the number by a space
+        if symbol.len() == 3 && symbol.chars().all(|c| c.is_ascii_uppercase()) {
+            pattern = pattern.replace("¤#", "¤\u{a0}#").replace("#¤", "#\u{a0}¤");
+        }
+        sign.to_owned() + &pattern.replace('¤', &symbol).replace('#', &formatter.localize(digits))
+    }
+
+    /// Selects the plural category of a number as it is formatted,
+    /// so that, for example, `1.0` is plural when one fraction digit is shown.
+    pub fn plural_category(&self, n: f64) -> PluralCategory {
+        self.m_plural_rules.select_decimal(&self.format_digits(n))
+    }
+
+    /// Rounds a number to ASCII digits, such as `"-1234.5"`.
+    pub(crate) fn format_digits(&self, n: f64) -> String {
+        if !n.is_finite() {
+            return n.to_string();
+        }
+        let mut r = format!("{:.*}", self.m_maximum_fraction_digits, n);
+        if let Some(point) = r.find('.') {
+            let minimum_len = point + 1 + self.m_minimum_fraction_digits;
+            while r.len() > minimum_len && r.ends_with('0') {
+                r.pop();
+            }
+            if r.ends_with('.') {
+                r.pop();
+            }
+        }
+        if r.trim_start_matches('-').chars().all(|c| c == '0' || c == '.') {
+            r = r.trim_start_matches('-').to_owned();
+        }
+        r
+    }
+
+    /// Parses a number as formatted in the language, such as "1.234,5" in German.
+    pub fn parse(&self, text: &str, mode: ParseMode) -> Result<f64, ParseError> {
+        let chars: Vec<char> = text.chars().collect();
+        let (start, end) = trimmed_range(&chars, mode)?;
+        let (n, i) = self.parse_number_at(&chars, start, end, mode, true)?;
+        if i < end {
+            return Err(ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i])));
+        }
+        Ok(n)
+    }
+
+    /// Parses a percentage, such as "50 %" in French, returning it as a fraction,
+    /// such as `0.5`. In lenient mode the percent sign may be omitted.
+    pub fn parse_percent(&self, text: &str, mode: ParseMode) -> Result<f64, ParseError> {
+        let chars: Vec<char> = text.chars().collect();
+        let (start, end) = trimmed_range(&chars, mode)?;
+        let pattern: Vec<char> = match mode {
+            ParseMode::Strict => self.m_data.p.chars().collect(),
+            ParseMode::Lenient => vec![],
+        };
+        let mut i = start;
+        let negative = i < end && is_minus(chars[i]) && (mode == ParseMode::Lenient || chars[i] == '-');
+        if negative {
+            i += 1;
+        }
+        let mut n: Option<f64> = None;
+        if mode == ParseMode::Strict {
+            for p in pattern {
+                match p {
+                    '#' => {
+                        let (value, next) = self.parse_number_at(&chars, i, end, mode, false)?;
+                        n = Some(value);
+                        i = next;
+                    },
+                    '%' if i < end && chars[i] == '%' => i += 1,
+                    '%' => return Err(ParseError::new(i, ParseErrorKind::MissingPercentSign)),
+                    _ if i < end && is_space(p) && is_space(chars[i]) => i += 1,
+                    _ if i < end && chars[i] == p => i += 1,
+                    _ if i < end => return Err(ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i]))),
+                    _ => return Err(ParseError::new(i, ParseErrorKind::UnexpectedEnd)),
+                }
+            }
+        } else {
+            let is_percent = |c: char| c == '%' || c == '\u{066a}' || c == '\u{ff05}';
+            if i < end && is_percent(chars[i]) {
+                i = skip_spaces(&chars, i + 1, end);
+            }
+            let (value, next) = self.parse_number_at(&chars, i, end, mode, !negative)?;
+            n = Some(value);
+            i = skip_spaces(&chars, next, end);
+            if i < end && is_percent(chars[i]) {
+                i += 1;
+            }
+        }
+        if i < end {
+            return Err(ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i])));
+        }
+        let n = n.unwrap() / 100.0;
+        Ok(if negative { -n } else { n })
+    }
+
+    /// Parses an amount of money, such as "R$ 1.234,50" in Brazilian Portuguese,
+    /// with a currency symbol or ISO 4217 code. In lenient mode the symbol may be
+    /// on either side of the number or omitted for the currency of the language's region.
+    pub fn parse_currency(&self, text: &str, mode: ParseMode) -> Result<CurrencyAmount, ParseError> {
+        let chars: Vec<char> = text.chars().collect();
+        let (start, end) = trimmed_range(&chars, mode)?;
+        let mut i = start;
+        let mut negative = i < end && is_minus(chars[i]) && (mode == ParseMode::Lenient || chars[i] == '-');
+        if negative {
+            i += 1;
+        }
+        let mut amount: Option<f64> = None;
+        let mut symbol: Option<(usize, String)> = None;
+        if mode == ParseMode::Strict {
+            let pattern: Vec<char> = self.m_data.c.chars().collect();
+            for (k, p) in pattern.iter().enumerate() {
+                match p {
+                    '#' => {
+                        let (value, next) = self.parse_number_at(&chars, i, end, mode, false)?;
+                        amount = Some(value);
+                        i = next;
+                    },
+                    '¤' => {
+                        let length = chars[i..end].iter().take_while(|c| is_currency_symbol_char(**c)).count();
+                        if length == 0 {
+                            return Err(ParseError::new(i, ParseErrorKind::MissingCurrency));
+                        }
+                        let s: String = chars[i..i + length].iter().collect();
+                        let is_code = s.len() == 3 && s.chars().all(|c| c.is_ascii_uppercase());
+                        symbol = Some((i, s));
+                        i += length;
+                        // ISO codes are separated from the number by a space
+                        if is_code && pattern.get(k + 1) == Some(&'#') && i < end && is_space(chars[i]) {
+                            i += 1;
+                        }
+                    },
+                    _ if i < end && is_space(*p) && is_space(chars[i]) => i += 1,
+                    _ if i < end && chars[i] == *p => i += 1,
+                    _ if i < end => return Err(ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i]))),
+                    _ => return Err(ParseError::new(i, ParseErrorKind::UnexpectedEnd)),
+                }
+            }
+        } else {
+            let symbol_length = chars[i..end].iter().take_while(|c| is_currency_symbol_char(**c)).count();
+            if symbol_length > 0 {
+                symbol = Some((i, chars[i..i + symbol_length].iter().collect()));
+                i = skip_spaces(&chars, i + symbol_length, end);
+            }
+            if !negative && i < end && is_minus(chars[i]) {
+                negative = true;
+                i += 1;
+            }
+            let (value, next) = self.parse_number_at(&chars, i, end, mode, false)?;
+            amount = Some(value);
+            i = skip_spaces(&chars, next, end);
+            let symbol_length = chars[i..end].iter().take_while(|c| is_currency_symbol_char(**c)).count();
+            if symbol.is_none() && symbol_length > 0 {
+                symbol = Some((i, chars[i..i + symbol_length].iter().collect()));
+                i += symbol_length;
+            }
+        }
+        if i < end {
+            return Err(ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i])));
+        }
+        let currency = match symbol {
+            Some((position, symbol)) => {
+                let mut currencies = find_currencies(&symbol, &self.m_region);
+                match currencies.len() {
+                    0 => return Err(ParseError::new(position, ParseErrorKind::UnknownCurrency(symbol))),
+                    1 => currencies.remove(0),
+                    _ => return Err(ParseError::new(position, ParseErrorKind::AmbiguousCurrency(symbol))),
+                }
+            },
+            None => currency_for_region(&self.m_region).ok_or_else(|| ParseError::new(start, ParseErrorKind::MissingCurrency))?,
+        };
+        let amount = amount.unwrap();
+        Ok(CurrencyAmount { amount: if negative { -amount } else { amount }, currency })
+    }
+
+    /// Parses a number starting at `start`, returning it with the offset after it.
+    fn parse_number_at(&self, chars: &[char], start: usize, end: usize, mode: ParseMode, signed: bool) -> Result<(f64, usize), ParseError> {
+        let lenient = mode == ParseMode::Lenient;
+        let decimal: Vec<char> = self.m_data.d.chars().collect();
+        let group: Vec<char> = self.m_data.g.chars().collect();
+        let (decimal, group) = (decimal[0], group[0]);
+        let is_group = |c: char| c == group || (lenient && ((is_space(group) && is_space(c)) || (group == '’' && c == '\'')));
+        // a period is a decimal separator in lenient mode if it cannot be a group separator
+        let is_decimal = |c: char| c == decimal || (lenient && c == '.' && !is_group('.'));
+
+        let mut digits = String::new();
+        let mut i = start;
+        if signed && i < end && (chars[i] == '-' || (lenient && (is_minus(chars[i]) || chars[i] == '+'))) {
+            if chars[i] != '+' {
+                digits.push('-');
+            }
+            i += 1;
+        }
+        let integer_start = i;
+        // digits of each group of the integer part and where each group separator is
+        let mut groups: Vec<(usize, usize)> = vec![(i, 0)];
+        while i < end {
+            let c = chars[i];
+            if c.is_ascii_digit() {
+                digits.push(c);
+                groups.last_mut().unwrap().1 += 1;
+            } else if is_group(c) && groups.last().unwrap().1 > 0 && i + 1 < end && chars[i + 1].is_ascii_digit() {
+                groups.push((i, 0));
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        if i == integer_start {
+            return Err(if i < end { ParseError::new(i, ParseErrorKind::UnexpectedCharacter(chars[i])) } else { ParseError::new(i, ParseErrorKind::UnexpectedEnd) });
+        }
+        let mut fraction = i < end && is_decimal(chars[i]);
+        // a single group separator not followed by three digits is a decimal
+        // separator in lenient mode, as in "1.5" typed in German
+        if lenient && !fraction && groups.len() == 2 && groups[1].1 != 3 && !(i < end && is_decimal(chars[i])) {
+            let (separator, length) = groups.pop().unwrap();
+            digits.insert(digits.len() - length, '.');
+            if chars[separator] == '.' || chars[separator] == ',' {
+                return self.finish_number(digits, start, i);
+            }
+            return Err(ParseError::new(separator, ParseErrorKind::InvalidGrouping));
+        }
+        if !lenient {
+            for (k, (separator, length)) in groups.iter().enumerate() {
+                if (k == 0 && groups.len() > 1 && *length > 3) || (k > 0 && *length != 3) {
+                    return Err(ParseError::new(if k == 0 { groups[1].0 } else { *separator }, ParseErrorKind::InvalidGrouping));
+                }
+            }
+        }
+        if fraction {
+            let fraction_start = i + 1;
+            let mut j = fraction_start;
+            while j < end && chars[j].is_ascii_digit() {
+                j += 1;
+            }
+            if j == fraction_start {
+                fraction = false;
+                if !lenient {
+                    return Err(if j < end { ParseError::new(j, ParseErrorKind::UnexpectedCharacter(chars[j])) } else { ParseError::new(j, ParseErrorKind::UnexpectedEnd) });
+                }
+            } else {
+                digits.push('.');
+                digits.extend(&chars[fraction_start..j]);
+                i = j;
+            }
+        }
+        if !fraction && lenient && i < end && is_decimal(chars[i]) {
+            // trailing decimal separator, as in "5."
+            i += 1;
+        }
+        self.finish_number(digits, start, i)
+    }
+
+    fn finish_number(&self, digits: String, start: usize, end: usize) -> Result<(f64, usize), ParseError> {
+        match digits.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok((n, end)),
+            _ => Err(ParseError::new(start, ParseErrorKind::Overflow)),
+        }
+    }
+
+    fn localize(&self, digits: &str) -> String {
+        let (sign, digits) = if let Some(d) = digits.strip_prefix('-') { ("-", d) } else { ("", digits) };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((i, f)) => (i, Some(f)),
+            None => (digits, None),
+        };
+        let mut r = sign.to_owned();
+        if self.m_grouping && integer.len() >= 3 + self.m_data.m && integer.chars().all(|c| c.is_ascii_digit()) {
+            for (i, c) in integer.chars().enumerate() {
+                if i > 0 && (integer.len() - i) % 3 == 0 {
+                    r.push_str(&self.m_data.g);
+                }
+                r.push(c);
+            }
+        } else {
+            r.push_str(integer);
+        }
+        if let Some(fraction) = fraction {
+            r.push_str(&self.m_data.d);
+            r.push_str(fraction);
+        }
+        r
+    }
+}
+
+pub struct NumberFormatOptions {
+    m_minimum_fraction_digits: Cell<usize>,
+    m_maximum_fraction_digits: Cell<usize>,
+    m_grouping: Cell<bool>,
+}
+
+impl Clone for NumberFormatOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_minimum_fraction_digits: self.m_minimum_fraction_digits.clone(),
+            m_maximum_fraction_digits: self.m_maximum_fraction_digits.clone(),
+            m_grouping: self.m_grouping.clone(),
+        }
+    }
+}
+
+impl NumberFormatOptions {
+    pub fn new() -> Self {
+        NumberFormatOptions {
+            m_minimum_fraction_digits: Cell::new(0),
+            m_maximum_fraction_digits: Cell::new(3),
+            m_grouping: Cell::new(true),
+        }
+    }
+
+    pub fn minimum_fraction_digits(&self, value: usize) -> &Self {
+        self.m_minimum_fraction_digits.set(value);
+        self
+    }
+
+    pub fn maximum_fraction_digits(&self, value: usize) -> &Self {
+        self.m_maximum_fraction_digits.set(value);
+        self
+    }
+
+    /// Whether to separate groups of thousands. Defaults to `true`.
+    pub fn grouping(&self, value: bool) -> &Self {
+        self.m_grouping.set(value);
+        self
+    }
+}
+
+impl Default for NumberFormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ParseMode::{Lenient, Strict};
+
+    fn formatter(tag: &str) -> NumberFormatter {
+        NumberFormatter::new(&Language::parse(tag).unwrap(), &NumberFormatOptions::new())
+    }
+
+    fn error(r: Result<impl std::fmt::Debug, ParseError>) -> (usize, ParseErrorKind) {
+        let e = r.unwrap_err();
+        (e.position(), e.kind())
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(formatter("en-US").format(1234567.891), "1,234,567.891");
+        assert_eq!(formatter("de-DE").format(-1234.5), "-1.234,5");
+        assert_eq!(formatter("pt-PT").format(1234.0), "1234");
+        assert_eq!(formatter("pt-PT").format(12345.0), "12\u{a0}345");
+        assert_eq!(formatter("en-US").format(-0.0001), "0");
+        assert_eq!(formatter("en-US").format_integer(i64::MIN), "-9,223,372,036,854,775,808");
+    }
+
+    #[test]
+    fn parse_strict() {
+        let en = formatter("en-US");
+        assert_eq!(en.parse("1,234.5", Strict), Ok(1234.5));
+        assert_eq!(en.parse("1234.5", Strict), Ok(1234.5));
+        assert_eq!(en.parse("-5", Strict), Ok(-5.0));
+        assert_eq!(formatter("de-DE").parse("1.234,5", Strict), Ok(1234.5));
+
+        assert_eq!(error(en.parse("", Strict)), (0, ParseErrorKind::Empty));
+        assert_eq!(error(en.parse(" 5", Strict)), (0, ParseErrorKind::UnexpectedCharacter(' ')));
+        assert_eq!(error(en.parse("1.5.2", Strict)), (3, ParseErrorKind::UnexpectedCharacter('.')));
+        assert_eq!(error(en.parse("5.", Strict)), (2, ParseErrorKind::UnexpectedEnd));
+        assert_eq!(error(en.parse("-", Strict)), (1, ParseErrorKind::UnexpectedEnd));
+        assert_eq!(error(en.parse("1,23", Strict)), (1, ParseErrorKind::InvalidGrouping));
+        assert_eq!(error(en.parse("1234,567", Strict)), (4, ParseErrorKind::InvalidGrouping));
+        assert_eq!(error(en.parse(&("1".to_owned() + &"0".repeat(400)), Strict)), (0, ParseErrorKind::Overflow));
+        // "1,5" is not a number in English
+        assert_eq!(error(en.parse("1,5", Strict)), (1, ParseErrorKind::InvalidGrouping));
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let en = formatter("en-US");
+        assert_eq!(en.parse(" 1,234 ", Lenient), Ok(1234.0));
+        assert_eq!(en.parse("1,5", Lenient), Ok(1.5));
+        assert_eq!(en.parse("1,2345", Lenient), Ok(1.2345));
+        assert_eq!(en.parse("1234,567", Lenient), Ok(1234567.0));
+        assert_eq!(en.parse("\u{2212}5", Lenient), Ok(-5.0));
+        assert_eq!(en.parse("+5", Lenient), Ok(5.0));
+        assert_eq!(en.parse("5.", Lenient), Ok(5.0));
+        assert_eq!(error(en.parse("   ", Lenient)), (3, ParseErrorKind::Empty));
+        assert_eq!(error(en.parse("5 apples", Lenient)), (1, ParseErrorKind::UnexpectedCharacter(' ')));
+
+        let de = formatter("de-DE");
+        assert_eq!(de.parse("1.5", Lenient), Ok(1.5));
+        assert_eq!(de.parse("1,5", Lenient), Ok(1.5));
+        assert_eq!(de.parse("1.234", Lenient), Ok(1234.0));
+        assert_eq!(formatter("fr-FR").parse("1 234,5", Lenient), Ok(1234.5));
+        assert_eq!(formatter("de-CH").parse("1'234.5", Lenient), Ok(1234.5));
+        // a space is not a group separator in Swiss German
+        assert_eq!(error(formatter("de-CH").parse("1 5", Lenient)), (1, ParseErrorKind::UnexpectedCharacter(' ')));
+    }
+
+    #[test]
+    fn parse_percent() {
+        let en = formatter("en-US");
+        assert_eq!(en.parse_percent("50%", Strict), Ok(0.5));
+        assert_eq!(en.parse_percent("-12.5%", Strict), Ok(-0.125));
+        assert_eq!(error(en.parse_percent("50", Strict)), (2, ParseErrorKind::MissingPercentSign));
+        assert_eq!(error(en.parse_percent("50 %", Strict)), (2, ParseErrorKind::MissingPercentSign));
+        assert_eq!(en.parse_percent("50", Lenient), Ok(0.5));
+        assert_eq!(en.parse_percent("% 50", Lenient), Ok(0.5));
+        assert_eq!(en.parse_percent(" 50 % ", Lenient), Ok(0.5));
+        assert_eq!(error(en.parse_percent("50%%", Lenient)), (3, ParseErrorKind::UnexpectedCharacter('%')));
+
+        let de = formatter("de-DE");
+        assert_eq!(de.parse_percent("12,5\u{a0}%", Strict), Ok(0.125));
+        assert_eq!(error(de.parse_percent("12,5%", Strict)), (4, ParseErrorKind::UnexpectedCharacter('%')));
+        assert_eq!(error(de.parse_percent("12,5", Strict)), (4, ParseErrorKind::UnexpectedEnd));
+        assert_eq!(de.parse_percent("12,5%", Lenient), Ok(0.125));
+    }
+
+    #[test]
+    fn parse_currency() {
+        let amount = |amount: f64, currency: &str| Ok(CurrencyAmount { amount, currency: currency.to_owned() });
+        let en = formatter("en-US");
+        assert_eq!(en.parse_currency("$1,234.50", Strict), amount(1234.5, "USD"));
+        assert_eq!(en.parse_currency("-€5", Strict), amount(-5.0, "EUR"));
+        assert_eq!(en.parse_currency("BRL 5", Strict), amount(5.0, "BRL"));
+        assert_eq!(error(en.parse_currency("5", Strict)), (0, ParseErrorKind::MissingCurrency));
+        assert_eq!(error(en.parse_currency("5$", Strict)), (0, ParseErrorKind::MissingCurrency));
+        assert_eq!(error(en.parse_currency("XYZ 5", Strict)), (0, ParseErrorKind::UnknownCurrency("XYZ".to_owned())));
+
+        assert_eq!(en.parse_currency("12", Lenient), amount(12.0, "USD"));
+        assert_eq!(en.parse_currency("5 €", Lenient), amount(5.0, "EUR"));
+        assert_eq!(en.parse_currency("$-5", Lenient), amount(-5.0, "USD"));
+        assert_eq!(en.parse_currency("usd 5", Lenient), amount(5.0, "USD"));
+
+        let pt = formatter("pt-BR");
+        assert_eq!(pt.parse_currency("R$\u{a0}1.234,50", Strict), amount(1234.5, "BRL"));
+        assert_eq!(pt.parse_currency("R$ 1.234,50", Strict), amount(1234.5, "BRL"));
+        assert_eq!(pt.parse_currency("1234,5 R$", Lenient), amount(1234.5, "BRL"));
+        assert_eq!(formatter("de-DE").parse_currency("1.234,50\u{a0}€", Strict), amount(1234.5, "EUR"));
+
+        // "$" is the local currency in the regions that use a dollar
+        assert_eq!(formatter("en-CA").parse_currency("$5", Lenient), amount(5.0, "CAD"));
+        assert_eq!(error(formatter("en-GB").parse_currency("$5", Lenient)), (0, ParseErrorKind::AmbiguousCurrency("$".to_owned())));
+    }
+}
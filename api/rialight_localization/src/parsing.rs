@@ -0,0 +1,93 @@
+/// How strictly user input is parsed.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum ParseMode {
+    /// Accepts only input as it would be formatted, such as "1.234,5" in German.
+    Strict,
+    /// Also accepts common variations of the format, such as missing or misplaced
+    /// group separators, any kind of space, a period as the decimal separator when
+    /// it cannot be a group separator, and month names in place of numbers.
+    Lenient,
+}
+
+/// Error from parsing user input, with the character offset where parsing failed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    m_position: usize,
+    m_kind: ParseErrorKind,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseErrorKind {
+    /// The input is empty.
+    Empty,
+    /// A character that is not part of the format.
+    UnexpectedCharacter(char),
+    /// The input ends before the format is complete.
+    UnexpectedEnd,
+    /// A group separator that is not followed by three digits.
+    InvalidGrouping,
+    /// The number is out of range.
+    Overflow,
+    /// The percent sign is missing.
+    MissingPercentSign,
+    /// The currency symbol or code is missing.
+    MissingCurrency,
+    /// The currency symbol or code is not known.
+    UnknownCurrency(String),
+    /// The currency symbol denotes several currencies, such as `"$"`
+    /// outside the regions that use a dollar.
+    AmbiguousCurrency(String),
+    /// A month, weekday, era or day period name that is not known.
+    UnknownName(String),
+    /// The date or time does not exist, such as February 30.
+    InvalidDate,
+}
+
+impl ParseError {
+    pub(crate) fn new(position: usize, kind: ParseErrorKind) -> Self {
+        Self { m_position: position, m_kind: kind }
+    }
+
+    /// Offset of the character where parsing failed, counted in characters.
+    pub fn position(&self) -> usize {
+        self.m_position
+    }
+
+    pub fn kind(&self) -> ParseErrorKind {
+        self.m_kind.clone()
+    }
+}
+
+/// Whether a character is a space, including the no-break spaces used in formats.
+pub(crate) fn is_space(c: char) -> bool {
+    c.is_whitespace() || c == '\u{a0}' || c == '\u{202f}'
+}
+
+/// Whether a character is a minus sign.
+pub(crate) fn is_minus(c: char) -> bool {
+    c == '-' || c == '\u{2212}'
+}
+
+/// Range of the input without surrounding spaces in lenient mode.
+pub(crate) fn trimmed_range(chars: &[char], mode: ParseMode) -> Result<(usize, usize), ParseError> {
+    let (mut start, mut end) = (0, chars.len());
+    if mode == ParseMode::Lenient {
+        while start < end && is_space(chars[start]) {
+            start += 1;
+        }
+        while end > start && is_space(chars[end - 1]) {
+            end -= 1;
+        }
+    }
+    if start == end {
+        return Err(ParseError::new(start, ParseErrorKind::Empty));
+    }
+    Ok((start, end))
+}
+
+pub(crate) fn skip_spaces(chars: &[char], mut i: usize, end: usize) -> usize {
+    while i < end && is_space(chars[i]) {
+        i += 1;
+    }
+    i
+}
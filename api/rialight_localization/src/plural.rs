@@ -0,0 +1,144 @@
+use super::Language;
+
+/// CLDR plural category.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    /// Category name as used in CLDR data, such as `"one"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum PluralRuleSet {
+    /// one: i = 1 and v = 0
+    OneWithoutFraction,
+    /// one: i = 0,1
+    ZeroOrOne,
+    /// one: n = 1
+    ExactlyOne,
+    /// no plural forms
+    Other,
+    /// Russian and Ukrainian
+    EastSlavic,
+    Polish,
+    /// Czech and Slovak
+    WestSlavic,
+    Arabic,
+}
+
+/// Selects the CLDR cardinal plural category of a number for a language.
+#[derive(Copy, Clone, Debug)]
+pub struct PluralRules {
+    m_rule_set: PluralRuleSet,
+}
+
+impl PluralRules {
+    /// Constructs `PluralRules` for the given language.
+    pub fn new(language: &Language) -> Self {
+        let rule_set = match language.language_code().as_ref() {
+            "pt" if language.region().id() != "pt" => PluralRuleSet::ZeroOrOne,
+            "fr" => PluralRuleSet::ZeroOrOne,
+            "es" | "el" | "tr" | "hu" => PluralRuleSet::ExactlyOne,
+            "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" => PluralRuleSet::Other,
+            "ru" | "uk" | "be" => PluralRuleSet::EastSlavic,
+            "pl" => PluralRuleSet::Polish,
+            "cs" | "sk" => PluralRuleSet::WestSlavic,
+            "ar" => PluralRuleSet::Arabic,
+            _ => PluralRuleSet::OneWithoutFraction,
+        };
+        Self { m_rule_set: rule_set }
+    }
+
+    /// Selects the plural category of a number.
+    pub fn select(&self, n: f64) -> PluralCategory {
+        self.select_decimal(&n.abs().to_string())
+    }
+
+    /// Selects the plural category of a number written as ASCII decimal digits,
+    /// such as `"7.50"`, taking visible fraction digits into account.
+    pub(crate) fn select_decimal(&self, digits: &str) -> PluralCategory {
+        let digits = digits.trim_start_matches('-');
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        // integer digits (i), visible fraction digits count (v), absolute value (n)
+        let i: u64 = integer.parse().unwrap_or(0);
+        let v = fraction.len();
+        let n: f64 = digits.parse().unwrap_or(0.0);
+        let is_integer = n.fract() == 0.0;
+        let (i10, i100) = (i % 10, i % 100);
+        match self.m_rule_set {
+            PluralRuleSet::OneWithoutFraction => if i == 1 && v == 0 { PluralCategory::One } else { PluralCategory::Other },
+            PluralRuleSet::ZeroOrOne => if i <= 1 { PluralCategory::One } else { PluralCategory::Other },
+            PluralRuleSet::ExactlyOne => if n == 1.0 { PluralCategory::One } else { PluralCategory::Other },
+            PluralRuleSet::Other => PluralCategory::Other,
+            PluralRuleSet::EastSlavic => {
+                if v != 0 { PluralCategory::Other }
+                else if i10 == 1 && i100 != 11 { PluralCategory::One }
+                else if (2..=4).contains(&i10) && !(12..=14).contains(&i100) { PluralCategory::Few }
+                else { PluralCategory::Many }
+            },
+            PluralRuleSet::Polish => {
+                if v != 0 { PluralCategory::Other }
+                else if i == 1 { PluralCategory::One }
+                else if (2..=4).contains(&i10) && !(12..=14).contains(&i100) { PluralCategory::Few }
+                else { PluralCategory::Many }
+            },
+            PluralRuleSet::WestSlavic => {
+                if v != 0 { PluralCategory::Many }
+                else if i == 1 { PluralCategory::One }
+                else if (2..=4).contains(&i) { PluralCategory::Few }
+                else { PluralCategory::Other }
+            },
+            PluralRuleSet::Arabic => {
+                let n100 = (n % 100.0) as u64;
+                if n == 0.0 { PluralCategory::Zero }
+                else if n == 1.0 { PluralCategory::One }
+                else if n == 2.0 { PluralCategory::Two }
+                else if is_integer && (3..=10).contains(&n100) { PluralCategory::Few }
+                else if is_integer && (11..=99).contains(&n100) { PluralCategory::Many }
+                else { PluralCategory::Other }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(tag: &str, numbers: &[&str]) -> Vec<&'static str> {
+        let rules = PluralRules::new(&Language::parse(tag).unwrap());
+        numbers.iter().map(|n| rules.select_decimal(n).as_str()).collect()
+    }
+
+    #[test]
+    fn select() {
+        assert_eq!(categories("en-US", &["0", "1", "1.0", "2", "-1"]), ["other", "one", "other", "other", "one"]);
+        assert_eq!(categories("fr-FR", &["0", "1.5", "2"]), ["one", "one", "other"]);
+        assert_eq!(categories("pt-BR", &["0", "1", "2"]), ["one", "one", "other"]);
+        assert_eq!(categories("pt-PT", &["0", "1", "2"]), ["other", "one", "other"]);
+        assert_eq!(categories("es-ES", &["1", "1.0", "2"]), ["one", "one", "other"]);
+        assert_eq!(categories("ja-JP", &["0", "1", "2"]), ["other", "other", "other"]);
+        assert_eq!(categories("ru-RU", &["1", "3", "5", "11", "21", "22", "112", "1.5"]), ["one", "few", "many", "many", "one", "few", "many", "other"]);
+        assert_eq!(categories("pl-PL", &["1", "2", "5", "21", "22", "1.5"]), ["one", "few", "many", "many", "few", "other"]);
+        assert_eq!(categories("cs-CZ", &["1", "3", "5", "1.5"]), ["one", "few", "other", "many"]);
+        assert_eq!(categories("ar-EG", &["0", "1", "2", "3", "11", "100", "102", "0.5"]), ["zero", "one", "two", "few", "many", "other", "other", "other"]);
+        assert_eq!(PluralRules::new(&Language::parse("en-US").unwrap()).select(1.0), PluralCategory::One);
+    }
+}
@@ -15,7 +15,7 @@ pub struct Region {
 
 impl Region {
     pub fn parse(abbrev: impl AnyStringType) -> Option<Region> {
-        let abbrev = abbrev.convert().to_uppercase();
+        let abbrev = abbrev.convert().to_ascii_uppercase();
         let data = REGION_DATA.get(&abbrev);
         if let Some(international_name) = data { Some(Region { m_abbrev: abbrev.clone(), m_international_name: international_name.clone() }) } else { None }
     }
@@ -27,6 +27,25 @@ impl Region {
     pub fn id(&self) -> String {
         self.to_string()
     }
+
+    /// Measurement system customarily used in the region.
+    pub fn measurement_system(&self) -> MeasurementSystem {
+        match self.m_abbrev.as_ref() {
+            "US" | "LR" | "MM" => MeasurementSystem::Us,
+            "GB" => MeasurementSystem::Uk,
+            _ => MeasurementSystem::Metric,
+        }
+    }
+}
+
+/// Measurement system, as preferred by a region.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum MeasurementSystem {
+    Metric,
+    /// United States customary units.
+    Us,
+    /// Metric units, except for miles and miles per hour on roads.
+    Uk,
 }
 
 impl PartialEq for Region {
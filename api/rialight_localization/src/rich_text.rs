@@ -0,0 +1,134 @@
+/// A span of rich text, as returned by `LocaleBundle::get_rich`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RichSpan {
+    Text(String),
+    /// A markup element, such as `<b>...</b>`, `<link id="x">...</link>`
+    /// or `<icon name="coin"/>`, whose content is given by `children`.
+    Element { tag: String, attributes: Vec<(String, String)>, children: Vec<RichSpan> },
+}
+
+impl RichSpan {
+    /// Value of an attribute of an element.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        match self {
+            RichSpan::Element { attributes, .. } => attributes.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str()),
+            RichSpan::Text(_) => None,
+        }
+    }
+
+    /// Text of the spans without markup.
+    pub fn plain_text(spans: &[RichSpan]) -> String {
+        let mut r = String::new();
+        for span in spans {
+            match span {
+                RichSpan::Text(s) => r.push_str(s),
+                RichSpan::Element { children, .. } => r.push_str(&RichSpan::plain_text(children)),
+            }
+        }
+        r
+    }
+}
+
+/// An element whose end tag is yet to come: its tag, attributes and children so far.
+type OpenElement = (String, Vec<(String, String)>, Vec<RichSpan>);
+
+/// Builds a tree of spans from text and the start and end of elements.
+pub(crate) struct RichTextBuilder {
+    m_open: Vec<OpenElement>,
+    m_spans: Vec<RichSpan>,
+}
+
+impl RichTextBuilder {
+    pub(crate) fn new() -> Self {
+        Self { m_open: vec![], m_spans: vec![] }
+    }
+
+    fn current(&mut self) -> &mut Vec<RichSpan> {
+        match self.m_open.last_mut() {
+            Some((_, _, children)) => children,
+            None => &mut self.m_spans,
+        }
+    }
+
+    pub(crate) fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let current = self.current();
+        if let Some(RichSpan::Text(last)) = current.last_mut() {
+            last.push_str(text);
+        } else {
+            current.push(RichSpan::Text(text.to_owned()));
+        }
+    }
+
+    pub(crate) fn push_spans(&mut self, spans: Vec<RichSpan>) {
+        for span in spans {
+            match span {
+                RichSpan::Text(s) => self.push_text(&s),
+                element => self.current().push(element),
+            }
+        }
+    }
+
+    pub(crate) fn start(&mut self, tag: &str, attributes: Vec<(String, String)>) {
+        self.m_open.push((tag.to_owned(), attributes, vec![]));
+    }
+
+    /// Closes the innermost element, if it has the given tag.
+    pub(crate) fn end(&mut self, tag: &str) {
+        if self.m_open.last().map(|(t, _, _)| t == tag).unwrap_or(false) {
+            self.close();
+        }
+    }
+
+    fn close(&mut self) {
+        let (tag, attributes, children) = self.m_open.pop().unwrap();
+        self.current().push(RichSpan::Element { tag, attributes, children });
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<RichSpan> {
+        while !self.m_open.is_empty() {
+            self.close();
+        }
+        self.m_spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> RichSpan {
+        RichSpan::Text(s.to_owned())
+    }
+
+    #[test]
+    fn builder() {
+        let mut r = RichTextBuilder::new();
+        r.push_text("a");
+        r.push_text("b");
+        r.start("link", vec![("id".to_owned(), "terms".to_owned())]);
+        r.push_text("c");
+        r.start("b", vec![]);
+        // only the innermost element is closed
+        r.end("link");
+        r.push_spans(vec![text("d"), RichSpan::Element { tag: "i".to_owned(), attributes: vec![], children: vec![] }]);
+        r.end("b");
+        r.push_text("");
+        let spans = r.finish();
+        assert_eq!(spans, [
+            text("ab"),
+            RichSpan::Element { tag: "link".to_owned(), attributes: vec![("id".to_owned(), "terms".to_owned())], children: vec![
+                text("c"),
+                RichSpan::Element { tag: "b".to_owned(), attributes: vec![], children: vec![
+                    text("d"),
+                    RichSpan::Element { tag: "i".to_owned(), attributes: vec![], children: vec![] },
+                ] },
+            ] },
+        ]);
+        assert_eq!(spans[1].attribute("id"), Some("terms"));
+        assert_eq!(spans[1].attribute("name"), None);
+        assert_eq!(RichSpan::plain_text(&spans), "abcd");
+    }
+}
@@ -0,0 +1,309 @@
+use std::{collections::{HashMap, HashSet}, sync::{Arc, RwLock}};
+use unicode_linebreak::{BreakClass, BreakOpportunity};
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+use rialight_util::AnyStringType;
+use super::Language;
+
+lazy_static! {
+    static ref WORD_BREAK_TAILORINGS: RwLock<HashMap<String, Arc<dyn WordBreakTailoring>>> = RwLock::new(HashMap::new());
+}
+
+/// Splits text into graphemes, words, sentences and line break opportunities,
+/// following UAX #29 and UAX #14.
+///
+/// Word boundaries inside runs of complex-context scripts, such as Thai and Lao,
+/// which are not separated by spaces, are found by the `WordBreakTailoring`
+/// registered for the segmenter's language. Without a tailoring, such a run is
+/// taken as a single word.
+#[derive(Clone)]
+pub struct Segmenter {
+    m_word_break_tailoring: Option<Arc<dyn WordBreakTailoring>>,
+}
+
+/// A segment of text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment<'a> {
+    /// Byte offset of the segment in the text.
+    pub index: usize,
+    /// Segment text.
+    pub text: &'a str,
+    /// For words, whether the segment contains letters or digits,
+    /// as opposed to spaces and punctuation. Always `false` for other segments.
+    pub is_word_like: bool,
+}
+
+/// A position where a line may be broken.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LineBreakOpportunity {
+    /// Byte offset of the character that starts the next line.
+    pub index: usize,
+    /// Whether the line must be broken here, as after a line feed.
+    pub mandatory: bool,
+}
+
+impl Segmenter {
+    /// Constructs a `Segmenter` for the given language, using the word break
+    /// tailoring registered for it, if any.
+    pub fn new(language: &Language) -> Self {
+        let tailorings = WORD_BREAK_TAILORINGS.read().unwrap();
+        let tailoring = tailorings.get(&language.tag().to_lowercase())
+            .or_else(|| tailorings.get(&language.language_code()))
+            .cloned();
+        Self { m_word_break_tailoring: tailoring }
+    }
+
+    /// Constructs a `Segmenter` with the given word break tailoring.
+    pub fn with_word_break_tailoring(tailoring: Arc<dyn WordBreakTailoring>) -> Self {
+        Self { m_word_break_tailoring: Some(tailoring) }
+    }
+
+    /// Splits text into extended grapheme clusters, that is, user-perceived characters.
+    pub fn graphemes<'a>(&self, text: &'a str) -> Vec<Segment<'a>> {
+        text.grapheme_indices(true).map(|(index, text)| Segment { index, text, is_word_like: false }).collect()
+    }
+
+    /// Splits text into words, spaces and punctuation.
+    pub fn words<'a>(&self, text: &'a str) -> Vec<Segment<'a>> {
+        let mut r: Vec<Segment<'a>> = vec![];
+        let mut complex_run: Option<usize> = None;
+        for (index, segment) in text.split_word_bound_indices() {
+            if segment.chars().all(is_complex_context) {
+                complex_run = complex_run.or(Some(index));
+                continue;
+            }
+            if let Some(start) = complex_run.take() {
+                self.push_complex_run(text, start, index, &mut r);
+            }
+            r.push(Segment { index, text: segment, is_word_like: segment.chars().any(char::is_alphanumeric) });
+        }
+        if let Some(start) = complex_run {
+            self.push_complex_run(text, start, text.len(), &mut r);
+        }
+        r
+    }
+
+    fn push_complex_run<'a>(&self, text: &'a str, start: usize, end: usize, output: &mut Vec<Segment<'a>>) {
+        let run = &text[start..end];
+        let mut last = 0;
+        for boundary in self.complex_run_boundaries(run).into_iter().chain(std::iter::once(run.len())) {
+            output.push(Segment { index: start + last, text: &run[last..boundary], is_word_like: true });
+            last = boundary;
+        }
+    }
+
+    /// Internal word boundaries of a complex-context run, as byte offsets into the run.
+    fn complex_run_boundaries(&self, run: &str) -> Vec<usize> {
+        let mut r: Vec<usize> = match &self.m_word_break_tailoring {
+            Some(tailoring) => tailoring.word_boundaries(run),
+            None => vec![],
+        };
+        r.retain(|i| *i > 0 && *i < run.len() && run.is_char_boundary(*i));
+        r.sort();
+        r.dedup();
+        r
+    }
+
+    /// Splits text into sentences.
+    pub fn sentences<'a>(&self, text: &'a str) -> Vec<Segment<'a>> {
+        text.split_sentence_bound_indices().map(|(index, text)| Segment { index, text, is_word_like: false }).collect()
+    }
+
+    /// Returns the positions where lines may be broken when wrapping text,
+    /// including the end of the text, which is a mandatory break.
+    pub fn line_break_opportunities(&self, text: &str) -> Vec<LineBreakOpportunity> {
+        let mut r: Vec<LineBreakOpportunity> = unicode_linebreak::linebreaks(text)
+            .map(|(index, opportunity)| LineBreakOpportunity { index, mandatory: opportunity == BreakOpportunity::Mandatory })
+            .collect();
+        if self.m_word_break_tailoring.is_some() {
+            for segment in self.words(text) {
+                if segment.index > 0 && segment.text.chars().next().map(is_complex_context).unwrap_or(false)
+                && text[..segment.index].chars().next_back().map(is_complex_context).unwrap_or(false)
+                && !r.iter().any(|o| o.index == segment.index) {
+                    r.push(LineBreakOpportunity { index: segment.index, mandatory: false });
+                }
+            }
+            r.sort_by_key(|o| o.index);
+        }
+        r
+    }
+
+    /// Returns the grapheme boundary following the byte offset, for moving a caret forward,
+    /// or `None` at the end of the text.
+    pub fn next_grapheme_boundary(&self, text: &str, index: usize) -> Option<usize> {
+        let mut cursor = GraphemeCursor::new(index.min(text.len()), text.len(), true);
+        cursor.next_boundary(text, 0).ok().flatten()
+    }
+
+    /// Returns the grapheme boundary preceding the byte offset, for moving a caret backward,
+    /// or `None` at the start of the text.
+    pub fn previous_grapheme_boundary(&self, text: &str, index: usize) -> Option<usize> {
+        let mut cursor = GraphemeCursor::new(index.min(text.len()), text.len(), true);
+        cursor.prev_boundary(text, 0).ok().flatten()
+    }
+
+    /// Returns the end of the word following the byte offset, for moving a caret
+    /// forward by word, or `None` if there is no next word.
+    pub fn next_word_boundary(&self, text: &str, index: usize) -> Option<usize> {
+        self.words(text).into_iter()
+            .filter(|s| s.is_word_like)
+            .map(|s| s.index + s.text.len())
+            .find(|i| *i > index)
+    }
+
+    /// Returns the start of the word preceding the byte offset, for moving a caret
+    /// backward by word, or `None` if there is no previous word.
+    pub fn previous_word_boundary(&self, text: &str, index: usize) -> Option<usize> {
+        self.words(text).into_iter()
+            .filter(|s| s.is_word_like)
+            .map(|s| s.index)
+            .rfind(|i| *i < index)
+    }
+}
+
+/// Finds word boundaries within runs of a complex-context script,
+/// such as Thai, Lao, Khmer or Myanmar, which do not separate words with spaces.
+pub trait WordBreakTailoring: Send + Sync {
+    /// Returns the byte offsets, within the run, where a new word starts.
+    /// The run never contains spaces or punctuation.
+    fn word_boundaries(&self, run: &str) -> Vec<usize>;
+}
+
+/// Registers the word break tailoring used by segmenters for a language,
+/// which is either a language code, such as `"th"`, or a language tag, such as `"th-TH"`.
+pub fn register_word_break_tailoring(language: impl AnyStringType, tailoring: Arc<dyn WordBreakTailoring>) {
+    WORD_BREAK_TAILORINGS.write().unwrap().insert(language.convert().to_lowercase().replace("_", "-"), tailoring);
+}
+
+/// Word break tailoring that splits a run into dictionary words,
+/// preferring the split with the fewest characters not covered by a word,
+/// then the one with the fewest words.
+pub struct DictionaryWordBreaker {
+    m_words: HashSet<String>,
+    m_max_word_length: usize,
+}
+
+impl DictionaryWordBreaker {
+    /// Constructs a `DictionaryWordBreaker` from a word list.
+    pub fn new(words: Vec<impl AnyStringType>) -> Self {
+        let words: HashSet<String> = words.iter().map(|w| w.convert().to_owned()).filter(|w| !w.is_empty()).collect();
+        let max_word_length = words.iter().map(|w| w.chars().count()).max().unwrap_or(0);
+        Self { m_words: words, m_max_word_length: max_word_length }
+    }
+
+    /// Constructs a `DictionaryWordBreaker` from a text with one word per line.
+    pub fn from_word_list(list: impl AnyStringType) -> Self {
+        DictionaryWordBreaker::new(list.convert().lines().map(|l| l.trim()).collect())
+    }
+}
+
+impl WordBreakTailoring for DictionaryWordBreaker {
+    fn word_boundaries(&self, run: &str) -> Vec<usize> {
+        let offsets: Vec<usize> = run.char_indices().map(|(i, _)| i).chain(std::iter::once(run.len())).collect();
+        let n = offsets.len() - 1;
+        // best[i]: uncovered characters, words, previous position and whether
+        // the characters since it form a dictionary word, for the first i characters
+        let mut best: Vec<Option<(usize, usize, usize, bool)>> = vec![None; n + 1];
+        best[0] = Some((0, 0, 0, false));
+        for i in 0..n {
+            let Some((uncovered, words, _, _)) = best[i] else { continue };
+            let mut candidates = vec![(i + 1, uncovered + 1, words + 1, false)];
+            for j in (i + 1)..=(i + self.m_max_word_length).min(n) {
+                if self.m_words.contains(&run[offsets[i]..offsets[j]]) {
+                    candidates.push((j, uncovered, words + 1, true));
+                }
+            }
+            for (j, uncovered, words, is_word) in candidates {
+                if best[j].map(|(u, w, _, _)| (uncovered, words) < (u, w)).unwrap_or(true) {
+                    best[j] = Some((uncovered, words, i, is_word));
+                }
+            }
+        }
+        let mut r: Vec<usize> = vec![];
+        let mut i = n;
+        while i > 0 {
+            let (_, _, previous, is_word) = best[i].unwrap();
+            // keep characters not covered by a word together
+            let previous_is_word = best[previous].map(|b| b.3).unwrap_or(true);
+            if previous > 0 && (is_word || previous_is_word) {
+                r.push(offsets[previous]);
+            }
+            i = previous;
+        }
+        r.reverse();
+        r
+    }
+}
+
+fn is_complex_context(c: char) -> bool {
+    unicode_linebreak::break_property(c as u32) == BreakClass::ComplexContext
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(segments: Vec<Segment<'a>>) -> Vec<&'a str> {
+        segments.into_iter().map(|s| s.text).collect()
+    }
+
+    fn segmenter() -> Segmenter {
+        Segmenter::new(&Language::parse("en-US").unwrap())
+    }
+
+    #[test]
+    fn graphemes() {
+        let text = "e\u{301}👍🏽a";
+        assert_eq!(texts(segmenter().graphemes(text)), ["e\u{301}", "👍🏽", "a"]);
+        assert_eq!(segmenter().next_grapheme_boundary(text, 0), Some(3));
+        assert_eq!(segmenter().previous_grapheme_boundary(text, 3), Some(0));
+        assert_eq!(segmenter().next_grapheme_boundary(text, text.len()), None);
+    }
+
+    #[test]
+    fn words() {
+        let text = "Hello, world!";
+        let words = segmenter().words(text);
+        assert_eq!(texts(words.clone()), ["Hello", ",", " ", "world", "!"]);
+        assert_eq!(words.iter().filter(|s| s.is_word_like).map(|s| s.index).collect::<Vec<usize>>(), [0, 7]);
+        assert_eq!(segmenter().next_word_boundary(text, 0), Some(5));
+        assert_eq!(segmenter().next_word_boundary(text, 5), Some(12));
+        assert_eq!(segmenter().previous_word_boundary(text, 12), Some(7));
+        assert_eq!(segmenter().previous_word_boundary(text, 0), None);
+    }
+
+    #[test]
+    fn sentences() {
+        assert_eq!(texts(segmenter().sentences("One. Two?")), ["One. ", "Two?"]);
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert_eq!(segmenter().line_break_opportunities("a b\nc"), [
+            LineBreakOpportunity { index: 2, mandatory: false },
+            LineBreakOpportunity { index: 4, mandatory: true },
+            LineBreakOpportunity { index: 5, mandatory: true },
+        ]);
+    }
+
+    #[test]
+    fn dictionary_word_breaker() {
+        let breaker = DictionaryWordBreaker::from_word_list("ภาษา\nไทย\n");
+        assert_eq!(breaker.word_boundaries("ภาษาไทย"), [12]);
+        // characters not covered by a word are kept together
+        assert_eq!(DictionaryWordBreaker::new(vec!["ไทย"]).word_boundaries("ภาษาไทย"), [12]);
+        assert_eq!(DictionaryWordBreaker::new(vec!["ไทย"]).word_boundaries("ไทยภาษา"), [9]);
+
+        let thai = Segmenter::with_word_break_tailoring(Arc::new(breaker));
+        assert_eq!(texts(thai.words("ภาษาไทย ดี")), ["ภาษา", "ไทย", " ", "ดี"]);
+        assert!(thai.line_break_opportunities("ภาษาไทย").contains(&LineBreakOpportunity { index: 12, mandatory: false }));
+        // without a tailoring, a run is a single word
+        assert_eq!(texts(segmenter().words("ภาษาไทย")), ["ภาษาไทย"]);
+    }
+
+    #[test]
+    fn registered_tailoring() {
+        register_word_break_tailoring("th", Arc::new(DictionaryWordBreaker::new(vec!["ภาษา", "ไทย"])));
+        let thai = Segmenter::new(&Language::parse("th-TH").unwrap());
+        assert_eq!(texts(thai.words("ภาษาไทย")), ["ภาษา", "ไทย"]);
+    }
+}
@@ -0,0 +1,262 @@
+use std::{cell::Cell, collections::HashMap};
+use super::{Language, NumberFormatter, NumberFormatOptions};
+
+lazy_static! {
+    /// Rule sets by language and rule set name. Rule sets whose name starts
+    /// with `%` are only referenced by other rule sets.
+    static ref SPELLOUT_DATA: HashMap<String, HashMap<String, RuleSet>> = {
+        let data: HashMap<String, HashMap<String, Vec<(String, String)>>> = serde_json::from_str(include_str!("../data/spellout.json")).unwrap();
+        data.into_iter().map(|(language, sets)| (language, sets.into_iter().map(|(name, rules)| (name, RuleSet::from_rules(rules))).collect())).collect()
+    };
+}
+
+/// A rule-based number format, as in CLDR RBNF.
+///
+/// Each rule applies from its base value up to the next rule's base value.
+/// In the rule text, `<<` is replaced by the value divided by the rule's divisor,
+/// the highest power of 10 not greater than the base value, `>>` by the remainder
+/// and `==` by the value itself. A rule set name, such as in `<%spellout-cardinal<`,
+/// formats the substituted value with another rule set, with `%%` for the rule sets
+/// only referenced by other rule sets, and `#` formats it as digits.
+/// Text in brackets is omitted when the remainder is zero.
+struct RuleSet {
+    /// Rule for negative values.
+    m_negative: Option<String>,
+    /// Rules sorted by base value.
+    m_rules: Vec<(i64, String)>,
+}
+
+impl RuleSet {
+    fn from_rules(rules: Vec<(String, String)>) -> Self {
+        let mut r = RuleSet { m_negative: None, m_rules: vec![] };
+        for (base, text) in rules {
+            if base == "-x" {
+                r.m_negative = Some(text);
+            } else {
+                r.m_rules.push((base.parse().unwrap(), text));
+            }
+        }
+        r.m_rules.sort_by_key(|(base, _)| *base);
+        r
+    }
+}
+
+/// Style of a spelled-out number.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum SpelloutStyle {
+    /// Such as "twenty-one".
+    Cardinal,
+    /// Such as "twenty-first".
+    Ordinal,
+    /// Such as "21st".
+    DigitsOrdinal,
+    /// Such as "XXI".
+    Roman,
+}
+
+impl SpelloutStyle {
+    fn rule_set_name(&self) -> &'static str {
+        match self {
+            SpelloutStyle::Cardinal => "spellout-cardinal",
+            SpelloutStyle::Ordinal => "spellout-ordinal",
+            SpelloutStyle::DigitsOrdinal => "digits-ordinal",
+            SpelloutStyle::Roman => "roman",
+        }
+    }
+}
+
+/// Grammatical gender a spelled-out number agrees with.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum GrammaticalGender {
+    Masculine,
+    Feminine,
+}
+
+/// Spells out integers in words, as ordinals or as roman numerals, such as
+/// "twenty-one", "21st" or "vigésimo primeiro".
+///
+/// Words are available for English, Portuguese (Brazilian spelling) and Spanish;
+/// other languages format cardinals and ordinals as digits. Roman numerals range
+/// from 1 to 3999 and larger values are formatted as digits.
+#[derive(Clone)]
+pub struct SpelloutFormatter {
+    m_language_keys: Vec<String>,
+    m_number_formatter: NumberFormatter,
+    m_style: SpelloutStyle,
+    m_gender: GrammaticalGender,
+}
+
+impl SpelloutFormatter {
+    pub fn new(language: &Language, options: &SpelloutFormatOptions) -> Self {
+        let number_options = NumberFormatOptions::new();
+        number_options.maximum_fraction_digits(0);
+        Self {
+            m_language_keys: vec![language.tag().to_ascii_lowercase(), language.language_code(), "root".to_owned()],
+            m_number_formatter: NumberFormatter::new(language, &number_options),
+            m_style: options.m_style.get(),
+            m_gender: options.m_gender.get(),
+        }
+    }
+
+    /// Formats an integer.
+    pub fn format(&self, value: i64) -> String {
+        let name = self.m_style.rule_set_name();
+        let feminine_name = format!("{}-feminine", name);
+        if self.m_gender == GrammaticalGender::Feminine && self.rule_set(&feminine_name).is_some() {
+            return self.format_with(&feminine_name, value);
+        }
+        self.format_with(name, value)
+    }
+
+    fn rule_set(&self, name: &str) -> Option<&'static RuleSet> {
+        self.m_language_keys.iter().find_map(|key| SPELLOUT_DATA.get(key).and_then(|sets| sets.get(name)))
+    }
+
+    fn format_with(&self, name: &str, value: i64) -> String {
+        let Some(rule_set) = self.rule_set(name) else {
+            return self.m_number_formatter.format_integer(value);
+        };
+        if value < 0 {
+            return match (&rule_set.m_negative, value.checked_neg()) {
+                (Some(text), Some(absolute)) => self.apply_rule(name, text, absolute, 0, absolute),
+                _ => self.m_number_formatter.format_integer(value),
+            };
+        }
+        let Some((base, text)) = rule_set.m_rules.iter().rev().find(|(base, _)| *base <= value) else {
+            return self.m_number_formatter.format_integer(value);
+        };
+        let divisor = 10i64.pow(if *base == 0 { 0 } else { base.ilog10() });
+        self.apply_rule(name, text, value, value / divisor, value % divisor)
+    }
+
+    fn apply_rule(&self, name: &str, text: &str, value: i64, quotient: i64, remainder: i64) -> String {
+        let mut r = String::new();
+        let mut chars = text.chars();
+        let mut optional = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '[' => optional = true,
+                ']' => optional = false,
+                _ if optional && remainder == 0 => {},
+                '<' | '>' | '=' => {
+                    let descriptor: String = chars.by_ref().take_while(|d| *d != c).collect();
+                    let substituted = match c { '<' => quotient, '>' => remainder, _ => value };
+                    r.push_str(&if descriptor.starts_with('#') {
+                        self.m_number_formatter.format_integer(substituted)
+                    } else if descriptor.is_empty() {
+                        self.format_with(name, substituted)
+                    } else {
+                        self.format_with(descriptor.strip_prefix('%').unwrap_or(&descriptor), substituted)
+                    });
+                },
+                _ => r.push(c),
+            }
+        }
+        r
+    }
+}
+
+pub struct SpelloutFormatOptions {
+    m_style: Cell<SpelloutStyle>,
+    m_gender: Cell<GrammaticalGender>,
+}
+
+impl Clone for SpelloutFormatOptions {
+    fn clone(&self) -> Self {
+        Self {
+            m_style: self.m_style.clone(),
+            m_gender: self.m_gender.clone(),
+        }
+    }
+}
+
+impl SpelloutFormatOptions {
+    pub fn new() -> Self {
+        SpelloutFormatOptions {
+            m_style: Cell::new(SpelloutStyle::Cardinal),
+            m_gender: Cell::new(GrammaticalGender::Masculine),
+        }
+    }
+
+    pub fn style(&self, value: SpelloutStyle) -> &Self {
+        self.m_style.set(value);
+        self
+    }
+
+    /// Gender of the noun the number agrees with, in languages that
+    /// inflect numbers. Defaults to masculine.
+    pub fn gender(&self, value: GrammaticalGender) -> &Self {
+        self.m_gender.set(value);
+        self
+    }
+}
+
+impl Default for SpelloutFormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spell(tag: &str, style: SpelloutStyle, gender: GrammaticalGender, values: &[i64]) -> Vec<String> {
+        let options = SpelloutFormatOptions::new();
+        options.style(style).gender(gender);
+        let formatter = SpelloutFormatter::new(&Language::parse(tag).unwrap(), &options);
+        values.iter().map(|v| formatter.format(*v)).collect()
+    }
+
+    use GrammaticalGender::{Feminine, Masculine};
+    use SpelloutStyle::{Cardinal, DigitsOrdinal, Ordinal, Roman};
+
+    #[test]
+    fn english() {
+        assert_eq!(spell("en-US", Cardinal, Masculine, &[0, 13, 21, 101, 1234, 1_000_000, -5]),
+            ["zero", "thirteen", "twenty-one", "one hundred one", "one thousand two hundred thirty-four", "one million", "minus five"]);
+        assert_eq!(spell("en-US", Ordinal, Masculine, &[1, 12, 13, 21, 100, 101, 1000]),
+            ["first", "twelfth", "thirteenth", "twenty-first", "one hundredth", "one hundred first", "one thousandth"]);
+        assert_eq!(spell("en-US", DigitsOrdinal, Masculine, &[1, 2, 3, 11, 12, 13, 21, 102, 111, 1234, -1]),
+            ["1st", "2nd", "3rd", "11th", "12th", "13th", "21st", "102nd", "111th", "1,234th", "-1st"]);
+        // English has no feminine forms
+        assert_eq!(spell("en-US", Cardinal, Feminine, &[1]), ["one"]);
+    }
+
+    #[test]
+    fn portuguese() {
+        assert_eq!(spell("pt-BR", Cardinal, Masculine, &[1, 2, 21, 100, 101, 200, 1000, 1100, 1234, 2001, 1_000_000, 2_000_000]),
+            ["um", "dois", "vinte e um", "cem", "cento e um", "duzentos", "mil", "mil e cem", "mil duzentos e trinta e quatro", "dois mil e um", "um milhão", "dois milhões"]);
+        assert_eq!(spell("pt-BR", Cardinal, Feminine, &[1, 2, 22, 200]), ["uma", "duas", "vinte e duas", "duzentas"]);
+        assert_eq!(spell("pt-BR", Ordinal, Masculine, &[1, 21, 100]), ["primeiro", "vigésimo primeiro", "centésimo"]);
+        assert_eq!(spell("pt-BR", Ordinal, Feminine, &[1, 21]), ["primeira", "vigésima primeira"]);
+        assert_eq!(spell("pt-BR", DigitsOrdinal, Masculine, &[1, 1000]), ["1º", "1.000º"]);
+        assert_eq!(spell("pt-BR", DigitsOrdinal, Feminine, &[1]), ["1ª"]);
+    }
+
+    #[test]
+    fn spanish() {
+        assert_eq!(spell("es-ES", Cardinal, Masculine, &[1, 16, 21, 31, 100, 101, 500, 1000, 2000, 21000, 1_000_000, 2_000_000]),
+            ["uno", "dieciséis", "veintiuno", "treinta y uno", "cien", "ciento uno", "quinientos", "mil", "dos mil", "veintiún mil", "un millón", "dos millones"]);
+        assert_eq!(spell("es-ES", Cardinal, Feminine, &[1, 21, 200]), ["una", "veintiuna", "doscientas"]);
+        assert_eq!(spell("es-ES", Ordinal, Masculine, &[1, 3]), ["primero", "tercero"]);
+        assert_eq!(spell("es-ES", Ordinal, Feminine, &[1, 3]), ["primera", "tercera"]);
+        assert_eq!(spell("es-ES", DigitsOrdinal, Masculine, &[1]), ["1.º"]);
+        assert_eq!(spell("es-ES", DigitsOrdinal, Feminine, &[1]), ["1.ª"]);
+    }
+
+    #[test]
+    fn roman() {
+        assert_eq!(spell("en-US", Roman, Masculine, &[0, 4, 14, 1994, 2024, 3999, 4000, -5]),
+            ["N", "IV", "XIV", "MCMXCIV", "MMXXIV", "MMMCMXCIX", "4,000", "-5"]);
+    }
+
+    #[test]
+    fn limits() {
+        assert_eq!(spell("en-US", Cardinal, Masculine, &[i64::MAX, i64::MIN]), ["9,223,372,036,854,775,807", "-9,223,372,036,854,775,808"]);
+        assert_eq!(spell("en-US", Cardinal, Masculine, &[-i64::MAX]), ["minus 9,223,372,036,854,775,807"]);
+        // languages without words format digits
+        assert_eq!(spell("de-DE", Cardinal, Masculine, &[1234, -1]), ["1.234", "-1"]);
+        assert_eq!(spell("de-DE", Roman, Masculine, &[12]), ["XII"]);
+    }
+}
@@ -0,0 +1,645 @@
+use std::{collections::HashMap, fmt::Debug, sync::{Arc, RwLock}, time::SystemTime, convert::TryInto};
+use rialight_util::AnyStringType;
+use super::date_time::{days_from_civil, civil_from_days, timestamp_from_system_time};
+
+lazy_static! {
+    /// Bundled and already loaded time zones by identifier.
+    static ref TIME_ZONES: RwLock<HashMap<String, TimeZone>> = RwLock::new(HashMap::new());
+}
+
+const ZONEINFO_DIRECTORIES: [&str; 3] = ["/usr/share/zoneinfo", "/usr/lib/zoneinfo", "/usr/share/lib/zoneinfo"];
+
+/// An IANA time zone, such as `America/Sao_Paulo`.
+///
+/// Time zones are read from TZif files, either from the system's time zone database
+/// (`TZDIR` or `/usr/share/zoneinfo`) or from data bundled with the application
+/// through `TimeZone::register`, which takes precedence. Platforms without a
+/// time zone database, such as Windows, need bundled data.
+#[derive(Clone)]
+pub struct TimeZone {
+    m_id: String,
+    m_data: Arc<TimeZoneData>,
+}
+
+struct TimeZoneData {
+    /// Transition times, in seconds since the Unix epoch, with the index of the local time type.
+    m_transitions: Vec<(i64, usize)>,
+    m_types: Vec<TimeZoneOffset>,
+    /// Rule for times after the last transition.
+    m_footer: Option<PosixTimeZone>,
+}
+
+/// Local time type of a time zone at a given instant.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimeZoneOffset {
+    /// Offset from UTC in seconds, positive east of Greenwich.
+    pub utc_offset: i32,
+    /// Whether daylight saving time is in effect.
+    pub is_dst: bool,
+    /// Abbreviation from the time zone database, such as `"PDT"` or `"-03"`.
+    pub abbreviation: String,
+}
+
+impl TimeZone {
+    /// Coordinated Universal Time.
+    pub fn utc() -> TimeZone {
+        TimeZone::fixed_with_id("UTC", 0, "UTC")
+    }
+
+    /// A time zone with a fixed offset from UTC, in seconds.
+    pub fn fixed(utc_offset: i32) -> TimeZone {
+        let id = if utc_offset == 0 { "UTC".to_owned() } else { "UTC".to_owned() + &format_offset(utc_offset) };
+        TimeZone::fixed_with_id(&id, utc_offset, &format_offset(utc_offset))
+    }
+
+    fn fixed_with_id(id: &str, utc_offset: i32, abbreviation: &str) -> TimeZone {
+        TimeZone {
+            m_id: id.to_owned(),
+            m_data: Arc::new(TimeZoneData {
+                m_transitions: vec![],
+                m_types: vec![TimeZoneOffset { utc_offset, is_dst: false, abbreviation: abbreviation.to_owned() }],
+                m_footer: None,
+            }),
+        }
+    }
+
+    /// Finds a time zone by IANA identifier, such as `"Europe/Lisbon"`,
+    /// in the bundled data or in the system's time zone database.
+    pub fn named(id: impl AnyStringType) -> Option<TimeZone> {
+        let id = id.convert();
+        if id == "UTC" || id == "Etc/UTC" {
+            return Some(TimeZone::utc());
+        }
+        if let Some(tz) = TIME_ZONES.read().unwrap().get(id) {
+            return Some(tz.clone());
+        }
+        if id.is_empty() || id.starts_with('/') || id.split('/').any(|c| c == ".." || c == ".") {
+            return None;
+        }
+        let mut directories: Vec<String> = vec![];
+        if let Ok(tzdir) = std::env::var("TZDIR") {
+            directories.push(tzdir);
+        }
+        directories.extend(ZONEINFO_DIRECTORIES.iter().map(|d| d.to_string()));
+        for directory in directories {
+            if let Ok(bytes) = std::fs::read(std::path::Path::new(&directory).join(id)) {
+                let tz = TimeZone::from_tzif(id, &bytes)?;
+                TIME_ZONES.write().unwrap().insert(id.to_owned(), tz.clone());
+                return Some(tz);
+            }
+        }
+        None
+    }
+
+    /// Registers bundled TZif data for a time zone identifier, so that `TimeZone::named`
+    /// finds it without the system's time zone database. Returns `false` if the data is invalid.
+    pub fn register(id: impl AnyStringType, tzif: &[u8]) -> bool {
+        let id = id.convert();
+        match TimeZone::from_tzif(id, tzif) {
+            Some(tz) => {
+                TIME_ZONES.write().unwrap().insert(id.to_owned(), tz);
+                true
+            },
+            None => {
+                println!("Invalid time zone data: {}.", id);
+                false
+            },
+        }
+    }
+
+    /// The time zone of the system, from the `TZ` environment variable or `/etc/localtime`,
+    /// or UTC if it cannot be determined.
+    pub fn local() -> TimeZone {
+        if let Ok(tz) = std::env::var("TZ") {
+            let tz = tz.trim_start_matches(':');
+            if tz.starts_with('/') {
+                if let Some(tz) = std::fs::read(tz).ok().and_then(|bytes| TimeZone::from_tzif(zoneinfo_id(tz), &bytes)) {
+                    return tz;
+                }
+            } else if !tz.is_empty() {
+                if let Some(tz) = TimeZone::named(tz) {
+                    return tz;
+                }
+                if let Some(rule) = PosixTimeZone::parse(tz) {
+                    return TimeZone {
+                        m_id: tz.to_owned(),
+                        m_data: Arc::new(TimeZoneData { m_transitions: vec![], m_types: vec![rule.m_std.clone()], m_footer: Some(rule) }),
+                    };
+                }
+            }
+        }
+        let id = std::fs::read_link("/etc/localtime").ok()
+            .map(|p| zoneinfo_id(&p.to_string_lossy()))
+            .unwrap_or_else(|| "localtime".to_owned());
+        if let Some(tz) = TimeZone::named(id.as_str()) {
+            return tz;
+        }
+        std::fs::read("/etc/localtime").ok()
+            .and_then(|bytes| TimeZone::from_tzif(id.as_str(), &bytes))
+            .unwrap_or_else(TimeZone::utc)
+    }
+
+    /// Parses a TZif file (RFC 8536), versions 1 to 4.
+    pub fn from_tzif(id: impl AnyStringType, bytes: &[u8]) -> Option<TimeZone> {
+        let (header, mut data_start) = TzifHeader::parse(bytes, 0)?;
+        let mut header = header;
+        let mut time_size = 4;
+        if header.m_version >= b'2' {
+            // skip the version 1 data block, which only has 32-bit times
+            let (v2_header, v2_start) = TzifHeader::parse(bytes, data_start + header.data_len(4))?;
+            header = v2_header;
+            data_start = v2_start;
+            time_size = 8;
+        }
+        let mut i = data_start;
+        let mut transition_times: Vec<i64> = vec![];
+        for _ in 0..header.m_time_count {
+            let time = bytes.get(i..i + time_size)?;
+            transition_times.push(if time_size == 8 { i64::from_be_bytes(time.try_into().ok()?) } else { i32::from_be_bytes(time.try_into().ok()?) as i64 });
+            i += time_size;
+        }
+        let transition_types = bytes.get(i..i + header.m_time_count)?.to_vec();
+        i += header.m_time_count;
+        let mut types: Vec<(i32, bool, usize)> = vec![];
+        for _ in 0..header.m_type_count {
+            let record = bytes.get(i..i + 6)?;
+            types.push((i32::from_be_bytes(record[0..4].try_into().ok()?), record[4] != 0, record[5] as usize));
+            i += 6;
+        }
+        let designations = bytes.get(i..i + header.m_char_count)?;
+        let types: Vec<TimeZoneOffset> = types.into_iter().map(|(utc_offset, is_dst, index)| {
+            let abbreviation = designations.get(index..).unwrap_or(&[]);
+            let end = abbreviation.iter().position(|b| *b == 0).unwrap_or(abbreviation.len());
+            TimeZoneOffset { utc_offset, is_dst, abbreviation: String::from_utf8_lossy(&abbreviation[..end]).into_owned() }
+        }).collect();
+        if types.is_empty() || transition_types.iter().any(|t| *t as usize >= types.len()) {
+            return None;
+        }
+        let mut footer: Option<PosixTimeZone> = None;
+        if time_size == 8 {
+            let footer_start = data_start + header.data_len(8);
+            if let Some(rest) = bytes.get(footer_start..) {
+                let text = String::from_utf8_lossy(rest);
+                let text = text.trim_start_matches('\n');
+                footer = PosixTimeZone::parse(text.split('\n').next().unwrap_or(""));
+            }
+        }
+        Some(TimeZone {
+            m_id: id.convert().to_owned(),
+            m_data: Arc::new(TimeZoneData {
+                m_transitions: transition_times.into_iter().zip(transition_types.into_iter().map(|t| t as usize)).collect(),
+                m_types: types,
+                m_footer: footer,
+            }),
+        })
+    }
+
+    /// IANA identifier, such as `"America/Sao_Paulo"`.
+    pub fn id(&self) -> String {
+        self.m_id.clone()
+    }
+
+    /// Returns the local time type at an instant.
+    pub fn offset_at(&self, time: SystemTime) -> TimeZoneOffset {
+        self.offset_at_timestamp(timestamp_from_system_time(time).0)
+    }
+
+    /// Returns the local time type at a time in seconds since the Unix epoch.
+    pub fn offset_at_timestamp(&self, timestamp: i64) -> TimeZoneOffset {
+        let data = &self.m_data;
+        let count = data.m_transitions.partition_point(|(t, _)| *t <= timestamp);
+        if count == 0 {
+            if data.m_transitions.is_empty() {
+                if let Some(footer) = &data.m_footer {
+                    return footer.offset_at(timestamp);
+                }
+            }
+            return data.m_types[0].clone();
+        }
+        if count == data.m_transitions.len() {
+            if let Some(footer) = &data.m_footer {
+                return footer.offset_at(timestamp);
+            }
+        }
+        data.m_types[data.m_transitions[count - 1].1].clone()
+    }
+
+    /// Returns the instants, in seconds since the Unix epoch, at which the wall clock
+    /// shows the given local time, also in seconds since the Unix epoch.
+    /// There are two instants for a local time repeated when clocks are turned back
+    /// and none for a local time skipped when clocks are turned forward.
+    pub(crate) fn timestamps_for_local(&self, local: i64) -> Vec<i64> {
+        let mut offsets: Vec<i32> = vec![
+            self.offset_at_timestamp(local - 86400).utc_offset,
+            self.offset_at_timestamp(local).utc_offset,
+            self.offset_at_timestamp(local + 86400).utc_offset,
+        ];
+        offsets.sort();
+        offsets.dedup();
+        let mut r: Vec<i64> = offsets.into_iter()
+            .map(|offset| local - offset as i64)
+            .filter(|t| local - self.offset_at_timestamp(*t).utc_offset as i64 == *t)
+            .collect();
+        r.sort();
+        r.dedup();
+        r
+    }
+}
+
+impl PartialEq for TimeZone {
+    fn eq(&self, other: &Self) -> bool {
+        self.m_id == other.m_id
+    }
+}
+
+impl Debug for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.m_id)
+    }
+}
+
+/// Formats an offset in seconds as `+HH:MM`, or `+HH:MM:SS` if it has seconds.
+pub(crate) fn format_offset(utc_offset: i32) -> String {
+    let sign = if utc_offset < 0 { '-' } else { '+' };
+    let offset = utc_offset.unsigned_abs();
+    let r = format!("{}{:02}:{:02}", sign, offset / 3600, offset / 60 % 60);
+    if offset.is_multiple_of(60) { r } else { format!("{}:{:02}", r, offset % 60) }
+}
+
+fn zoneinfo_id(path: &str) -> String {
+    match path.find("zoneinfo/") {
+        Some(i) => path[i + "zoneinfo/".len()..].to_owned(),
+        None => path.to_owned(),
+    }
+}
+
+struct TzifHeader {
+    m_version: u8,
+    m_ut_indicator_count: usize,
+    m_std_indicator_count: usize,
+    m_leap_count: usize,
+    m_time_count: usize,
+    m_type_count: usize,
+    m_char_count: usize,
+}
+
+impl TzifHeader {
+    fn parse(bytes: &[u8], start: usize) -> Option<(TzifHeader, usize)> {
+        let header = bytes.get(start..start + 44)?;
+        if &header[0..4] != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| u32::from_be_bytes(header[20 + i * 4..24 + i * 4].try_into().unwrap()) as usize;
+        Some((TzifHeader {
+            m_version: if header[4] == 0 { b'1' } else { header[4] },
+            m_ut_indicator_count: count(0),
+            m_std_indicator_count: count(1),
+            m_leap_count: count(2),
+            m_time_count: count(3),
+            m_type_count: count(4),
+            m_char_count: count(5),
+        }, start + 44))
+    }
+
+    fn data_len(&self, time_size: usize) -> usize {
+        self.m_time_count * time_size
+            + self.m_time_count
+            + self.m_type_count * 6
+            + self.m_char_count
+            + self.m_leap_count * (time_size + 4)
+            + self.m_std_indicator_count
+            + self.m_ut_indicator_count
+    }
+}
+
+/// A POSIX `TZ` rule, such as `EST5EDT,M3.2.0,M11.1.0`, as found in the footer of TZif files.
+#[derive(Clone)]
+struct PosixTimeZone {
+    m_std: TimeZoneOffset,
+    m_dst: Option<PosixDaylightSaving>,
+}
+
+#[derive(Clone)]
+struct PosixDaylightSaving {
+    m_offset: TimeZoneOffset,
+    m_start: PosixDate,
+    /// Local standard time of the start, in seconds.
+    m_start_time: i64,
+    m_end: PosixDate,
+    /// Local daylight saving time of the end, in seconds.
+    m_end_time: i64,
+}
+
+#[derive(Copy, Clone)]
+enum PosixDate {
+    /// `Jn`: day of the year from 1 to 365, not counting February 29.
+    JulianWithoutLeapDay(i64),
+    /// `n`: day of the year from 0 to 365.
+    Julian(i64),
+    /// `Mm.w.d`: day `d` (0 is Sunday) of week `w` (5 is the last) of month `m`.
+    MonthWeekDay(u32, u32, u32),
+}
+
+impl PosixTimeZone {
+    fn parse(rule: &str) -> Option<PosixTimeZone> {
+        let mut parser = PosixParser { m_chars: rule.chars().collect(), m_index: 0 };
+        let std_abbreviation = parser.name()?;
+        let std_offset = i32::try_from(-parser.offset()?).ok()?;
+        let std = TimeZoneOffset { utc_offset: std_offset, is_dst: false, abbreviation: std_abbreviation };
+        if parser.at_end() {
+            return Some(PosixTimeZone { m_std: std, m_dst: None });
+        }
+        let dst_abbreviation = parser.name()?;
+        let dst_offset = if parser.peek().map(|c| c.is_ascii_digit() || c == '+' || c == '-').unwrap_or(false) {
+            i32::try_from(-parser.offset()?).ok()?
+        } else {
+            std_offset + 3600
+        };
+        parser.expect(',')?;
+        let start = parser.date()?;
+        let start_time = if parser.eat('/') { parser.offset()? } else { 7200 };
+        parser.expect(',')?;
+        let end = parser.date()?;
+        let end_time = if parser.eat('/') { parser.offset()? } else { 7200 };
+        if !parser.at_end() {
+            return None;
+        }
+        Some(PosixTimeZone {
+            m_std: std,
+            m_dst: Some(PosixDaylightSaving {
+                m_offset: TimeZoneOffset { utc_offset: dst_offset, is_dst: true, abbreviation: dst_abbreviation },
+                m_start: start,
+                m_start_time: start_time,
+                m_end: end,
+                m_end_time: end_time,
+            }),
+        })
+    }
+
+    fn offset_at(&self, timestamp: i64) -> TimeZoneOffset {
+        let Some(dst) = &self.m_dst else { return self.m_std.clone() };
+        let (year, _, _) = civil_from_days((timestamp + self.m_std.utc_offset as i64).div_euclid(86400));
+        let start = dst.m_start.day(year) * 86400 + dst.m_start_time - self.m_std.utc_offset as i64;
+        let end = dst.m_end.day(year) * 86400 + dst.m_end_time - dst.m_offset.utc_offset as i64;
+        let in_dst = if start < end {
+            timestamp >= start && timestamp < end
+        } else {
+            // southern hemisphere, with daylight saving time across the new year
+            timestamp < end || timestamp >= start
+        };
+        if in_dst { dst.m_offset.clone() } else { self.m_std.clone() }
+    }
+}
+
+impl PosixDate {
+    /// Day of the rule in a year, in days since the Unix epoch.
+    fn day(&self, year: i64) -> i64 {
+        let january_1 = days_from_civil(year, 1, 1);
+        match *self {
+            PosixDate::JulianWithoutLeapDay(n) => {
+                let is_leap = days_from_civil(year + 1, 1, 1) - january_1 == 366;
+                january_1 + n - 1 + if is_leap && n >= 60 { 1 } else { 0 }
+            },
+            PosixDate::Julian(n) => january_1 + n,
+            PosixDate::MonthWeekDay(month, week, weekday) => {
+                let first = days_from_civil(year, month, 1);
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (weekday as i64 - first_weekday).rem_euclid(7) + (week as i64 - 1) * 7;
+                let next_month = if month == 12 { days_from_civil(year + 1, 1, 1) } else { days_from_civil(year, month + 1, 1) };
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            },
+        }
+    }
+}
+
+struct PosixParser {
+    m_chars: Vec<char>,
+    m_index: usize,
+}
+
+impl PosixParser {
+    fn peek(&self) -> Option<char> {
+        self.m_chars.get(self.m_index).cloned()
+    }
+
+    fn at_end(&self) -> bool {
+        self.m_index >= self.m_chars.len()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.m_index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        if self.eat(c) { Some(()) } else { None }
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let mut r = String::new();
+        if self.eat('<') {
+            while let Some(c) = self.peek() {
+                self.m_index += 1;
+                if c == '>' {
+                    return Some(r);
+                }
+                r.push(c);
+            }
+            return None;
+        }
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphabetic()) {
+            r.push(c);
+            self.m_index += 1;
+        }
+        if r.len() < 3 { None } else { Some(r) }
+    }
+
+    fn number(&mut self) -> Option<i64> {
+        let start = self.m_index;
+        while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
+            self.m_index += 1;
+        }
+        self.m_chars[start..self.m_index].iter().collect::<String>().parse().ok()
+    }
+
+    fn number_in(&mut self, range: std::ops::RangeInclusive<i64>) -> Option<i64> {
+        self.number().filter(|n| range.contains(n))
+    }
+
+    /// `[+-]hh[:mm[:ss]]`, in seconds, positive west of Greenwich.
+    /// Hours range from 0 to 167, as extended by RFC 8536.
+    fn offset(&mut self) -> Option<i64> {
+        let sign = if self.eat('-') { -1 } else { self.eat('+'); 1 };
+        let mut r = self.number_in(0..=167)?.checked_mul(3600)?;
+        if self.eat(':') {
+            r = r.checked_add(self.number_in(0..=59)?.checked_mul(60)?)?;
+            if self.eat(':') {
+                r = r.checked_add(self.number_in(0..=59)?)?;
+            }
+        }
+        r.checked_mul(sign)
+    }
+
+    fn date(&mut self) -> Option<PosixDate> {
+        if self.eat('J') {
+            return Some(PosixDate::JulianWithoutLeapDay(self.number_in(1..=365)?));
+        }
+        if self.eat('M') {
+            let month = self.number_in(1..=12)? as u32;
+            self.expect('.')?;
+            let week = self.number_in(1..=5)? as u32;
+            self.expect('.')?;
+            let weekday = self.number_in(0..=6)? as u32;
+            return Some(PosixDate::MonthWeekDay(month, week, weekday));
+        }
+        Some(PosixDate::Julian(self.number_in(0..=365)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Disambiguation, ZonedDateTime};
+
+    /// Writes a version 2 TZif file.
+    fn tzif(transitions: &[(i64, u8)], types: &[(i32, bool, &str)], footer: &str) -> Vec<u8> {
+        let mut records: Vec<u8> = vec![];
+        let mut designations: Vec<u8> = vec![];
+        for (utc_offset, is_dst, abbreviation) in types {
+            records.extend(utc_offset.to_be_bytes());
+            records.push(*is_dst as u8);
+            records.push(designations.len() as u8);
+            designations.extend(abbreviation.bytes());
+            designations.push(0);
+        }
+        let mut r: Vec<u8> = vec![];
+        for time_size in [4, 8] {
+            r.extend(b"TZif2");
+            r.extend([0; 15]);
+            for count in [0, 0, 0, transitions.len(), types.len(), designations.len()] {
+                r.extend((count as u32).to_be_bytes());
+            }
+            for (time, _) in transitions {
+                if time_size == 4 { r.extend((*time as i32).to_be_bytes()) } else { r.extend(time.to_be_bytes()) }
+            }
+            r.extend(transitions.iter().map(|(_, t)| *t));
+            r.extend(&records);
+            r.extend(&designations);
+        }
+        r.extend(format!("\n{}\n", footer).bytes());
+        r
+    }
+
+    fn new_york() -> TimeZone {
+        TimeZone::from_tzif("Test/New_York", &tzif(&[], &[(-18000, false, "EST")], "EST5EDT,M3.2.0,M11.1.0")).unwrap()
+    }
+
+    #[test]
+    fn from_tzif() {
+        let tz = TimeZone::from_tzif("Test/Zone", &tzif(&[(0, 1), (100000, 0)], &[(3600, false, "AAA"), (7200, true, "BBB")], "CCC-3")).unwrap();
+        assert_eq!(tz.offset_at_timestamp(-1), TimeZoneOffset { utc_offset: 3600, is_dst: false, abbreviation: "AAA".to_owned() });
+        assert_eq!(tz.offset_at_timestamp(0), TimeZoneOffset { utc_offset: 7200, is_dst: true, abbreviation: "BBB".to_owned() });
+        assert_eq!(tz.offset_at_timestamp(99999).abbreviation, "BBB");
+        // the footer applies after the last transition
+        assert_eq!(tz.offset_at_timestamp(100000).abbreviation, "CCC");
+        assert_eq!(tz.offset_at_timestamp(100000).utc_offset, 10800);
+
+        assert!(TimeZone::from_tzif("Test/Zone", &tzif(&[(0, 2)], &[(0, false, "AAA")], "")).is_none());
+        assert!(TimeZone::from_tzif("Test/Zone", &tzif(&[], &[], "")).is_none());
+        assert!(TimeZone::from_tzif("Test/Zone", b"TZjf2").is_none());
+        let bytes = tzif(&[(0, 0)], &[(0, false, "AAA")], "");
+        assert!(TimeZone::from_tzif("Test/Zone", &bytes[..bytes.len() - 10]).is_none());
+    }
+
+    #[test]
+    fn register() {
+        assert!(TimeZone::register("Test/Registered", &tzif(&[], &[(19800, false, "IST")], "IST-5:30")));
+        assert!(!TimeZone::register("Test/Invalid", b"TZif"));
+        assert_eq!(TimeZone::named("Test/Registered").unwrap().offset_at_timestamp(0).utc_offset, 19800);
+        assert!(TimeZone::named("Test/Invalid").is_none());
+        assert!(TimeZone::named("../Test/Registered").is_none());
+        assert_eq!(TimeZone::named("Etc/UTC").unwrap(), TimeZone::utc());
+    }
+
+    #[test]
+    fn posix_rules() {
+        let tz = new_york();
+        // 2024-03-10 07:00 UTC and 2024-11-03 06:00 UTC
+        assert_eq!(tz.offset_at_timestamp(1710054000 - 1).abbreviation, "EST");
+        assert_eq!(tz.offset_at_timestamp(1710054000), TimeZoneOffset { utc_offset: -14400, is_dst: true, abbreviation: "EDT".to_owned() });
+        assert_eq!(tz.offset_at_timestamp(1730613600 - 1).abbreviation, "EDT");
+        assert_eq!(tz.offset_at_timestamp(1730613600).abbreviation, "EST");
+
+        // southern hemisphere, with daylight saving time across the new year
+        let rule = PosixTimeZone::parse("<-03>3<-02>,M10.3.0/0,M2.3.0/0").unwrap();
+        assert_eq!(rule.offset_at(1514808000).abbreviation, "-02");
+        assert_eq!(rule.offset_at(1527854400).abbreviation, "-03");
+
+        let rule = PosixTimeZone::parse("AAA0BBB-1,J60/0,300/0").unwrap();
+        // 2024-03-01 is day J60 of a leap year and day 60 counting from 0
+        assert!(!rule.offset_at(1709251200 - 1).is_dst);
+        assert!(rule.offset_at(1709251200).is_dst);
+        assert!(rule.offset_at(1709251200 + 239 * 86400).is_dst);
+        assert!(!rule.offset_at(1709251200 + 240 * 86400).is_dst);
+
+        assert_eq!(PosixTimeZone::parse("<+0530>-5:30").unwrap().m_std.utc_offset, 19800);
+        assert!(PosixTimeZone::parse("AB5").is_none());
+        assert!(PosixTimeZone::parse("EST5EDT,M13.1.0,M11.1.0").is_none());
+        assert!(PosixTimeZone::parse("EST5EDT,M3.2.0").is_none());
+
+        // malformed rules, such as from the `TZ` environment variable
+        assert!(PosixTimeZone::parse("AAA99999999999999999").is_none());
+        assert!(PosixTimeZone::parse("AAA9999999999999999").is_none());
+        assert!(PosixTimeZone::parse("AAA168").is_none());
+        assert_eq!(PosixTimeZone::parse("AAA-167:59:59").unwrap().m_std.utc_offset, 604799);
+        assert!(PosixTimeZone::parse("AAA5:60").is_none());
+        assert!(PosixTimeZone::parse("AAA5:30:60").is_none());
+        assert!(PosixTimeZone::parse("AAA5BBB,M3.2.0/99999999999999,M11.1.0").is_none());
+        assert!(PosixTimeZone::parse("AAA5BBB,J0,J365").is_none());
+        assert!(PosixTimeZone::parse("AAA5BBB,J1,99999999999999").is_none());
+        assert!(PosixTimeZone::parse("AAA5BBB,M4294967297.1.0,M11.1.0").is_none());
+        let tzif = tzif(&[], &[(0, false, "AAA")], "AAA9999999999999999");
+        assert!(TimeZone::from_tzif("Test/Malformed", &tzif).is_some_and(|tz| tz.offset_at_timestamp(0).utc_offset == 0));
+    }
+
+    #[test]
+    fn disambiguation() {
+        let tz = new_york();
+        let local = |day: u32, hour: u32, disambiguation: Disambiguation| {
+            ZonedDateTime::from_local(2024, if day == 10 { 3 } else { 11 }, day, hour, 30, 0, &tz, disambiguation)
+        };
+
+        // 02:30 is skipped on 2024-03-10
+        assert!(local(10, 2, Disambiguation::Reject).is_none());
+        let date = local(10, 2, Disambiguation::Compatible).unwrap();
+        assert_eq!((date.hour(), date.minute(), date.offset().is_dst), (3, 30, true));
+        assert_eq!(local(10, 2, Disambiguation::Later).unwrap().hour(), 3);
+        let date = local(10, 2, Disambiguation::Earlier).unwrap();
+        assert_eq!((date.hour(), date.offset().is_dst), (1, false));
+
+        // 01:30 is repeated on 2024-11-03
+        assert!(local(3, 1, Disambiguation::Reject).is_none());
+        assert_eq!(local(3, 1, Disambiguation::Compatible).unwrap().timestamp(), 1730611800);
+        assert_eq!(local(3, 1, Disambiguation::Earlier).unwrap().timestamp(), 1730611800);
+        let date = local(3, 1, Disambiguation::Later).unwrap();
+        assert_eq!((date.timestamp(), date.hour(), date.offset().is_dst), (1730615400, 1, false));
+
+        assert_eq!(local(3, 12, Disambiguation::Reject).unwrap().timestamp(), 1730655000);
+        assert!(ZonedDateTime::from_local(2023, 2, 29, 0, 0, 0, &tz, Disambiguation::Compatible).is_none());
+    }
+
+    #[test]
+    fn offset_format() {
+        assert_eq!(format_offset(-12600), "-03:30");
+        assert_eq!(format_offset(3661), "+01:01:01");
+        assert_eq!(TimeZone::fixed(-10800).id(), "UTC-03:00");
+        assert_eq!(TimeZone::fixed(0).id(), "UTC");
+    }
+}
@@ -0,0 +1,561 @@
+use lazy_regex::regex;
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+
+/// A message used in source code.
+#[derive(Clone, Debug)]
+pub struct ExtractedMessage {
+    pub id: String,
+    /// Variables given with `bundle_vars!` or `bundle_args!`.
+    pub variables: Vec<String>,
+    /// Literal variant arguments, such as `&"female"`, each selecting `<id>_<variant>`.
+    pub variants: Vec<String>,
+    /// Whether other arguments select variants that are only known at run time.
+    pub dynamic_variants: bool,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+/// Result of `extract_messages`.
+#[derive(Clone, Debug, Default)]
+pub struct MessageExtraction {
+    pub messages: Vec<ExtractedMessage>,
+    /// Identifiers added to the locale's resources.
+    pub added: Vec<String>,
+    /// Identifiers in the locale's resources that no source code uses,
+    /// directly or through `$ref(...)`.
+    pub orphaned: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum MessageExtractionError {
+    Io(std::io::Error),
+    InvalidJson(PathBuf),
+}
+
+impl From<std::io::Error> for MessageExtractionError {
+    fn from(error: std::io::Error) -> Self {
+        MessageExtractionError::Io(error)
+    }
+}
+
+/// Prefix marking the messages added by `extract_messages`.
+pub const TODO_MARKER: &str = "TODO";
+
+/// Scans the Rust sources under `src_dir` for `t(...)`, `tf(...)`, `B.get(...)` and
+/// `get_formatted(...)` calls with a literal identifier, and merges the identifiers
+/// into the JSON resources of the default locale at `locale_dir` (such as `res/lang/en-US`).
+///
+/// A missing message is added to the resource file whose path matches the longest prefix
+/// of its identifier, or to a new file named after its first component, with a value
+/// consisting of `TODO` followed by its variables, such as `"TODO $x"`. A message
+/// with literal variants is added once per variant. Existing messages are kept, and
+/// messages that are no longer used are reported in `MessageExtraction::orphaned`.
+pub fn extract_messages<S1: AsRef<str>, S2: AsRef<str>>(src_dir: S1, locale_dir: S2) -> Result<MessageExtraction, MessageExtractionError> {
+    let mut r = MessageExtraction::default();
+    let mut sources: Vec<PathBuf> = vec![];
+    find_files(Path::new(src_dir.as_ref()), "rs", &mut sources)?;
+    for path in sources {
+        let source = fs::read_to_string(&path)?;
+        r.messages.extend(scan_calls(&source, &path));
+    }
+
+    let locale_dir = Path::new(locale_dir.as_ref());
+    let mut files: Vec<ResourceFile> = vec![];
+    let mut paths: Vec<PathBuf> = vec![];
+    if locale_dir.is_dir() {
+        find_files(locale_dir, "json", &mut paths)?;
+    }
+    for path in paths {
+        let root: serde_json::Value = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|_| MessageExtractionError::InvalidJson(path.clone()))?;
+        let relative = path.strip_prefix(locale_dir).unwrap().with_extension("");
+        let prefix = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+        files.push(ResourceFile { m_path: path, m_prefix: prefix, m_root: root, m_modified: false });
+    }
+
+    for message in r.messages.iter() {
+        let ids: Vec<String> = if message.variants.is_empty() {
+            if message.dynamic_variants { vec![] } else { vec![message.id.clone()] }
+        } else {
+            message.variants.iter().map(|v| format!("{}_{}", message.id, v)).collect()
+        };
+        for id in ids {
+            if r.added.contains(&id) || files.iter().any(|f| f.get(&id).is_some()) {
+                continue;
+            }
+            let value = TODO_MARKER.to_owned() + &message.variables.iter().map(|v| format!(" ${}", v)).collect::<String>();
+            if insert_message(&mut files, locale_dir, &id, value) {
+                r.added.push(id);
+            }
+        }
+    }
+
+    for file in files.iter().filter(|f| f.m_modified) {
+        write_json(&file.m_path, &file.m_root)?;
+    }
+
+    r.orphaned = find_orphans(&files, &r.messages);
+    Ok(r)
+}
+
+/// A JSON resource file, whose messages are prefixed by its path
+/// relative to the locale directory.
+struct ResourceFile {
+    m_path: PathBuf,
+    m_prefix: Vec<String>,
+    m_root: serde_json::Value,
+    m_modified: bool,
+}
+
+impl ResourceFile {
+    /// Path of an identifier within the file, if the file's prefix matches it.
+    fn relative_id<'a>(&self, id: &'a [String]) -> Option<&'a [String]> {
+        if id.len() > self.m_prefix.len() && self.m_prefix.iter().zip(id.iter()).all(|(a, b)| a == b) {
+            Some(&id[self.m_prefix.len()..])
+        } else {
+            None
+        }
+    }
+
+    fn get(&self, id: &str) -> Option<&serde_json::Value> {
+        let id: Vec<String> = id.split(".").map(|s| s.to_owned()).collect();
+        let mut value = &self.m_root;
+        for name in self.relative_id(&id)? {
+            value = value.get(name)?;
+        }
+        Some(value)
+    }
+
+    fn message_ids(&self) -> Vec<(String, String)> {
+        let mut r: Vec<(String, String)> = vec![];
+        collect_messages(&self.m_root, &mut self.m_prefix.clone(), &mut r);
+        r
+    }
+}
+
+fn collect_messages(value: &serde_json::Value, path: &mut Vec<String>, output: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::String(s) => output.push((path.join("."), s.clone())),
+        serde_json::Value::Object(map) => {
+            // keys starting with `@` hold notes for translators
+            for (k, v) in map.iter().filter(|(k, _)| !k.starts_with('@')) {
+                path.push(k.clone());
+                collect_messages(v, path, output);
+                path.pop();
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Adds a message to the file with the longest matching prefix, or to a new file
+/// named after the identifier's first component. Returns `false` if the identifier
+/// conflicts with an existing message.
+fn insert_message(files: &mut Vec<ResourceFile>, locale_dir: &Path, id: &str, value: String) -> bool {
+    let id: Vec<String> = id.split(".").map(|s| s.to_owned()).collect();
+    let best = files.iter().enumerate()
+        .filter(|(_, f)| f.relative_id(&id).is_some())
+        .max_by_key(|(_, f)| f.m_prefix.len())
+        .map(|(i, _)| i);
+    let index = match best {
+        Some(i) => i,
+        None if id.len() > 1 => {
+            files.push(ResourceFile {
+                m_path: locale_dir.join(id[0].clone() + ".json"),
+                m_prefix: vec![id[0].clone()],
+                m_root: serde_json::json!({}),
+                m_modified: true,
+            });
+            files.len() - 1
+        },
+        None => return false,
+    };
+    let file = &mut files[index];
+    let names = id[file.m_prefix.len()..].to_vec();
+    let mut object = &mut file.m_root;
+    for name in names[..names.len() - 1].iter() {
+        let Some(map) = object.as_object_mut() else { return false };
+        object = map.entry(name.clone()).or_insert_with(|| serde_json::json!({}));
+    }
+    let Some(map) = object.as_object_mut() else { return false };
+    if map.contains_key(names.last().unwrap()) {
+        return false;
+    }
+    map.insert(names.last().unwrap().clone(), serde_json::Value::String(value));
+    file.m_modified = true;
+    true
+}
+
+/// Lists the messages that are neither used by source code nor referenced
+/// by a used message.
+fn find_orphans(files: &[ResourceFile], messages: &[ExtractedMessage]) -> Vec<String> {
+    let all: Vec<(String, String)> = files.iter().flat_map(|f| f.message_ids()).collect();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut used_prefixes: HashSet<String> = HashSet::new();
+    for message in messages {
+        used.insert(message.id.clone());
+        used.extend(message.variants.iter().map(|v| format!("{}_{}", message.id, v)));
+        if message.dynamic_variants {
+            used_prefixes.insert(message.id.clone() + "_");
+        }
+    }
+    let is_used = |id: &str, used: &HashSet<String>, used_prefixes: &HashSet<String>| used.contains(id) || used_prefixes.iter().any(|p| id.starts_with(p.as_str()));
+
+    // follow references until no more messages become used
+    let mut visited: HashSet<String> = HashSet::new();
+    loop {
+        let mut changed = false;
+        for (id, text) in all.iter() {
+            if visited.contains(id) || !is_used(id, &used, &used_prefixes) {
+                continue;
+            }
+            visited.insert(id.clone());
+            for m in regex!(r"\$ref\(\s*([^,)\s]+)\s*(?:,\s*([^)\s]+)\s*)?\)").captures_iter(text) {
+                let target = m.get(1).unwrap().as_str().to_owned();
+                match m.get(2).map(|v| v.as_str()) {
+                    Some(variant) if variant.starts_with('$') => { used_prefixes.insert(target.clone() + "_"); },
+                    Some(variant) => { used.insert(format!("{}_{}", target, variant)); },
+                    None => {},
+                }
+                used.insert(target);
+            }
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut r: Vec<String> = all.into_iter().map(|(id, _)| id).filter(|id| !is_used(id, &used, &used_prefixes)).collect();
+    r.sort();
+    r
+}
+
+/// Writes a JSON resource file, indented as the resources are usually written by hand.
+pub(crate) fn write_json(path: &Path, value: &serde_json::Value) -> std::io::Result<()> {
+    let mut bytes: Vec<u8> = vec![];
+    let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, serde_json::ser::PrettyFormatter::with_indent(b"    "));
+    serde::Serialize::serialize(value, &mut serializer).unwrap();
+    bytes.push(b'\n');
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bytes)
+}
+
+pub(crate) fn find_files(dir: &Path, extension: &str, output: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<std::io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            find_files(&path, extension, output)?;
+        } else if path.extension().map(|e| e == extension).unwrap_or(false) {
+            output.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+}
+
+/// Splits Rust source into identifiers, string literals and punctuation,
+/// skipping comments, with the line of each token.
+fn tokenize(source: &str) -> Vec<(Token, usize)> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut r: Vec<(Token, usize)> = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start_line = line;
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+            }
+        } else if c == 'r' && (chars.get(i + 1) == Some(&'"') || (chars.get(i + 1) == Some(&'#') && matches!(chars.get(i + 2), Some('"') | Some('#')))) {
+            // raw string
+            let mut hashes = 0;
+            i += 1;
+            while chars.get(i) == Some(&'#') {
+                hashes += 1;
+                i += 1;
+            }
+            i += 1;
+            let mut s = String::new();
+            while i < chars.len() && !(chars[i] == '"' && (1..=hashes).all(|k| chars.get(i + k) == Some(&'#'))) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                s.push(chars[i]);
+                i += 1;
+            }
+            i += 1 + hashes;
+            r.push((Token::Str(s), start_line));
+        } else if c == '"' {
+            i += 1;
+            let mut s = String::new();
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                if chars[i] == '\\' && i + 1 < chars.len() {
+                    i += 1;
+                    match chars[i] {
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        '0' => s.push('\0'),
+                        '\n' => {
+                            // line continuation
+                            line += 1;
+                            while i + 1 < chars.len() && chars[i + 1].is_whitespace() {
+                                i += 1;
+                            }
+                        },
+                        'u' => {
+                            let end = chars[i..].iter().position(|c| *c == '}').map(|p| i + p).unwrap_or(i);
+                            let hex: String = chars[i + 1..end].iter().filter(|c| c.is_ascii_hexdigit()).collect();
+                            s.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+                            i = end;
+                        },
+                        other => s.push(other),
+                    }
+                } else {
+                    s.push(chars[i]);
+                }
+                i += 1;
+            }
+            i += 1;
+            r.push((Token::Str(s), start_line));
+        } else if c == '\'' && (chars.get(i + 2) == Some(&'\'') || chars.get(i + 1) == Some(&'\\')) {
+            // character literal, as opposed to a lifetime
+            i += 1;
+            while i < chars.len() && chars[i] != '\'' {
+                i += if chars[i] == '\\' { 2 } else { 1 };
+            }
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            r.push((Token::Ident(chars[start..i].iter().collect()), start_line));
+        } else {
+            r.push((Token::Punct(c), start_line));
+            i += 1;
+        }
+    }
+    r
+}
+
+/// Splits the tokens of a delimited group, starting after its opening delimiter,
+/// at top-level commas. Returns the arguments and the index after the closing delimiter.
+fn split_arguments(tokens: &[(Token, usize)], mut i: usize) -> (Vec<Vec<Token>>, usize) {
+    let mut r: Vec<Vec<Token>> = vec![vec![]];
+    let mut depth = 0;
+    while i < tokens.len() {
+        let token = &tokens[i].0;
+        i += 1;
+        match token {
+            Token::Punct('(') | Token::Punct('[') | Token::Punct('{') => depth += 1,
+            Token::Punct(')') | Token::Punct(']') | Token::Punct('}') if depth == 0 => break,
+            Token::Punct(')') | Token::Punct(']') | Token::Punct('}') => depth -= 1,
+            Token::Punct(',') if depth == 0 => {
+                r.push(vec![]);
+                continue;
+            },
+            _ => {},
+        }
+        r.last_mut().unwrap().push(token.clone());
+    }
+    if r.last().map(|a| a.is_empty()).unwrap_or(false) {
+        r.pop();
+    }
+    (r, i)
+}
+
+fn scan_calls(source: &str, path: &Path) -> Vec<ExtractedMessage> {
+    let tokens = tokenize(source);
+    let mut r: Vec<ExtractedMessage> = vec![];
+    for i in 0..tokens.len() {
+        let Token::Ident(name) = &tokens[i].0 else { continue };
+        let previous = |k: usize| if i >= k { Some(&tokens[i - k].0) } else { None };
+        let is_call = match name.as_str() {
+            "t" | "tf" | "get_formatted" => previous(1) != Some(&Token::Ident("fn".to_owned())),
+            "get" => previous(1) == Some(&Token::Punct('.')) && previous(2) == Some(&Token::Ident("B".to_owned())),
+            _ => false,
+        };
+        if !is_call || tokens.get(i + 1).map(|t| &t.0) != Some(&Token::Punct('(')) {
+            continue;
+        }
+        let (arguments, _) = split_arguments(&tokens, i + 2);
+        let id = match arguments.first().map(|a| a.as_slice()) {
+            Some([Token::Str(id)]) | Some([Token::Punct('&'), Token::Str(id)]) => id.clone(),
+            _ => continue,
+        };
+        let mut message = ExtractedMessage {
+            id,
+            variables: vec![],
+            variants: vec![],
+            dynamic_variants: false,
+            file: path.to_owned(),
+            line: tokens[i].1,
+        };
+        if let Some(options) = arguments.get(1) {
+            scan_options(options, &mut message);
+        }
+        r.push(message);
+    }
+    r
+}
+
+/// Reads the options of a `tf(...)` or `get_formatted(...)` call, given as `vec![...]`.
+fn scan_options(tokens: &[Token], message: &mut ExtractedMessage) {
+    let [Token::Ident(v), Token::Punct('!'), Token::Punct('['), ..] = tokens else {
+        message.dynamic_variants = true;
+        return;
+    };
+    if v != "vec" {
+        message.dynamic_variants = true;
+        return;
+    }
+    let tokens: Vec<(Token, usize)> = tokens[3..].iter().map(|t| (t.clone(), 0)).collect();
+    let (options, _) = split_arguments(&tokens, 0);
+    for option in options {
+        let option: &[Token] = match option.as_slice() {
+            [Token::Punct('&'), rest @ ..] => rest,
+            rest => rest,
+        };
+        match option {
+            [Token::Str(variant)] => message.variants.push(variant.clone()),
+            [Token::Ident(m), Token::Punct('!'), Token::Punct('{'), rest @ ..] if m == "bundle_vars" || m == "bundle_args" => {
+                for k in 0..rest.len() {
+                    if let (Token::Str(name), Some(Token::Punct('=')), Some(Token::Punct('>'))) = (&rest[k], rest.get(k + 1), rest.get(k + 2)) {
+                        if !message.variables.contains(name) {
+                            message.variables.push(name.clone());
+                        }
+                    }
+                }
+            },
+            _ => message.dynamic_variants = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rialight-pi-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("lang/en/menu")).unwrap();
+        dir
+    }
+
+    fn read_json(path: PathBuf) -> serde_json::Value {
+        serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn scan() {
+        let source = r##"
+fn show(B: &LocaleBundle) {
+    t("_.title");
+    // t("_.line_comment");
+    /* tf("_.block_comment", vec![]) /* nested */ */
+    tf(r#"_.raw"#, vec![&bundle_vars!{ "name" => "x", "count" => 1 }]);
+    B.get("menu.play");
+    bundle.get_formatted("_.gender", vec![&"female", &bundle_vars!{"name" => n}]);
+    bundle.get_formatted("_.dynamic", vec![&gender]);
+    other.get("_.not_a_message");
+    let s = "t(\"_.in_string\")";
+    t(id);
+}
+fn t(id: &str) {}
+"##;
+        let messages = scan_calls(source, Path::new("main.rs"));
+        let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["_.title", "_.raw", "menu.play", "_.gender", "_.dynamic"]);
+        assert_eq!(messages[0].line, 3);
+        assert_eq!(messages[1].variables, ["name", "count"]);
+        assert_eq!(messages[3].variants, ["female"]);
+        assert_eq!(messages[3].variables, ["name"]);
+        assert!(!messages[3].dynamic_variants);
+        assert!(messages[4].dynamic_variants);
+    }
+
+    #[test]
+    fn extract() {
+        let dir = temp_dir("extract");
+        fs::write(dir.join("src/main.rs"), r#"
+fn main() {
+    t("_.title");
+    t("_.intro");
+    tf("menu.main.play", vec![&bundle_vars!{"n" => 1}]);
+    t("menu.quit");
+    bundle.get_formatted("_.gender", vec![&"female"]);
+}
+"#).unwrap();
+        fs::write(dir.join("lang/en/_.json"), r#"{
+            "title": "Title",
+            "old": "Old",
+            "intro": "$ref(_.shared, genitive) and $ref(_.plain)",
+            "shared_genitive": "Shared's",
+            "plain": "Plain",
+            "orphan_ref": "$ref(_.unused_ref_target)",
+            "unused_ref_target": "x"
+        }"#).unwrap();
+        fs::write(dir.join("lang/en/menu.json"), r#"{"back": "Back"}"#).unwrap();
+        fs::write(dir.join("lang/en/menu/main.json"), r#"{}"#).unwrap();
+        let (src, lang) = (dir.join("src"), dir.join("lang/en"));
+
+        let r = extract_messages(src.to_str().unwrap(), lang.to_str().unwrap()).unwrap();
+        assert_eq!(r.messages.len(), 5);
+        assert_eq!(r.added, ["menu.main.play", "menu.quit", "_.gender_female"]);
+        // messages referenced by used messages are not orphaned
+        assert_eq!(r.orphaned, ["_.old", "_.orphan_ref", "_.unused_ref_target", "menu.back"]);
+        // messages go to the file with the longest matching prefix
+        assert_eq!(read_json(lang.join("menu/main.json")), serde_json::json!({"play": "TODO $n"}));
+        assert_eq!(read_json(lang.join("menu.json")), serde_json::json!({"back": "Back", "quit": "TODO"}));
+        assert_eq!(read_json(lang.join("_.json"))["gender_female"], "TODO");
+        assert_eq!(read_json(lang.join("_.json"))["title"], "Title");
+
+        let r = extract_messages(src.to_str().unwrap(), lang.to_str().unwrap()).unwrap();
+        assert!(r.added.is_empty());
+
+        fs::write(dir.join("lang/en/menu.json"), "{").unwrap();
+        assert!(matches!(extract_messages(src.to_str().unwrap(), lang.to_str().unwrap()), Err(MessageExtractionError::InvalidJson(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,12 @@
 use lazy_regex::{regex_is_match};
+use rialight_localization::LocaleCatalog;
 use serde::{Serialize, Deserialize};
-use std::{fs, path::Path};
+use std::{fs, path::{Path, PathBuf}};
+
+mod extraction;
+pub use extraction::{extract_messages, ExtractedMessage, MessageExtraction, MessageExtractionError, TODO_MARKER};
+mod xliff;
+pub use xliff::{export_xliff, import_xliff, XliffError};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ProjectSettings {
@@ -61,4 +67,43 @@ use rialight::filesystem::{
     // rialight_debug_app_storage_dir
     drop(fs::remove_dir_all(out_dir.to_owned() + "/rialight_debug_app_storage_dir"));
     fs::create_dir_all(out_dir.to_owned() + "/rialight_debug_app_storage_dir").unwrap();
+}
+
+#[derive(Debug)]
+pub enum CatalogCompileError {
+    Io(std::io::Error),
+    InvalidJson(PathBuf),
+}
+
+impl From<std::io::Error> for CatalogCompileError {
+    fn from(error: std::io::Error) -> Self {
+        CatalogCompileError::Io(error)
+    }
+}
+
+/// Compiles every JSON resource under `src_dir` (such as `res/lang`) into a
+/// catalog at the same relative path under `out_dir`, with the `.rlcat` extension.
+/// For example, `res/lang/en-US/_.json` is compiled into `<out_dir>/en-US/_.rlcat`.
+///
+/// Returns the number of compiled catalogs.
+pub fn compile_locale_catalogs<S1: AsRef<str>, S2: AsRef<str>>(src_dir: S1, out_dir: S2) -> Result<usize, CatalogCompileError> {
+    compile_locale_catalogs_in(Path::new(src_dir.as_ref()), Path::new(out_dir.as_ref()))
+}
+
+fn compile_locale_catalogs_in(src_dir: &Path, out_dir: &Path) -> Result<usize, CatalogCompileError> {
+    let mut r = 0;
+    for entry in fs::read_dir(src_dir)? {
+        let path = entry?.path();
+        let out_path = out_dir.join(path.file_name().unwrap());
+        if path.is_dir() {
+            r += compile_locale_catalogs_in(&path, &out_path)?;
+        } else if path.extension().map(|e| e == "json").unwrap_or(false) {
+            let root: serde_json::Value = serde_json::from_slice(&fs::read(&path)?)
+                .map_err(|_| CatalogCompileError::InvalidJson(path.clone()))?;
+            fs::create_dir_all(out_dir)?;
+            fs::write(out_path.with_extension("rlcat"), LocaleCatalog::compile(&root))?;
+            r += 1;
+        }
+    }
+    Ok(r)
 }
\ No newline at end of file
@@ -0,0 +1,349 @@
+use lazy_regex::regex;
+use quick_xml::{escape::escape, events::{BytesStart, Event}, Reader};
+use rialight_localization::Language;
+use std::{collections::HashMap, fs, path::{Component, Path, PathBuf}};
+use super::MessageExtraction;
+use super::extraction::{find_files, write_json};
+
+#[derive(Debug)]
+pub enum XliffError {
+    Io(std::io::Error),
+    InvalidJson(PathBuf),
+    /// The document is not well-formed XLIFF 2.0, with a description of the problem.
+    InvalidXliff(String),
+}
+
+impl From<std::io::Error> for XliffError {
+    fn from(error: std::io::Error) -> Self {
+        XliffError::Io(error)
+    }
+}
+
+impl From<quick_xml::Error> for XliffError {
+    fn from(error: quick_xml::Error) -> Self {
+        XliffError::InvalidXliff(error.to_string())
+    }
+}
+
+/// Exports the messages of a locale for translation as an XLIFF 2.0 document.
+///
+/// `lang_dir` is the directory with a subdirectory per locale, such as `res/lang`.
+/// Each JSON file of `source_locale` becomes a `<file>`, each nested object a `<group>`
+/// and each message a `<unit>` identified by its message identifier, such as `_.greeting`.
+/// Existing messages of `target_locale` are exported as targets. Variables and
+/// references, such as `$name` and `$ref(id)`, and markup tags become `<ph>` inline codes, which
+/// translation tools protect from edits.
+///
+/// A sibling key starting with `@` is a note for translators, as in `"@greeting": "Shown on the title screen"`.
+/// If `extraction` is given, the source locations of each message are also exported as notes.
+pub fn export_xliff<S1: AsRef<str>, S2: AsRef<str>, S3: AsRef<str>>(lang_dir: S1, source_locale: S2, target_locale: S3, extraction: Option<&MessageExtraction>) -> Result<String, XliffError> {
+    let lang_dir = Path::new(lang_dir.as_ref());
+    let source_dir = lang_dir.join(source_locale.as_ref());
+    let target_dir = lang_dir.join(target_locale.as_ref());
+    let mut locations = HashMap::<String, Vec<String>>::new();
+    for message in extraction.iter().flat_map(|e| e.messages.iter()) {
+        locations.entry(message.id.clone()).or_default().push(format!("{}:{}", message.file.display(), message.line));
+    }
+
+    let mut r = String::new();
+    r.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    r.push_str(&format!("<xliff xmlns=\"urn:oasis:names:tc:xliff:document:2.0\" version=\"2.0\" srcLang=\"{}\" trgLang=\"{}\">\n", escape(source_locale.as_ref()), escape(target_locale.as_ref())));
+    let mut paths: Vec<PathBuf> = vec![];
+    find_files(&source_dir, "json", &mut paths)?;
+    for (i, path) in paths.iter().enumerate() {
+        let relative = path.strip_prefix(&source_dir).unwrap();
+        let original: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+        let source = read_json(path)?;
+        let target_path = target_dir.join(relative);
+        let target = if target_path.is_file() { read_json(&target_path)? } else { serde_json::json!({}) };
+        let prefix: Vec<String> = relative.with_extension("").components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+
+        r.push_str(&format!("  <file id=\"f{}\" original=\"{}\">\n", i + 1, escape(&original.join("/"))));
+        export_group(&mut r, &source, Some(&target), &prefix, &locations, 2);
+        r.push_str("  </file>\n");
+    }
+    r.push_str("</xliff>\n");
+    Ok(r)
+}
+
+fn export_group(output: &mut String, source: &serde_json::Value, target: Option<&serde_json::Value>, path: &[String], locations: &HashMap<String, Vec<String>>, depth: usize) {
+    let Some(map) = source.as_object() else { return };
+    let indent = "  ".repeat(depth);
+    for (key, value) in map.iter().filter(|(k, _)| !k.starts_with('@')) {
+        let mut path = path.to_vec();
+        path.push(key.clone());
+        let id = path.join(".");
+        let target = target.and_then(|t| t.get(key));
+        match value {
+            serde_json::Value::Object(_) => {
+                output.push_str(&format!("{}<group id=\"{}\">\n", indent, escape(&id)));
+                export_group(output, value, target, &path, locations, depth + 1);
+                output.push_str(&format!("{}</group>\n", indent));
+            },
+            serde_json::Value::String(text) => {
+                output.push_str(&format!("{}<unit id=\"{}\">\n", indent, escape(&id)));
+                let mut notes: Vec<(&str, String)> = vec![];
+                if let Some(note) = map.get(&format!("@{}", key)).and_then(|n| n.as_str()) {
+                    notes.push(("description", note.to_owned()));
+                }
+                for location in locations.get(&id).into_iter().flatten() {
+                    notes.push(("location", location.clone()));
+                }
+                if !notes.is_empty() {
+                    output.push_str(&format!("{}  <notes>\n", indent));
+                    for (category, note) in notes {
+                        output.push_str(&format!("{}    <note category=\"{}\">{}</note>\n", indent, category, escape(&note)));
+                    }
+                    output.push_str(&format!("{}  </notes>\n", indent));
+                }
+                let mut placeholders: Vec<String> = vec![];
+                let source = inline_codes(text, None, &mut placeholders);
+                match target.and_then(|t| t.as_str()) {
+                    Some(target) => {
+                        output.push_str(&format!("{}  <segment state=\"translated\">\n", indent));
+                        output.push_str(&format!("{}    <source>{}</source>\n", indent, source));
+                        output.push_str(&format!("{}    <target>{}</target>\n", indent, inline_codes(target, Some(&placeholders), &mut vec![])));
+                    },
+                    None => {
+                        output.push_str(&format!("{}  <segment state=\"initial\">\n", indent));
+                        output.push_str(&format!("{}    <source>{}</source>\n", indent, source));
+                    },
+                }
+                output.push_str(&format!("{}  </segment>\n", indent));
+                output.push_str(&format!("{}</unit>\n", indent));
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Escapes message text, turning placeholders into `<ph>` elements numbered in order
+/// of occurrence. For a target, `source` holds the placeholders of the source, so that
+/// the n-th occurrence of a placeholder takes the identifier of its n-th occurrence in the
+/// source, and placeholders missing from the source are numbered after the source's.
+fn inline_codes(text: &str, source: Option<&[String]>, placeholders: &mut Vec<String>) -> String {
+    let mut r = String::new();
+    let mut last = 0;
+    for m in regex!(r#"\$(\$|<|ref\([^)]*\)|[A-Za-z0-9]+(?::[a-z][a-z\-]*)*)|</?[A-Za-z][A-Za-z0-9\-]*(?:\s+[A-Za-z][A-Za-z0-9\-]*="[^"]*")*\s*/?>"#).find_iter(text) {
+        r.push_str(&escape(&text[last..m.start()]));
+        last = m.end();
+        if m.as_str() == "$$" || m.as_str() == "$<" {
+            r.push_str(&escape(&m.as_str()[1..]));
+            continue;
+        }
+        let code = m.as_str().to_owned();
+        let occurrence = placeholders.iter().filter(|p| **p == code).count();
+        placeholders.push(code.clone());
+        let id = match source {
+            None => placeholders.len(),
+            Some(source) => source.iter().enumerate()
+                .filter(|(_, p)| **p == code)
+                .nth(occurrence)
+                .map(|(i, _)| i + 1)
+                .unwrap_or(source.len() + placeholders.len()),
+        };
+        r.push_str(&format!("<ph id=\"{}\" equiv=\"{}\" disp=\"{}\"/>", id, escape(&code), escape(&code)));
+    }
+    r.push_str(&escape(&text[last..]));
+    r
+}
+
+/// Imports the translations of an XLIFF 2.0 document into the JSON resources of
+/// its target locale, at `<lang_dir>/<trgLang>/<original>`. Translated messages
+/// are merged into the existing files at the nested position given by their
+/// identifier, and units without a target are skipped.
+///
+/// `trgLang` must be a language tag, and `original` a relative path
+/// inside the locale directory, separated by `/`.
+///
+/// Returns the number of imported messages.
+pub fn import_xliff<S1: AsRef<str>, S2: AsRef<str>>(xliff: S1, lang_dir: S2) -> Result<usize, XliffError> {
+    let mut reader = Reader::from_str(xliff.as_ref());
+    let mut target_locale: Option<String> = None;
+    let mut files: Vec<(String, Vec<(String, String)>)> = vec![];
+    let mut unit: Option<String> = None;
+    let mut target: Option<String> = None;
+    loop {
+        match reader.read_event()? {
+            // inline codes are restored from their original text
+            Event::Start(e) | Event::Empty(e) if target.is_some() && e.local_name().as_ref() == b"ph" => {
+                let code = attribute(&e, "equiv")?.ok_or_else(|| XliffError::InvalidXliff("<ph> without equiv".to_owned()))?;
+                target.as_mut().unwrap().push_str(&code);
+            },
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"xliff" => target_locale = attribute(&e, "trgLang")?,
+                b"file" => {
+                    let original = attribute(&e, "original")?.ok_or_else(|| XliffError::InvalidXliff("<file> without original".to_owned()))?;
+                    files.push((original, vec![]));
+                },
+                b"unit" => unit = attribute(&e, "id")?,
+                b"target" if unit.is_some() => target = Some(String::new()),
+                _ => {},
+            },
+            Event::Text(e) if target.is_some() => {
+                target.as_mut().unwrap().push_str(&message_text(&e.unescape()?));
+            },
+            Event::CData(e) if target.is_some() => {
+                target.as_mut().unwrap().push_str(&message_text(&String::from_utf8_lossy(&e.into_inner())));
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"target" if target.is_some() => {
+                    let text = target.take().unwrap_or_default();
+                    let file = files.last_mut().ok_or_else(|| XliffError::InvalidXliff("<unit> outside <file>".to_owned()))?;
+                    file.1.push((unit.clone().unwrap(), text));
+                },
+                b"unit" => unit = None,
+                _ => {},
+            },
+            Event::Eof => break,
+            _ => {},
+        }
+    }
+    let target_locale = target_locale.ok_or_else(|| XliffError::InvalidXliff("missing trgLang".to_owned()))?;
+    if Language::parse(target_locale.as_str()).is_none() {
+        return Err(XliffError::InvalidXliff(format!("invalid trgLang {}", target_locale)));
+    }
+
+    let locale_dir = Path::new(lang_dir.as_ref()).join(&target_locale);
+    let mut r = 0;
+    for (original, messages) in files {
+        if messages.is_empty() {
+            continue;
+        }
+        let path = locale_dir.join(&original);
+        if !is_relative_file_path(&original) || !path.starts_with(&locale_dir) {
+            return Err(XliffError::InvalidXliff(format!("invalid original path {}", original)));
+        }
+        let mut root = if path.is_file() { read_json(&path)? } else { serde_json::json!({}) };
+        let prefix = original.strip_suffix(".json").unwrap_or(&original).replace('/', ".") + ".";
+        for (id, text) in messages {
+            let Some(relative) = id.strip_prefix(&prefix) else {
+                return Err(XliffError::InvalidXliff(format!("unit {} outside file {}", id, original)));
+            };
+            let names: Vec<&str> = relative.split(".").collect();
+            let mut object = &mut root;
+            for name in names[..names.len() - 1].iter() {
+                let map = object.as_object_mut().ok_or_else(|| XliffError::InvalidXliff(format!("unit {} conflicts with a message", id)))?;
+                object = map.entry(name.to_string()).or_insert_with(|| serde_json::json!({}));
+            }
+            let map = object.as_object_mut().ok_or_else(|| XliffError::InvalidXliff(format!("unit {} conflicts with a message", id)))?;
+            map.insert(names.last().unwrap().to_string(), serde_json::Value::String(text));
+            r += 1;
+        }
+        write_json(&path, &root)?;
+    }
+    Ok(r)
+}
+
+/// Determines whether a path separated by `/` only has file and directory names,
+/// so that it cannot leave the directory it is joined to.
+fn is_relative_file_path(path: &str) -> bool {
+    !path.contains('\\')
+        && path.split('/').all(|c| !c.is_empty() && c != "." && c != "..")
+        && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Escapes translated text for a message, where `$` and a `<` that would start a tag are escaped by `$`.
+fn message_text(text: &str) -> String {
+    let mut r = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' || (c == '<' && chars.peek().map(|n| n.is_ascii_alphabetic() || *n == '/').unwrap_or(false)) {
+            r.push('$');
+        }
+        r.push(c);
+    }
+    r
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, XliffError> {
+    match element.try_get_attribute(name)? {
+        Some(a) => Ok(Some(a.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+fn read_json(path: &Path) -> Result<serde_json::Value, XliffError> {
+    serde_json::from_slice(&fs::read(path)?).map_err(|_| XliffError::InvalidJson(path.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rialight-pi-xliff-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("en/menu")).unwrap();
+        fs::create_dir_all(dir.join("pt")).unwrap();
+        dir
+    }
+
+    fn xliff(trg_lang: &str, original: &str) -> String {
+        format!(r#"<xliff xmlns="urn:oasis:names:tc:xliff:document:2.0" version="2.0" srcLang="en" trgLang="{}">
+  <file id="f1" original="{}">
+    <unit id="_.title"><segment><source>Title</source><target>Título</target></segment></unit>
+  </file>
+</xliff>"#, trg_lang, original)
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = temp_dir("round-trip");
+        fs::write(dir.join("en/_.json"), r#"{
+            "greeting": "Hello, $name! Welcome to $ref(_.title)",
+            "@greeting": "Shown on the title screen",
+            "title": "Crystal <b>Forge</b>",
+            "price": "Costs $$5 $<b $name",
+            "untranslated": "Later"
+        }"#).unwrap();
+        fs::write(dir.join("en/menu/main.json"), r#"{"play": {"label": "Play"}}"#).unwrap();
+        fs::write(dir.join("pt/_.json"), r#"{
+            "greeting": "Boas-vindas a $ref(_.title), $name!",
+            "title": "<b>Forja</b> de Cristal",
+            "price": "$name: custa $$5 $<b"
+        }"#).unwrap();
+
+        let exported = export_xliff(dir.to_str().unwrap(), "en", "pt", None).unwrap();
+        assert!(exported.contains(r#"<note category="description">Shown on the title screen</note>"#));
+        // placeholders of the target keep the identifiers of the source
+        assert!(exported.contains(r#"<source>Hello, <ph id="1" equiv="$name" disp="$name"/>! Welcome to <ph id="2" equiv="$ref(_.title)" disp="$ref(_.title)"/></source>"#));
+        assert!(exported.contains(r#"<target>Boas-vindas a <ph id="2" equiv="$ref(_.title)" disp="$ref(_.title)"/>, <ph id="1" equiv="$name" disp="$name"/>!</target>"#));
+        assert!(exported.contains(r#"<target><ph id="1" equiv="&lt;b&gt;" disp="&lt;b&gt;"/>Forja<ph id="2" equiv="&lt;/b&gt;" disp="&lt;/b&gt;"/> de Cristal</target>"#));
+        assert!(exported.contains(r#"<source>Costs $5 &lt;b <ph id="1" equiv="$name" disp="$name"/></source>"#));
+        assert!(exported.contains(r#"<unit id="menu.main.play.label">"#));
+
+        let imported = temp_dir("round-trip-import");
+        assert_eq!(import_xliff(&exported, imported.to_str().unwrap()).unwrap(), 3);
+        let original: serde_json::Value = serde_json::from_slice(&fs::read(dir.join("pt/_.json")).unwrap()).unwrap();
+        assert_eq!(read_json(&imported.join("pt/_.json")).unwrap(), original);
+        assert!(!imported.join("pt/menu/main.json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&imported).unwrap();
+    }
+
+    #[test]
+    fn escaping() {
+        let dir = temp_dir("escaping");
+        let document = xliff("pt", "_.json").replace("<target>Título</target>", "<target>$5 &lt;b> <![CDATA[<i>]]> <ph id=\"1\" equiv=\"&lt;i&gt;\"/></target>");
+        assert_eq!(import_xliff(&document, dir.to_str().unwrap()).unwrap(), 1);
+        assert_eq!(read_json(&dir.join("pt/_.json")).unwrap(), serde_json::json!({"title": "$$5 $<b> $<i> <i>"}));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejected_paths() {
+        let dir = temp_dir("rejected-paths");
+        for original in ["../_.json", "menu/../../_.json", "/_.json", "menu\\..\\..\\_.json", "./_.json", "menu//_.json"] {
+            assert!(matches!(import_xliff(xliff("pt", original), dir.to_str().unwrap()), Err(XliffError::InvalidXliff(_))), "{}", original);
+        }
+        for trg_lang in ["..", "../en", "pt/../..", "not a tag", ""] {
+            assert!(matches!(import_xliff(xliff(trg_lang, "_.json"), dir.to_str().unwrap()), Err(XliffError::InvalidXliff(_))), "{}", trg_lang);
+        }
+        assert!(!dir.join("_.json").exists());
+        assert!(import_xliff(xliff("pt-BR", "_.json"), dir.to_str().unwrap()).is_ok());
+        assert!(dir.join("pt-BR/_.json").is_file());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}